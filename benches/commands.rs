@@ -0,0 +1,55 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mpv::Mpv;
+
+use std::time::Duration;
+
+fn commands(c: &mut Criterion) {
+    let mpv = Mpv::new().unwrap();
+    mpv.set_property("vo", "null").unwrap();
+    mpv.set_property("ao", "null").unwrap();
+
+    // Formatting the arguments into temporary `String`s, as `add_property` used to
+    c.bench_function("add_property/format", |b| {
+        b.iter(|| {
+            mpv.command("add", &["volume", &format!("{}", black_box(0isize))])
+                .unwrap()
+        })
+    });
+    c.bench_function("add_property", |b| {
+        b.iter(|| mpv.add_property("volume", black_box(0)).unwrap())
+    });
+    // No file is loaded, so mpv rejects the seeks after parsing them
+    c.bench_function("seek_absolute/format", |b| {
+        b.iter(|| {
+            let target = format!("{}", black_box(10f64));
+            mpv.command("seek", &[&target, "absolute"])
+        })
+    });
+    c.bench_function("seek_absolute", |b| {
+        b.iter(|| mpv.seek_absolute(black_box(Duration::from_secs(10))))
+    });
+    c.bench_function("command", |b| {
+        b.iter(|| mpv.command("add", &["volume", black_box("0")]).unwrap())
+    });
+}
+
+criterion_group!(benches, commands);
+criterion_main!(benches);
@@ -39,6 +39,8 @@ fn main() {
     mpv.set_property("volume", 15).unwrap();
     mpv.set_property("vo", "null").unwrap();
 
+    let mut ev_ctx = mpv.create_event_context().unwrap();
+
     crossbeam::scope(|scope| {
         scope.spawn(|_| {
             mpv.playlist_load_files(&[(&path, FileState::AppendPlay, None)])
@@ -53,8 +55,8 @@ fn main() {
             // Trigger `Event::EndFile`.
             mpv.playlist_next_force().unwrap();
         });
-        scope.spawn(|_| loop {
-            let ev = unsafe { mpv.wait_event(600.) };
+        scope.spawn(move |_| loop {
+            let ev = ev_ctx.wait_event(600.);
             if let Some(Ok(Event::EndFile(r))) = ev {
                 println!("Exiting! Reason: {:?}", r);
                 break;
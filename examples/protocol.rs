@@ -21,8 +21,6 @@ use mpv::*;
 use std::env;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::mem;
-use std::slice;
 use std::thread;
 use std::time::Duration;
 
@@ -40,60 +38,54 @@ fn main() {
             .expect("Expected path to local media as argument, found nil.")
     );
 
-    let protocol = unsafe {
-        Protocol::new(
-            "filereader".into(),
-            (),
-            open,
-            close,
-            read,
-            Some(seek),
-            Some(size),
-        )
-    };
+    // Every opened stream gets its own file handle
+    let protocol = Protocol::new(
+        "filereader".into(),
+        |uri: &str| -> OpenResult {
+            // Open the file, and strip the `filereader://` part
+            let file = File::open(&uri[13..])?;
+
+            println!("Opened file[{}], ready for orders o7", &uri[13..]);
+            Ok(Box::new(FileStream(file)))
+        },
+    );
 
     let mpv = Mpv::new().unwrap();
     mpv.set_property("volume", 25).unwrap();
 
-    let proto_ctx = mpv.create_protocol_context(1).unwrap();
-    proto_ctx.register(protocol).unwrap();
+    mpv.register_protocol(protocol).unwrap();
 
     mpv.playlist_load_files(&[(&path, FileState::AppendPlay, None)])
         .unwrap();
 
     thread::sleep(Duration::from_secs(10));
 
-    mpv.seek_forward(15.).unwrap();
+    mpv.seek_forward(Duration::from_secs(15)).unwrap();
 
     thread::sleep(Duration::from_secs(5));
 }
 
-fn open(_: &mut (), uri: &str) -> File {
-    // Open the file, and strip the `filereader://` part
-    let ret = File::open(&uri[13..]).unwrap();
-
-    println!("Opened file[{}], ready for orders o7", &uri[13..]);
-    ret
-}
-
-fn close(_: Box<File>) {
-    println!("Closing file, bye bye~~");
-}
+#[cfg(feature = "protocols")]
+struct FileStream(File);
 
-fn read(cookie: &mut File, buf: *mut i8, nbytes: u64) -> i64 {
-    unsafe {
-        let slice = slice::from_raw_parts_mut(buf, nbytes as _);
-        let forbidden_magic = mem::transmute::<&mut [i8], &mut [u8]>(slice);
+#[cfg(feature = "protocols")]
+impl mpv::protocol::Stream for FileStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf).map_err(|_| Error::Raw(ErrorCode::Generic))
+    }
 
-        cookie.read(forbidden_magic).unwrap() as _
+    fn seek(&mut self, offset: u64) -> Result<u64> {
+        println!("Seeking to byte {}", offset);
+        self.0
+            .seek(SeekFrom::Start(offset))
+            .map_err(|_| Error::Raw(ErrorCode::Generic))
     }
-}
 
-fn seek(cookie: &mut File, offset: i64) -> i64 {
-    println!("Seeking to byte {}", offset);
-    cookie.seek(SeekFrom::Start(offset as u64)).unwrap() as _
-}
+    fn size(&mut self) -> Option<u64> {
+        self.0.metadata().ok().map(|m| m.len())
+    }
 
-fn size(cookie: &mut File) -> i64 {
-    cookie.metadata().unwrap().len() as _
+    fn close(&mut self) {
+        println!("Closing file, bye bye~~");
+    }
 }
@@ -0,0 +1,100 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+#[cfg(all(not(test), not(feature = "sdl2")))]
+compile_error!("The feature `sdl2` needs to be enabled for this example`");
+
+#[cfg(feature = "sdl2")]
+fn main() {
+    use mpv::render::{OpenGlFbo, RenderContext, RenderOptions};
+    use mpv::*;
+    use sdl2::event::Event;
+    use std::{env, mem};
+
+    const GL_SCISSOR_TEST: u32 = 0x0C11;
+    const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+
+    let path = env::args()
+        .nth(1)
+        .expect("Expected path to media as argument, found nil.");
+
+    let sdl = sdl2::init().unwrap();
+    let video = sdl.video().unwrap();
+    let window = video
+        .window("mpv", 1280, 720)
+        .opengl()
+        .resizable()
+        .build()
+        .unwrap();
+    let gl = window.gl_create_context().unwrap();
+    video.gl_set_swap_interval(1).unwrap();
+
+    let mpv = Mpv::new().unwrap();
+    mpv.set_property("vo", "libmpv").unwrap();
+    let render_ctx = RenderContext::from_sdl_gl(&mpv, &window, &gl).unwrap();
+
+    // The UI is drawn with plain OpenGL calls, after mpv has rendered the video
+    let (gl_enable, gl_disable, gl_scissor, gl_clear_color, gl_clear): (
+        extern "system" fn(u32),
+        extern "system" fn(u32),
+        extern "system" fn(i32, i32, i32, i32),
+        extern "system" fn(f32, f32, f32, f32),
+        extern "system" fn(u32),
+    ) = unsafe {
+        (
+            mem::transmute(video.gl_get_proc_address("glEnable")),
+            mem::transmute(video.gl_get_proc_address("glDisable")),
+            mem::transmute(video.gl_get_proc_address("glScissor")),
+            mem::transmute(video.gl_get_proc_address("glClearColor")),
+            mem::transmute(video.gl_get_proc_address("glClear")),
+        )
+    };
+
+    mpv.playlist_load_files(&[(&path, FileState::AppendPlay, None)])
+        .unwrap();
+
+    let mut event_pump = sdl.event_pump().unwrap();
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            if let Event::Quit { .. } = event {
+                break 'main;
+            }
+        }
+
+        let (width, height) = window.drawable_size();
+        render_ctx
+            .render(
+                OpenGlFbo::new(0, width as _, height as _),
+                RenderOptions {
+                    flip_y: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // A bar at the bottom of the window, on top of the video
+        gl_enable(GL_SCISSOR_TEST);
+        gl_scissor(0, 0, width as _, 32);
+        gl_clear_color(0.1, 0.1, 0.1, 1.);
+        gl_clear(GL_COLOR_BUFFER_BIT);
+        gl_disable(GL_SCISSOR_TEST);
+
+        window.gl_swap_window();
+        render_ctx.report_swap();
+    }
+}
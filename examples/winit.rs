@@ -0,0 +1,100 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+#[cfg(all(not(test), not(feature = "winit")))]
+compile_error!("The feature `winit` needs to be enabled for this example`");
+
+#[cfg(feature = "winit")]
+#[derive(Clone, Copy, Debug)]
+struct Redraw;
+
+#[cfg(feature = "winit")]
+fn main() {
+    use glutin::{
+        event::{Event, WindowEvent},
+        event_loop::{ControlFlow, EventLoop},
+        window::WindowBuilder,
+        ContextBuilder,
+    };
+    use mpv::render::{RenderContext, RenderOptions};
+    use mpv::*;
+    use std::env;
+
+    let path = env::args()
+        .nth(1)
+        .expect("Expected path to media as argument, found nil.");
+
+    let event_loop = EventLoop::<Redraw>::with_user_event();
+    let windowed = ContextBuilder::new()
+        .with_vsync(true)
+        .build_windowed(WindowBuilder::new().with_title("mpv"), &event_loop)
+        .unwrap();
+    let windowed = unsafe { windowed.make_current() }
+        .map_err(|(_, e)| e)
+        .unwrap();
+
+    // The event loop never returns, so everything it uses has to live forever
+    let windowed: &_ = Box::leak(Box::new(windowed));
+    let mpv: &_ = Box::leak(Box::new(Mpv::new().unwrap()));
+    mpv.set_property("vo", "libmpv").unwrap();
+    // Forward input to mpv, so that its on-screen controller and key bindings work
+    #[cfg(feature = "winit_input")]
+    let mut input = {
+        mpv.set_property("osc", true).unwrap();
+        mpv.set_property("input-default-bindings", true).unwrap();
+        mpv::input::InputBridge::new()
+    };
+
+    let mut render_ctx = RenderContext::from_glutin(mpv, windowed).unwrap();
+    render_ctx.set_update_proxy(event_loop.create_proxy(), Redraw);
+
+    mpv.playlist_load_files(&[(&path, FileState::AppendPlay, None)])
+        .unwrap();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+        match event {
+            Event::UserEvent(Redraw) => {
+                if render_ctx.update().frame() {
+                    windowed.window().request_redraw();
+                }
+            }
+            Event::RedrawRequested(_) => {
+                render_ctx
+                    .render_window(windowed, RenderOptions::default())
+                    .unwrap();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                windowed.resize(size);
+                windowed.window().request_redraw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            #[cfg(feature = "winit_input")]
+            Event::WindowEvent { event, .. } => {
+                input.handle(mpv, &event).unwrap();
+            }
+            _ => {}
+        }
+    });
+}
@@ -27,6 +27,7 @@ pub const WINT_MIN: u32 = 0;
 pub const WINT_MAX: u32 = 65535;
 pub const MPV_ENABLE_DEPRECATED: u32 = 1;
 pub const MPV_RENDER_API_TYPE_OPENGL: &'static [u8; 7usize] = b"opengl\0";
+pub const MPV_RENDER_API_TYPE_SW: &'static [u8; 3usize] = b"sw\0";
 pub type va_list = *mut ::std::os::raw::c_char;
 extern "C" {
     pub fn __va_start(arg1: *mut *mut ::std::os::raw::c_char, ...);
@@ -2424,6 +2425,61 @@ pub const mpv_render_param_type_MPV_RENDER_PARAM_DRM_DISPLAY: mpv_render_param_t
 #[doc = " Valid for mpv_render_context_create()."]
 #[doc = " Type : struct mpv_opengl_drm_draw_surface_size*"]
 pub const mpv_render_param_type_MPV_RENDER_PARAM_DRM_DRAW_SURFACE_SIZE: mpv_render_param_type = 15;
+#[doc = " MPV_RENDER_API_TYPE_SW only: rendering target surface size, mandatory."]
+#[doc = " Valid for MPV_RENDER_API_TYPE_SW & mpv_render_context_render()."]
+#[doc = " Type: int[2] (e.g.: int s[2] = {w, h}; param.data = &s[0];)"]
+#[doc = ""]
+#[doc = " The video frame is transformed as with other VOs. Typically, this means"]
+#[doc = " the video gets scaled and black bars are added if the video size or"]
+#[doc = " aspect ratio mismatches with the target size."]
+pub const mpv_render_param_type_MPV_RENDER_PARAM_SW_SIZE: mpv_render_param_type = 17;
+#[doc = " MPV_RENDER_API_TYPE_SW only: rendering target surface pixel format,"]
+#[doc = " mandatory."]
+#[doc = " Valid for MPV_RENDER_API_TYPE_SW & mpv_render_context_render()."]
+#[doc = " Type: char* (e.g.: char *f = \"rgb0\"; param.data = f;)"]
+#[doc = ""]
+#[doc = " Valid values are:"]
+#[doc = "  \"rgb0\", \"bgr0\", \"0bgr\", \"0rgb\""]
+#[doc = "      4 bytes per pixel RGB, 1 byte (8 bit) per component, component bytes"]
+#[doc = "      with increasing address from left to right (e.g. \"rgb0\" has r at"]
+#[doc = "      address 0), the \"0\" component contains uninitialized garbage (often"]
+#[doc = "      the value 0, but not necessarily; the bad naming is inherited from"]
+#[doc = "      FFmpeg)"]
+#[doc = "      Pixel alignment size: 4 bytes"]
+#[doc = "  \"rgb24\""]
+#[doc = "      3 bytes per pixel RGB. This is strongly discouraged because it is"]
+#[doc = "      very slow."]
+#[doc = "      Pixel alignment size: 1 bytes"]
+#[doc = "  other"]
+#[doc = "      The API may accept other pixel formats, using mpv internal format"]
+#[doc = "      names, as long as it's internally marked as RGB, has exactly 1"]
+#[doc = "      plane, and is supported as conversion output. It is not a good idea"]
+#[doc = "      to rely on any of these. Their semantics and handling could change."]
+pub const mpv_render_param_type_MPV_RENDER_PARAM_SW_FORMAT: mpv_render_param_type = 18;
+#[doc = " MPV_RENDER_API_TYPE_SW only: rendering target surface bytes per line,"]
+#[doc = " mandatory."]
+#[doc = " Valid for MPV_RENDER_API_TYPE_SW & mpv_render_context_render()."]
+#[doc = " Type: size_t*"]
+#[doc = ""]
+#[doc = " This is the number of bytes between a pixel (x, y) and (x, y + 1) on the"]
+#[doc = " target surface. It must be a multiple of the pixel size, and have space"]
+#[doc = " for the surface width as specified by MPV_RENDER_PARAM_SW_SIZE."]
+#[doc = ""]
+#[doc = " Both stride and pointer value should be a multiple of 64 to facilitate"]
+#[doc = " fast SIMD operation. Lower alignment might trigger slower code paths,"]
+#[doc = " and in the worst case, will copy the entire target frame."]
+pub const mpv_render_param_type_MPV_RENDER_PARAM_SW_STRIDE: mpv_render_param_type = 19;
+#[doc = " MPV_RENDER_API_TYPE_SW only: rendering target surface pixel data pointer,"]
+#[doc = " mandatory."]
+#[doc = " Valid for MPV_RENDER_API_TYPE_SW & mpv_render_context_render()."]
+#[doc = " Type: void*"]
+#[doc = ""]
+#[doc = " This points to the first pixel at the left/top corner (0, 0). In"]
+#[doc = " particular, each line y starts at (pointer + stride * y). Upon rendering,"]
+#[doc = " all data between pointer and (pointer + stride * h) is overwritten."]
+#[doc = " Whether the padding between (w, bytes_per_pixel * w) is overwritten"]
+#[doc = " is unspecified."]
+pub const mpv_render_param_type_MPV_RENDER_PARAM_SW_POINTER: mpv_render_param_type = 20;
 #[doc = " Parameters for mpv_render_param (which is used in a few places such as"]
 #[doc = " mpv_render_context_create()."]
 #[doc = ""]
@@ -2830,6 +2886,20 @@ pub type mpv_stream_cb_size_fn =
 #[doc = "               returned from mpv_stream_cb_open_fn"]
 pub type mpv_stream_cb_close_fn =
     ::std::option::Option<unsafe extern "C" fn(cookie: *mut ::std::os::raw::c_void)>;
+#[doc = " Cancel callback used to implement a custom stream."]
+#[doc = ""]
+#[doc = " This callback is used to interrupt any current or future read and seek"]
+#[doc = " operations. It will be called from a separate thread than the demux"]
+#[doc = " thread, and should not block."]
+#[doc = ""]
+#[doc = " This callback can be NULL."]
+#[doc = ""]
+#[doc = " Available since API 1.106."]
+#[doc = ""]
+#[doc = " @param cookie opaque cookie identifying the stream,"]
+#[doc = "               returned from mpv_stream_cb_open_fn"]
+pub type mpv_stream_cb_cancel_fn =
+    ::std::option::Option<unsafe extern "C" fn(cookie: *mut ::std::os::raw::c_void)>;
 #[doc = " See mpv_stream_cb_open_ro_fn callback."]
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -2848,12 +2918,13 @@ pub struct mpv_stream_cb_info {
     pub seek_fn: mpv_stream_cb_seek_fn,
     pub size_fn: mpv_stream_cb_size_fn,
     pub close_fn: mpv_stream_cb_close_fn,
+    pub cancel_fn: mpv_stream_cb_cancel_fn,
 }
 #[test]
 fn bindgen_test_layout_mpv_stream_cb_info() {
     assert_eq!(
         ::std::mem::size_of::<mpv_stream_cb_info>(),
-        40usize,
+        48usize,
         concat!("Size of: ", stringify!(mpv_stream_cb_info))
     );
     assert_eq!(
@@ -2911,6 +2982,16 @@ fn bindgen_test_layout_mpv_stream_cb_info() {
             stringify!(close_fn)
         )
     );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<mpv_stream_cb_info>())).cancel_fn as *const _ as usize },
+        40usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mpv_stream_cb_info),
+            "::",
+            stringify!(cancel_fn)
+        )
+    );
 }
 #[doc = " Open callback used to implement a custom read-only (ro) stream. The user"]
 #[doc = " must set the callback fields in the passed info struct. The cookie field"]
@@ -31,7 +31,7 @@ fn properties() {
     mpv.set_property("sub-gauss", 0.6).unwrap();
 
     assert_eq!(0i64, mpv.get_property("volume").unwrap());
-    let vo: MpvStr = mpv.get_property("vo").unwrap();
+    let vo: MpvString = mpv.get_property("vo").unwrap();
     assert_eq!("null", &*vo);
     assert_eq!(true, mpv.get_property("ytdl").unwrap());
     let subg: f64 = mpv.get_property("sub-gauss").unwrap();
@@ -49,7 +49,7 @@ fn properties() {
 
     thread::sleep(Duration::from_millis(250));
 
-    let title: MpvStr = mpv.get_property("media-title").unwrap();
+    let title: MpvString = mpv.get_property("media-title").unwrap();
     assert!(
         "Rick Astley - Never Gonna Give You Up [HQ]" == &*title || "watch?v=DLzxrzFCyOs" == &*title
     );
@@ -76,13 +76,17 @@ fn events_simple() {
     use crate::events::simple::{Event, PropertyData};
 
     let mpv = Mpv::new().unwrap();
-    assert_eq!(Event::Idle, unsafe { mpv.wait_event(3.) }.unwrap().unwrap());
+    let mut ev_ctx = mpv.create_event_context().unwrap();
+    assert!(mpv.create_event_context().is_none());
+    assert_eq!(Event::Idle, ev_ctx.wait_event(3.).unwrap().unwrap());
 
     mpv.disable_deprecated_events().unwrap();
-    mpv.observe_property("volume", Format::Int64, 0).unwrap();
-    mpv.observe_property("media-title", Format::String, 1)
+    ev_ctx.observe_property("volume", Format::Int64, 0).unwrap();
+    ev_ctx
+        .observe_property("media-title", Format::String, 1)
         .unwrap();
-    mpv.observe_property("sub-gauss", Format::Double, 2)
+    ev_ctx
+        .observe_property("sub-gauss", Format::Double, 2)
         .unwrap();
 
     mpv.set_property("cache-initial", 1).unwrap();
@@ -94,7 +98,7 @@ fn events_simple() {
             change: PropertyData::Int64(0),
             reply_userdata: 0,
         },
-        unsafe { mpv.wait_event(3.) }.unwrap().unwrap()
+        ev_ctx.wait_event(3.).unwrap().unwrap()
     );
     assert_eq!(
         Event::PropertyChange {
@@ -102,7 +106,7 @@ fn events_simple() {
             change: PropertyData::Double(0.),
             reply_userdata: 2,
         },
-        unsafe { mpv.wait_event(3.) }.unwrap().unwrap()
+        ev_ctx.wait_event(3.).unwrap().unwrap()
     );
 
     mpv.set_property("ytdl", false).unwrap();
@@ -112,23 +116,20 @@ fn events_simple() {
         None,
     )])
     .unwrap();
-    assert_eq!(
-        Event::StartFile,
-        unsafe { mpv.wait_event(10.) }.unwrap().unwrap()
-    );
+    assert_eq!(Event::StartFile, ev_ctx.wait_event(10.).unwrap().unwrap());
     assert_eq!(
         Event::PropertyChange {
             name: "media-title",
             change: PropertyData::Str("watch?v=DLzxrzFCyOs"),
             reply_userdata: 1,
         },
-        unsafe { mpv.wait_event(10.) }.unwrap().unwrap()
+        ev_ctx.wait_event(10.).unwrap().unwrap()
     );
     assert_eq!(
-        Err(Error::Raw(mpv_error::UnknownFormat)),
-        unsafe { mpv.wait_event(20.) }.unwrap()
+        Err(Error::Raw(ErrorCode::UnknownFormat)),
+        ev_ctx.wait_event(20.).unwrap()
     );
-    assert_eq!(Event::Idle, unsafe { mpv.wait_event(4.) }.unwrap().unwrap());
+    assert_eq!(Event::Idle, ev_ctx.wait_event(4.).unwrap().unwrap());
 
     mpv.set_property("ytdl", true).unwrap();
     mpv.set_property("ytdl-format", "best[width<240]").unwrap();
@@ -138,16 +139,13 @@ fn events_simple() {
         None,
     )])
     .unwrap();
-    assert_eq!(
-        Event::StartFile,
-        unsafe { mpv.wait_event(10.) }.unwrap().unwrap()
-    );
+    assert_eq!(Event::StartFile, ev_ctx.wait_event(10.).unwrap().unwrap());
     // The order of events is unfortunately non-deterministic.
     for _ in 0..7 {
         // A possible order is:
         //      StartFile -> AudioReconfig -> FileLoaded -> AudioReconfig -> PropertyChange
         assert_eq_any!(
-            unsafe { mpv.wait_event(10.) }.unwrap().unwrap(),
+            ev_ctx.wait_event(10.).unwrap().unwrap(),
             Event::AudioReconfig,
             Event::VideoReconfig,
             Event::FileLoaded,
@@ -166,8 +164,1215 @@ fn events_simple() {
     }
     assert_eq!(
         Event::PlaybackRestart,
-        unsafe { mpv.wait_event(10.) }.unwrap().unwrap()
+        ev_ctx.wait_event(10.).unwrap().unwrap()
+    );
+
+    assert_eq!(None, ev_ctx.wait_event(0.));
+}
+
+#[cfg(feature = "protocols")]
+#[cfg_attr(feature = "protocols", test)]
+fn protocol_read() {
+    use crate::protocol::{close_wrapper, read_wrapper, stream_cookie, Stream};
+    use std::os::raw::{c_char, c_void};
+
+    struct Chunked(Vec<u8>, usize);
+    impl Stream for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(3).min(self.0.len() - self.1);
+            buf[..n].copy_from_slice(&self.0[self.1..self.1 + n]);
+            self.1 += n;
+            Ok(n)
+        }
+    }
+    struct Failing;
+    impl Stream for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Err(Error::Raw(ErrorCode::Generic))
+        }
+    }
+    struct Panicking;
+    impl Stream for Panicking {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            panic!("read panicked")
+        }
+    }
+    struct Overreporting;
+    impl Stream for Overreporting {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            Ok(buf.len() + 1)
+        }
+    }
+
+    fn cookie<S: Stream + 'static>(stream: S) -> *mut c_void {
+        stream_cookie(Box::new(stream))
+    }
+
+    let mut buf = [0u8; 8];
+    let ptr = buf.as_mut_ptr() as *mut c_char;
+    unsafe {
+        // Short reads are passed through, and `0` signals EOF
+        let chunked = cookie(Chunked(b"hello".to_vec(), 0));
+        assert_eq!(3, read_wrapper(chunked, ptr, 8));
+        assert_eq!(2, read_wrapper(chunked, ptr.add(3), 5));
+        assert_eq!(0, read_wrapper(chunked, ptr, 8));
+        // The handler only ever sees the buffer mpv provided
+        assert_eq!(0, read_wrapper(chunked, std::ptr::null_mut(), 0));
+        close_wrapper(chunked);
+
+        let failing = cookie(Failing);
+        assert_eq!(-1, read_wrapper(failing, ptr, 8));
+        close_wrapper(failing);
+
+        let panicking = cookie(Panicking);
+        assert_eq!(-1, read_wrapper(panicking, ptr, 8));
+        close_wrapper(panicking);
+
+        let overreporting = cookie(Overreporting);
+        assert_eq!(-1, read_wrapper(overreporting, ptr, 8));
+        close_wrapper(overreporting);
+    }
+    assert_eq!(b"hello", &buf[..5]);
+}
+
+#[cfg(feature = "protocols")]
+#[cfg_attr(feature = "protocols", test)]
+fn protocol_cancel() {
+    use crate::protocol::{cancel_wrapper, close_wrapper, read_wrapper, stream_cookie, Stream};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Cancellable(Arc<AtomicBool>);
+    impl Stream for Cancellable {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            if self.0.load(Ordering::SeqCst) {
+                Ok(0)
+            } else {
+                Err(Error::Raw(ErrorCode::Generic))
+            }
+        }
+        fn canceller(&mut self) -> Option<Box<dyn Fn() + Send + Sync>> {
+            let cancelled = self.0.clone();
+            Some(Box::new(move || cancelled.store(true, Ordering::SeqCst)))
+        }
+    }
+    struct Uncancellable;
+    impl Stream for Uncancellable {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    let mut buf = [0u8; 8];
+    let ptr = buf.as_mut_ptr() as *mut _;
+    unsafe {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancellable = stream_cookie(Box::new(Cancellable(cancelled.clone())));
+        assert_eq!(-1, read_wrapper(cancellable, ptr, 8));
+        let cookie = cancellable as usize;
+        std::thread::spawn(move || cancel_wrapper(cookie as *mut _))
+            .join()
+            .unwrap();
+        assert!(cancelled.load(Ordering::SeqCst));
+        assert_eq!(0, read_wrapper(cancellable, ptr, 8));
+        close_wrapper(cancellable);
+
+        // Streams without a canceller ignore cancellation
+        let uncancellable = stream_cookie(Box::new(Uncancellable));
+        cancel_wrapper(uncancellable);
+        assert_eq!(0, read_wrapper(uncancellable, ptr, 8));
+        close_wrapper(uncancellable);
+    }
+}
+
+#[test]
+fn error_display() {
+    assert_eq!(
+        "property not found",
+        Error::Raw(ErrorCode::PropertyNotFound).to_string()
+    );
+    let err = Error::Loadfiles {
+        index: 1,
+        error: std::sync::Arc::new(Error::Raw(ErrorCode::LoadingFailed)),
+    };
+    assert_eq!(
+        "Failed to load file at index 1: loading failed",
+        err.to_string()
+    );
+    let err = Error::Property {
+        name: "volume".to_owned(),
+        code: ErrorCode::PropertyFormat,
+    };
+    assert_eq!(
+        "Property 'volume': unsupported format for accessing property",
+        err.to_string()
+    );
+    assert_eq!(Some(ErrorCode::PropertyFormat), err.code());
+}
+
+#[test]
+fn error_code() {
+    assert_eq!(ErrorCode::Command, ErrorCode::from_raw(mpv_error::Command));
+    assert_eq!(mpv_error::Command, ErrorCode::Command.as_raw());
+    assert_eq!(ErrorCode::Unknown(-1000), ErrorCode::from_raw(-1000));
+    assert_eq!(-1000, ErrorCode::Unknown(-1000).as_raw());
+}
+
+#[test]
+fn error_send_sync() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<Error>();
+}
+
+#[test]
+fn subtitle_add_arguments() {
+    let mpv = Mpv::new().unwrap();
+    let path = "/nonexistent/sub.srt";
+    let title = Some("title");
+    let lang = Some("en");
+
+    for &add in &[Mpv::subtitle_add_select, Mpv::subtitle_add_auto] {
+        // The file doesn't exist, but the arguments are accepted
+        for &(t, l) in &[(None, None), (title, None), (title, lang)] {
+            let err = add(&mpv, path, t, l).unwrap_err();
+            assert_ne!(Error::InvalidArgument, err);
+        }
+        assert_eq!(Err(Error::InvalidArgument), add(&mpv, path, None, lang));
+    }
+}
+
+#[test]
+fn seek_arguments() {
+    use crate::wrapper::{seek_args, SeekMode};
+
+    let args =
+        |target, mode| seek_args(target, mode).map(|(target, mode)| format!("{} {}", target, mode));
+    assert_eq!(Ok("1.5 relative".to_owned()), args(1.5, SeekMode::Relative));
+    assert_eq!(Ok("-2 relative".to_owned()), args(-2., SeekMode::Relative));
+    assert_eq!(
+        Ok("-10 absolute".to_owned()),
+        args(-10., SeekMode::Absolute)
+    );
+    assert_eq!(
+        Ok("-100 relative-percent".to_owned()),
+        args(-150., SeekMode::RelativePercent)
+    );
+    assert_eq!(
+        Ok("50 absolute-percent".to_owned()),
+        args(50., SeekMode::AbsolutePercent)
+    );
+    assert_eq!(
+        Ok("100 absolute-percent".to_owned()),
+        args(250., SeekMode::AbsolutePercent)
+    );
+    assert_eq!(
+        Err(Error::InvalidArgument),
+        args(f64::NAN, SeekMode::Relative)
+    );
+    assert_eq!(
+        Err(Error::InvalidArgument),
+        args(f64::INFINITY, SeekMode::Absolute)
+    );
+}
+
+#[test]
+fn property_defaults() {
+    let mpv = Mpv::new().unwrap();
+    // Unavailable while no file is loaded
+    assert_eq!(Ok(0), mpv.get_property_or::<i64>("chapter", 0));
+    assert_eq!(
+        Ok(7),
+        mpv.get_property_with::<i64, _>("does-not-exist", || 7)
+    );
+    assert_eq!(
+        Some(ErrorCode::PropertyFormat),
+        mpv.get_property_or::<i64>("vo", 0).unwrap_err().code()
+    );
+}
+
+#[test]
+fn command_buf() {
+    use crate::wrapper::CommandBuf;
+    use std::fmt::Write;
+
+    let mut short = CommandBuf::new();
+    let property = "volume";
+    write!(short, "add {} {}", property, -5).unwrap();
+    assert_eq!("add volume -5", short.as_str());
+
+    // Spills onto the heap, keeping what was written before
+    let mut long = CommandBuf::new();
+    let path = "a".repeat(300);
+    write!(long, "loadfile {}", path).unwrap();
+    write!(long, " append").unwrap();
+    assert_eq!(format!("loadfile {} append", path), long.as_str());
+
+    assert!(CommandBuf::new().write_str("a\0b").is_err());
+}
+
+#[test]
+fn c_names() {
+    use crate::wrapper::with_c_name;
+    use std::ffi::CStr;
+
+    let name = |name: &str| {
+        with_c_name(name, |ptr| unsafe {
+            Ok(CStr::from_ptr(ptr).to_str().unwrap().to_owned())
+        })
+    };
+    assert_eq!(Ok("time-pos".to_owned()), name("time-pos"));
+    let long = "a".repeat(100);
+    assert_eq!(Ok(long.clone()), name(&long));
+    assert_eq!(Err(Error::Null), name("time\0pos"));
+}
+
+#[test]
+fn signed_durations() {
+    let half = Duration::from_millis(500);
+    assert_eq!(-0.5, SignedDuration::negative(half).as_secs_f64());
+    assert_eq!(
+        SignedDuration::negative(half),
+        SignedDuration::from_secs_f64(-0.5)
+    );
+    assert_eq!(
+        SignedDuration::from(half),
+        SignedDuration::from_secs_f64(0.5)
+    );
+    assert_eq!(
+        SignedDuration::default(),
+        SignedDuration::negative(Duration::from_secs(0))
+    );
+    assert_eq!(
+        SignedDuration::default(),
+        SignedDuration::from_secs_f64(f64::NAN)
+    );
+    assert!(!SignedDuration::from_secs_f64(-0.).is_negative());
+    let huge = SignedDuration::from_secs_f64(-1e30);
+    assert!(huge.is_negative());
+    assert_eq!(u64::MAX, huge.abs().as_secs());
+}
+
+#[test]
+fn geometry_spec() {
+    assert_eq!("", GeometrySpec::new().to_string());
+    assert_eq!(
+        "50%x50%+10+10",
+        GeometrySpec::new()
+            .size(Length::Percent(50), Length::Percent(50))
+            .position(
+                Offset::Start(Length::Pixels(10)),
+                Offset::Start(Length::Pixels(10))
+            )
+            .to_string()
+    );
+    assert_eq!(
+        "640-0-5%",
+        GeometrySpec::new()
+            .width(Length::Pixels(640))
+            .position(
+                Offset::End(Length::Pixels(0)),
+                Offset::End(Length::Percent(5))
+            )
+            .to_string()
+    );
+}
+
+#[cfg(feature = "events_simple")]
+#[cfg_attr(feature = "events_simple", test)]
+fn wait_for_event() {
+    let mpv = Mpv::new().unwrap();
+    mpv.set_property("idle", "yes").unwrap();
+    assert_eq!(Ok(true), mpv.wait_until_idle(Some(Duration::from_secs(1))));
+    assert_eq!(
+        Ok(false),
+        mpv.wait_for_event(events::EventId::FileLoaded, Some(Duration::from_millis(50)))
+    );
+
+    let _ctx = mpv.create_event_context().unwrap();
+    assert_eq!(
+        Err(Error::EventContextExists),
+        mpv.wait_for_playback_end(Some(Duration::from_millis(50)))
+    );
+}
+
+#[test]
+fn playlist_iter() {
+    let mpv = Mpv::new().unwrap();
+    mpv.set_property("idle", "yes").unwrap();
+    assert_eq!(0, mpv.playlist_iter().unwrap().count());
+
+    mpv.playlist_load_files(&[
+        ("a.mkv", FileState::Append, None),
+        ("b.mkv", FileState::Append, None),
+    ])
+    .unwrap();
+    let entries = mpv
+        .playlist_iter()
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        vec!["a.mkv", "b.mkv"],
+        entries.iter().map(|e| &*e.filename).collect::<Vec<_>>()
+    );
+    assert!(entries.iter().all(|e| e.title.is_none() && !e.playing));
+}
+
+#[test]
+fn equalizer() {
+    use crate::equalizer::Equalizer;
+
+    let mut eq = Equalizer::new("eq", &[62.5, 1000.]).unwrap();
+    eq.update_band(0, -3.).unwrap();
+    eq.update_band(1, 1.5).unwrap();
+    assert_eq!(Err(Error::InvalidArgument), eq.update_band(2, 0.));
+    assert_eq!(Err(Error::InvalidArgument), eq.update_band(0, f64::NAN));
+    assert_eq!(
+        "@eq:lavfi=[firequalizer=gain_entry='entry(62.5,-3);entry(1000,1.5)']",
+        eq.filter()
+    );
+
+    assert_eq!(10, Equalizer::ten_band("eq").unwrap().bands().len());
+    assert!(Equalizer::new("", &[]).is_err());
+    assert!(Equalizer::new("e q", &[]).is_err());
+    assert!(Equalizer::new("eq", &[0.]).is_err());
+}
+
+#[test]
+fn hwdec_names() {
+    assert_eq!("auto-safe", Hwdec::AutoSafe.as_str());
+    assert_eq!(Hwdec::VaapiCopy, Hwdec::from_name("vaapi-copy"));
+    assert_eq!(Hwdec::Other("drm".to_owned()), Hwdec::from_name("drm"));
+    assert_eq!("drm", Hwdec::from_name("drm").as_str());
+}
+
+#[test]
+fn key_value_lists() {
+    use crate::wrapper::push_key_value;
+
+    let mut list = String::new();
+    push_key_value(&mut list, "cookies", "/tmp/cookies.txt").unwrap();
+    push_key_value(&mut list, "yes-playlist", "").unwrap();
+    push_key_value(&mut list, "sub-lang", "en,de").unwrap();
+    assert_eq!(
+        "cookies=/tmp/cookies.txt,yes-playlist=,sub-lang=%5%en,de",
+        list
+    );
+    assert_eq!(
+        Err(Error::InvalidArgument),
+        push_key_value(&mut list, "a=b", "")
+    );
+}
+
+#[test]
+fn http_headers() {
+    use crate::wrapper::http_header_list;
+
+    let fields = vec![
+        ("Referer".to_owned(), "https://example.com".to_owned()),
+        ("Cookie".to_owned(), "a=1, b=2".to_owned()),
+    ];
+    assert_eq!(
+        Ok("Referer: https://example.com,Cookie: a=1\\, b=2".to_owned()),
+        http_header_list(&fields)
+    );
+    let invalid = vec![("Referer:".to_owned(), String::new())];
+    assert_eq!(Err(Error::InvalidArgument), http_header_list(&invalid));
+}
+
+#[test]
+fn colors() {
+    assert_eq!("#FF1020A0", Color::rgb(0x10, 0x20, 0xa0).to_string());
+    let transparent = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0x80,
+    };
+    assert_eq!("#80000000", transparent.to_string());
+}
+
+#[cfg(feature = "events_simple")]
+#[cfg_attr(feature = "events_simple", test)]
+fn eof_reached_event() {
+    use crate::events::simple::{Event, PropertyData};
+
+    let change = |name, eof| Event::PropertyChange {
+        name,
+        change: PropertyData::Flag(eof),
+        reply_userdata: 0,
+    };
+    assert!(change("eof-reached", true).is_eof_reached());
+    assert!(!change("eof-reached", false).is_eof_reached());
+    assert!(!change("pause", true).is_eof_reached());
+    assert!(!Event::Seek.is_eof_reached());
+}
+
+#[test]
+fn visualizer_filters() {
+    use crate::visualizer::{SpectrumMode, Visualizer};
+
+    let spectrum = Visualizer::Spectrum {
+        size: (1280, 720),
+        mode: SpectrumMode::Separate,
+    };
+    assert_eq!(
+        "[aid1]asplit[ao][viz];[viz]showspectrum=s=1280x720:mode=separate[vo]",
+        spectrum.filter()
+    );
+}
+
+#[test]
+fn chapter_lookup() {
+    use crate::wrapper::chapter_at;
+
+    let chapter = |index, secs| Chapter {
+        index,
+        title: None,
+        start: Duration::from_secs(secs),
+    };
+    let chapters = [chapter(0, 10), chapter(1, 60), chapter(2, 120)];
+    assert_eq!(None, chapter_at(&chapters, Duration::from_secs(5)));
+    assert_eq!(
+        Some(&chapters[0]),
+        chapter_at(&chapters, Duration::from_secs(10))
+    );
+    assert_eq!(
+        Some(&chapters[1]),
+        chapter_at(&chapters, Duration::from_secs(119))
+    );
+    assert_eq!(
+        Some(&chapters[2]),
+        chapter_at(&chapters, Duration::from_secs(600))
+    );
+}
+
+#[test]
+fn quoted_arguments() {
+    use crate::wrapper::CommandBuf;
+
+    let mut cmd = CommandBuf::new();
+    cmd.write_quoted(r#"say "hi" C:\x"#).unwrap();
+    assert_eq!(r#""say \"hi\" C:\\x""#, cmd.as_str());
+}
+
+// Polls `future` to completion on the current thread, parking it while pending
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    unsafe fn clone(thread: *const ()) -> RawWaker {
+        let thread = std::mem::ManuallyDrop::new(Box::from_raw(thread as *mut thread::Thread));
+        RawWaker::new(Box::into_raw(Box::new((**thread).clone())) as _, &VTABLE)
+    }
+    unsafe fn wake(thread: *const ()) {
+        Box::from_raw(thread as *mut thread::Thread).unpark();
+    }
+    unsafe fn wake_by_ref(thread: *const ()) {
+        (*(thread as *const thread::Thread)).unpark();
+    }
+    unsafe fn drop(thread: *const ()) {
+        let _ = Box::from_raw(thread as *mut thread::Thread);
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+    let thread = Box::into_raw(Box::new(thread::current())) as *const ();
+    let waker = unsafe { Waker::from_raw(RawWaker::new(thread, &VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "async", test)]
+fn async_requests() {
+    let mpv = Mpv::new().unwrap();
+    block_on(mpv.set_property_async("volume", 20)).unwrap();
+    assert_eq!(Ok(20), block_on(mpv.get_property_async::<i64>("volume")));
+    block_on(mpv.command_async("add", &["volume", "5"])).unwrap();
+    assert_eq!(
+        25.,
+        block_on(mpv.get_property_async::<f64>("volume")).unwrap()
+    );
+
+    match block_on(mpv.get_property_async::<String>("no-such-property")) {
+        Err(Error::Property { name, .. }) => assert_eq!("no-such-property", name),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    // Dropping a pending reply discards it, without affecting other requests
+    drop(mpv.get_property_async::<bool>("pause"));
+    assert_eq!(Ok(false), block_on(mpv.get_property_async::<bool>("pause")));
+}
+
+#[test]
+fn playlist_parsing() {
+    use crate::playlist::{self, PlaylistFormat};
+    use std::path::Path;
+
+    let base = Path::new("/music");
+    let m3u = "\u{feff}#EXTM3U\n#EXTINF:123,Artist - Song\nsong.mp3\n\nhttp://radio/stream\n";
+    assert_eq!(
+        PlaylistFormat::M3u,
+        PlaylistFormat::detect(Path::new("a.M3U8"), m3u)
+    );
+    let items = playlist::parse_str(m3u, PlaylistFormat::M3u, base);
+    assert_eq!(2, items.len());
+    assert_eq!("/music/song.mp3", items[0].path);
+    assert_eq!(Some("Artist - Song"), items[0].title.as_deref());
+    assert_eq!(Some(Duration::from_secs(123)), items[0].duration);
+    assert_eq!("force-media-title=%13%Artist - Song", items[0].options);
+    assert_eq!(
+        ("http://radio/stream", FileState::Append, None),
+        items[1].as_tuple(FileState::Append)
     );
 
-    assert_eq!(None, unsafe { mpv.wait_event(0.) });
+    let pls =
+        "[playlist]\nTitle2=Second\nFile2=/b.ogg\nFile1=a.ogg\nLength1=-1\nNumberOfEntries=2\n";
+    assert_eq!(
+        PlaylistFormat::Pls,
+        PlaylistFormat::detect(Path::new("list"), pls)
+    );
+    let items = playlist::parse_str(pls, PlaylistFormat::Pls, base);
+    let args = playlist::load_args(&items, FileState::Replace);
+    assert_eq!(
+        vec![
+            ("/music/a.ogg", FileState::Replace, None),
+            (
+                "/b.ogg",
+                FileState::Append,
+                Some("force-media-title=%6%Second")
+            ),
+        ],
+        args
+    );
+
+    let cue = "PERFORMER \"Band\"\nFILE \"album.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"One\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Two\"\n    INDEX 00 01:59:00\n    INDEX 01 02:00:15\n";
+    assert_eq!(
+        PlaylistFormat::Cue,
+        PlaylistFormat::detect(Path::new("album"), cue)
+    );
+    let items = playlist::parse_str(cue, PlaylistFormat::Cue, base);
+    assert_eq!(2, items.len());
+    assert_eq!("/music/album.flac", items[1].path);
+    assert_eq!(Some(Duration::from_millis(120_200)), items[0].duration);
+    assert_eq!(
+        "force-media-title=%3%One,start=0,end=120.2",
+        items[0].options
+    );
+    assert_eq!("force-media-title=%3%Two,start=120.2", items[1].options);
+}
+
+#[cfg(feature = "image")]
+#[cfg_attr(feature = "image", test)]
+fn frame_conversion() {
+    use crate::frame::to_rgba;
+
+    // Two rows of two pixels, padded to a stride of 12 bytes
+    let bgr0 = [
+        3, 2, 1, 0, 6, 5, 4, 0, 9, 9, 9, 9, 9, 8, 7, 0, 0, 0, 0, 0, 9, 9, 9, 9,
+    ];
+    let image = to_rgba(2, 2, 12, "bgr0", &bgr0).unwrap();
+    assert_eq!(
+        vec![1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 0, 0, 0, 255],
+        image.into_raw()
+    );
+
+    let rgba64: Vec<u8> = [0x1234u16, 0xff00, 0x00ff, 0xffff]
+        .iter()
+        .flat_map(|c| c.to_ne_bytes().to_vec())
+        .collect();
+    let image = to_rgba(1, 1, 8, "rgba64", &rgba64).unwrap();
+    assert_eq!(vec![0x12, 0xff, 0x00, 0xff], image.into_raw());
+
+    assert_eq!(
+        Err(Error::Raw(ErrorCode::PropertyFormat)),
+        to_rgba(2, 2, 4, "rgba", &[0; 16])
+    );
+    assert_eq!(
+        Err(Error::Raw(ErrorCode::Unsupported)),
+        to_rgba(1, 1, 3, "rgb24", &[0; 3])
+    );
+}
+
+#[test]
+fn media_sources() {
+    use std::path::PathBuf;
+
+    let uri = |source: MediaSource| source.to_uri().map(|uri| uri.into_owned());
+    assert_eq!(
+        Ok("a b.mkv".to_owned()),
+        uri(PathBuf::from("a b.mkv").into())
+    );
+    assert_eq!(Ok("./-.mkv".to_owned()), uri(PathBuf::from("-.mkv").into()));
+    assert_eq!(Ok("./a://b".to_owned()), uri(PathBuf::from("a://b").into()));
+    assert_eq!(Ok("/a://b".to_owned()), uri(PathBuf::from("/a://b").into()));
+    assert_eq!(
+        Ok("hex://00ff10".to_owned()),
+        uri(MediaSource::Memory(vec![0, 255, 16]))
+    );
+    assert_eq!(
+        Ok("my-proto://x?a=1&b=2".to_owned()),
+        uri(MediaSource::Protocol {
+            scheme: "my-proto".to_owned(),
+            payload: "x?a=1&b=2".to_owned(),
+        })
+    );
+    assert_eq!(
+        Err(Error::InvalidArgument),
+        uri(MediaSource::Protocol {
+            scheme: "1x".to_owned(),
+            payload: String::new(),
+        })
+    );
+}
+
+#[cfg(feature = "winit_input")]
+#[cfg_attr(feature = "winit_input", test)]
+#[allow(deprecated)]
+fn input_translation() {
+    use crate::input::{Input, InputBridge};
+    use glutin::dpi::PhysicalPosition;
+    use glutin::event::{
+        DeviceId, ElementState, KeyboardInput, ModifiersState, MouseScrollDelta, TouchPhase,
+        VirtualKeyCode, WindowEvent,
+    };
+
+    let key = |state, key| WindowEvent::KeyboardInput {
+        device_id: unsafe { DeviceId::dummy() },
+        input: KeyboardInput {
+            scancode: 0,
+            state,
+            virtual_keycode: Some(key),
+            modifiers: ModifiersState::empty(),
+        },
+        is_synthetic: false,
+    };
+    let mut bridge = InputBridge::new();
+
+    assert_eq!(
+        None,
+        bridge.translate(&key(ElementState::Pressed, VirtualKeyCode::A))
+    );
+    assert_eq!(
+        Some(Input::KeyPress("SHARP".to_owned())),
+        bridge.translate(&WindowEvent::ReceivedCharacter('#'))
+    );
+
+    bridge.translate(&WindowEvent::ModifiersChanged(
+        ModifiersState::CTRL | ModifiersState::SHIFT,
+    ));
+    assert_eq!(
+        Some(Input::KeyDown("Ctrl+A".to_owned())),
+        bridge.translate(&key(ElementState::Pressed, VirtualKeyCode::A))
+    );
+    assert_eq!(
+        None,
+        bridge.translate(&key(ElementState::Pressed, VirtualKeyCode::A))
+    );
+    assert_eq!(
+        Some(Input::KeyDown("Shift+Ctrl+LEFT".to_owned())),
+        bridge.translate(&key(ElementState::Pressed, VirtualKeyCode::Left))
+    );
+    bridge.translate(&WindowEvent::ModifiersChanged(ModifiersState::empty()));
+    assert_eq!(
+        Some(Input::KeyUp("Ctrl+A".to_owned())),
+        bridge.translate(&key(ElementState::Released, VirtualKeyCode::A))
+    );
+
+    let scroll = |y| WindowEvent::MouseWheel {
+        device_id: unsafe { DeviceId::dummy() },
+        delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(0., y)),
+        phase: TouchPhase::Moved,
+        modifiers: ModifiersState::empty(),
+    };
+    assert_eq!(None, bridge.translate(&scroll(30.)));
+    assert_eq!(
+        Some(Input::Wheel("WHEEL_UP".to_owned(), 1)),
+        bridge.translate(&scroll(30.))
+    );
+    let scroll_lines = |y| WindowEvent::MouseWheel {
+        device_id: unsafe { DeviceId::dummy() },
+        delta: MouseScrollDelta::LineDelta(0., y),
+        phase: TouchPhase::Moved,
+        modifiers: ModifiersState::empty(),
+    };
+    let mut lines = InputBridge::new();
+    assert_eq!(
+        Some(Input::Wheel("WHEEL_DOWN".to_owned(), 3)),
+        lines.translate(&scroll_lines(-3.))
+    );
+    assert_eq!(None, lines.translate(&scroll_lines(0.5)));
+    assert_eq!(
+        Some(Input::Wheel("WHEEL_UP".to_owned(), 1)),
+        lines.translate(&scroll_lines(0.5))
+    );
+    assert_eq!(
+        Some(Input::ReleaseAll),
+        bridge.translate(&WindowEvent::Focused(false))
+    );
+}
+
+#[test]
+fn load_dropped() {
+    use std::path::PathBuf;
+
+    let mpv = Mpv::new().unwrap();
+    mpv.set_property("idle", "yes").unwrap();
+    mpv.set_property("pause", true).unwrap();
+    let paths = [PathBuf::from("a \"b\".mkv"), PathBuf::from("-c.mkv")];
+    assert!(mpv
+        .load_dropped(&paths, DropMode::Append)
+        .iter()
+        .all(Result::is_ok));
+    let entries = mpv
+        .playlist_iter()
+        .unwrap()
+        .map(|entry| entry.map(|entry| entry.filename))
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(vec!["a \"b\".mkv", "./-c.mkv"], entries);
+
+    // Subtitles need a file to be added to
+    let results = mpv.load_dropped(&[PathBuf::from("a.srt")], DropMode::Replace);
+    assert_eq!(1, results.len());
+    assert!(results[0].is_err());
+}
+
+#[cfg(feature = "events_simple")]
+#[cfg_attr(feature = "events_simple", test)]
+fn drain_events() {
+    let mpv = Mpv::new().unwrap();
+    let mut ev_ctx = mpv.create_event_context().unwrap();
+    ev_ctx.observe_property("volume", Format::Int64, 1).unwrap();
+    mpv.set_property("volume", 10).unwrap();
+    mpv.set_property("volume", 20).unwrap();
+    thread::sleep(Duration::from_millis(250));
+
+    let mut events = Vec::new();
+    assert!(ev_ctx.drain_into(&mut events) > 0);
+    assert_eq!(
+        Some(&Ok(events::simple::OwnedEvent::PropertyChange {
+            name: "volume".to_owned(),
+            change: events::simple::OwnedPropertyData::Int64(20),
+            reply_userdata: 1,
+        })),
+        events.last()
+    );
+    assert!(ev_ctx.drain().is_empty());
+}
+
+#[cfg(feature = "events_simple")]
+#[cfg_attr(feature = "events_simple", test)]
+fn throttled_observation() {
+    use crate::events::simple::{Event, PropertyData};
+
+    let mpv = Mpv::new().unwrap();
+    let mut ev_ctx = mpv.create_event_context().unwrap();
+    ev_ctx
+        .observe_throttled("volume", Format::Int64, 1, Duration::from_millis(200))
+        .unwrap();
+    assert!(matches!(
+        ev_ctx.wait_event(1.),
+        Some(Ok(Event::PropertyChange { .. }))
+    ));
+    for volume in &[10, 20, 30] {
+        mpv.set_property("volume", *volume).unwrap();
+    }
+
+    // Only the latest of the changes within the interval is returned
+    assert_eq!(
+        Ok(Event::PropertyChange {
+            name: "volume",
+            change: PropertyData::Int64(30),
+            reply_userdata: 1,
+        }),
+        ev_ctx.wait_event(1.).unwrap()
+    );
+    assert!(ev_ctx.wait_event(0.3).is_none());
+}
+
+#[cfg(feature = "events_sync")]
+#[test]
+fn event_queue_overflow() {
+    use crate::events::sync::{Event, OverflowPolicy, Queue, Router};
+
+    let queued = |overflow| {
+        let router = Router::new(2, overflow);
+        let queue = Queue::new(2);
+        for event in &[Event::StartFile, Event::FileLoaded, Event::Seek] {
+            router.push(&queue, event.clone());
+        }
+        assert_eq!(1, queue.take_dropped());
+        assert_eq!(0, queue.take_dropped());
+        let mut events = Vec::new();
+        while let Some(event) = queue.pop() {
+            events.push(event);
+        }
+        events
+    };
+
+    let events = queued(OverflowPolicy::DropOldest);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0], Event::FileLoaded));
+    assert!(matches!(events[1], Event::Seek));
+
+    let events = queued(OverflowPolicy::DropNewest);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0], Event::StartFile));
+    assert!(matches!(events[1], Event::FileLoaded));
+}
+
+#[test]
+fn node_views() {
+    use crate::node::NodeRef;
+    use std::ffi::CString;
+
+    let title = CString::new("title").unwrap();
+    let value = CString::new("Big Buck Bunny").unwrap();
+    let id = CString::new("id").unwrap();
+    let mut values = [
+        mpv_sys::mpv_node {
+            u: mpv_sys::mpv_node__bindgen_ty_1 {
+                string: value.as_ptr() as *mut _,
+            },
+            format: mpv_sys::mpv_format_MPV_FORMAT_STRING,
+        },
+        mpv_sys::mpv_node {
+            u: mpv_sys::mpv_node__bindgen_ty_1 { int64: 3 },
+            format: mpv_sys::mpv_format_MPV_FORMAT_INT64,
+        },
+    ];
+    let mut keys = [title.as_ptr() as *mut _, id.as_ptr() as *mut _];
+    let mut list = mpv_sys::mpv_node_list {
+        num: 2,
+        values: values.as_mut_ptr(),
+        keys: keys.as_mut_ptr(),
+    };
+    let map = mpv_sys::mpv_node {
+        u: mpv_sys::mpv_node__bindgen_ty_1 { list: &mut list },
+        format: mpv_sys::mpv_format_MPV_FORMAT_NODE_MAP,
+    };
+
+    let node = unsafe { NodeRef::new(&map) };
+    assert!(node.is_map());
+    assert_eq!(node.len(), 2);
+    assert_eq!(
+        node.get("title").and_then(|n| n.as_str()),
+        Some("Big Buck Bunny")
+    );
+    assert_eq!(node.get("id").and_then(|n| n.as_i64()), Some(3));
+    assert_eq!(node.get("id").and_then(|n| n.as_f64()), Some(3.));
+    assert!(node.get("id").and_then(|n| n.as_str()).is_none());
+    assert!(node.get("lang").is_none());
+    assert!(node.index(2).is_none());
+    assert_eq!(
+        node.entries().map(|(k, _)| k).collect::<Vec<_>>(),
+        vec![Some("title"), Some("id")]
+    );
+
+    let mpv = Mpv::new().unwrap();
+    assert!(mpv
+        .with_property_node("track-list", |tracks| tracks.is_array())
+        .unwrap());
+    assert!(mpv.with_property_node("does-not-exist", |_| ()).is_err());
+}
+
+#[test]
+fn mock_player() {
+    use crate::player::{Call, MockMpv, Player, Value};
+
+    // Player logic as an application would write it
+    fn skip_if_muted<P: Player>(player: &P) -> Result<bool> {
+        if player.get_property::<bool>("mute")? {
+            player.playlist_next_force()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    let mock = MockMpv::new();
+    assert!(matches!(
+        skip_if_muted(&mock),
+        Err(Error::Property {
+            code: ErrorCode::PropertyNotFound,
+            ..
+        })
+    ));
+
+    mock.script_property("mute", true);
+    mock.script_property("volume", 50i64);
+    mock.take_calls();
+    assert!(skip_if_muted(&mock).unwrap());
+    assert_eq!(
+        mock.take_calls(),
+        vec![
+            Call::GetProperty("mute".into()),
+            Call::Command("playlist-next".into(), vec!["force".into()]),
+        ]
+    );
+
+    assert_eq!(mock.get_property::<f64>("volume").unwrap(), 50.);
+    assert!(mock.get_property::<String>("volume").is_err());
+    mock.set_property("volume", 20i64).unwrap();
+    assert_eq!(mock.get_property::<i64>("volume").unwrap(), 20);
+    mock.unpause().unwrap();
+    assert_eq!(
+        mock.calls().last(),
+        Some(&Call::SetProperty("pause".into(), Value::Flag(false)))
+    );
+
+    mock.fail_command("loadfile", Error::Raw(ErrorCode::LoadingFailed));
+    assert!(mock.load_file("a.mkv", FileState::Replace).is_err());
+    assert_eq!(
+        mock.calls().last(),
+        Some(&Call::Command(
+            "loadfile".into(),
+            vec!["a.mkv".into(), "replace".into()]
+        ))
+    );
+
+    let mpv = Mpv::new().unwrap();
+    mpv.set_value("volume", Value::Int64(10)).unwrap();
+    assert_eq!(Player::get_property::<i64>(&mpv, "volume").unwrap(), 10);
+}
+
+#[cfg(feature = "event_replay")]
+#[test]
+fn event_replay() {
+    use crate::events::replay::{EventRecorder, EventReplayer};
+    use crate::events::simple::{Event, OwnedEvent, OwnedPropertyData, PropertyData};
+
+    let mut recorder = EventRecorder::new();
+    recorder.record(&Ok(Event::PropertyChange {
+        name: "pause",
+        change: PropertyData::Flag(true),
+        reply_userdata: 1,
+    }));
+    recorder.record(&Err(Error::Raw(ErrorCode::LoadingFailed)));
+    // Not recorded, as there's no code to replay it with
+    recorder.record(&Err(Error::InvalidUtf8));
+    recorder.record_owned(&Ok(OwnedEvent::ClientMessage(vec![
+        "osc-visibility".into(),
+        "always".into(),
+    ])));
+    recorder.record_owned(&Ok(OwnedEvent::Shutdown));
+    let events = recorder.finish();
+    assert_eq!(events.len(), 4);
+    assert!(events.windows(2).all(|w| w[0].offset <= w[1].offset));
+    assert_eq!(
+        events[0].event,
+        Ok(OwnedEvent::PropertyChange {
+            name: "pause".into(),
+            change: OwnedPropertyData::Flag(true),
+            reply_userdata: 1,
+        })
+    );
+
+    let mut replayer = EventReplayer::new(events.clone());
+    assert_eq!(replayer.remaining(), 4);
+    assert_eq!(replayer.next_offset(), Some(events[0].offset));
+    assert_eq!(
+        replayer.next_event(),
+        Some(Ok(Event::PropertyChange {
+            name: "pause",
+            change: PropertyData::Flag(true),
+            reply_userdata: 1,
+        }))
+    );
+    assert_eq!(
+        replayer.next_event(),
+        Some(Err(Error::Raw(ErrorCode::LoadingFailed)))
+    );
+    match replayer.next_event() {
+        Some(Ok(Event::ClientMessage(messages))) => assert_eq!(
+            messages.collect::<Result<Vec<_>>>().unwrap(),
+            vec!["osc-visibility", "always"]
+        ),
+        ev => panic!("Unexpected event: {:?}", ev),
+    }
+    assert_eq!(replayer.next_event(), Some(Ok(Event::Shutdown)));
+    assert_eq!(replayer.next_event(), None);
+    assert_eq!(replayer.next_offset(), None);
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn headless_fixtures() {
+    use crate::test_util::{self, assert_property_eventually};
+
+    let sine = test_util::sine(Duration::from_millis(500));
+    assert_eq!(
+        sine.to_uri().unwrap(),
+        "av://lavfi:sine=frequency=440:duration=0.5"
+    );
+
+    let mpv = test_util::headless().unwrap();
+    mpv.playlist_load_files(&[(
+        &test_util::testsrc(Duration::from_secs(1)),
+        FileState::Replace,
+        None,
+    )])
+    .unwrap();
+    assert_property_eventually(&mpv, "width", 320i64, Duration::from_secs(5));
+    assert_property_eventually(&mpv, "idle-active", true, Duration::from_secs(5));
+    assert!(!test_util::property_eventually(
+        &mpv,
+        "width",
+        &320i64,
+        Duration::from_millis(50)
+    ));
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn labelled_filters() {
+    let mpv = crate::test_util::headless().unwrap();
+
+    assert_eq!(mpv.vf_add("", "vflip"), Err(Error::InvalidArgument));
+    assert_eq!(mpv.vf_add("a:b", "vflip"), Err(Error::InvalidArgument));
+    assert_eq!(mpv.vf_exists("a:b"), Err(Error::InvalidArgument));
+    assert!(!mpv.vf_exists("flip").unwrap());
+
+    mpv.vf_add("flip", "vflip").unwrap();
+    assert!(mpv.vf_exists("flip").unwrap());
+    // Disabled filters stay in the chain
+    mpv.vf_toggle("flip").unwrap();
+    assert!(mpv.vf_exists("flip").unwrap());
+    mpv.vf_remove("flip").unwrap();
+    assert!(!mpv.vf_exists("flip").unwrap());
+
+    assert!(mpv.vf_metadata("flip").is_err());
+}
+
+#[test]
+fn playlist_malformed() {
+    use crate::playlist::{self, PlaylistFormat};
+    use std::path::Path;
+
+    let base = Path::new("/music");
+    // Multi-byte characters where a cue command would end
+    for content in &["FILEÉ\n", "ÄÖÜ\n", "TITLÉ\n"] {
+        assert_eq!(
+            PlaylistFormat::M3u,
+            PlaylistFormat::detect(Path::new("list"), content)
+        );
+    }
+
+    let m3u = "#EXTINF:1e30,Huge\nhuge.mp3\n#EXTINF:inf,Infinite\ninf.mp3\n";
+    let items = playlist::parse_str(m3u, PlaylistFormat::M3u, base);
+    assert_eq!(2, items.len());
+    assert_eq!(Some("Huge"), items[0].title.as_deref());
+    assert_eq!(None, items[0].duration);
+    assert_eq!(None, items[1].duration);
+}
+
+#[test]
+fn ytdl_path_with_spaces() {
+    let mpv = Mpv::new().unwrap();
+    YtdlOptions::new()
+        .ytdl_path("/opt/my tools/yt-dlp")
+        .apply(&mpv)
+        .unwrap();
+    let opts: String = mpv.get_property("script-opts").unwrap();
+    assert_eq!("ytdl_hook-ytdl_path=/opt/my tools/yt-dlp", opts);
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn osd_dimensions_headless() {
+    use crate::test_util::{self, assert_property_eventually};
+
+    let mpv = test_util::headless().unwrap();
+    mpv.playlist_load_files(&[(
+        &test_util::testsrc(Duration::from_secs(1)),
+        FileState::Replace,
+        None,
+    )])
+    .unwrap();
+    assert_property_eventually(&mpv, "width", 320i64, Duration::from_secs(5));
+
+    let dims = mpv.osd_dimensions().unwrap();
+    assert!(dims.w >= 0 && dims.h >= 0);
+    assert!(dims.margins.left + dims.margins.right <= dims.w);
+    assert!(dims.margins.top + dims.margins.bottom <= dims.h);
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn mouse_injection() {
+    let mpv = crate::test_util::headless().unwrap();
+    mpv.set_mouse_pos(10, 20).unwrap();
+    // `mouse-pos` requires mpv 0.33
+    match mpv.mouse_pos() {
+        Err(ref e) if e.code() == Some(ErrorCode::PropertyNotFound) => {}
+        pos => assert_eq!(Some((10, 20)), pos.unwrap()),
+    }
+    mpv.mouse_click(30, 40, MouseButton::Left, false).unwrap();
+    mpv.mouse_click(30, 40, MouseButton::Right, true).unwrap();
+    match mpv.mouse_pos() {
+        Err(ref e) if e.code() == Some(ErrorCode::PropertyNotFound) => {}
+        pos => assert_eq!(Some((30, 40)), pos.unwrap()),
+    }
+
+    let far = i64::from(i32::MAX) + 1;
+    assert_eq!(Err(Error::InvalidArgument), mpv.set_mouse_pos(far, 0));
+    assert_eq!(
+        Err(Error::InvalidArgument),
+        mpv.mouse_click(0, -far - 1, MouseButton::Left, false)
+    );
+}
+
+#[test]
+fn stats_pages() {
+    let mpv = Mpv::new().unwrap();
+    assert_eq!(Err(Error::InvalidArgument), mpv.toggle_stats(Some(0)));
+    assert_eq!(Err(Error::InvalidArgument), mpv.toggle_stats(Some(10)));
+}
+
+#[cfg(feature = "render")]
+#[cfg_attr(feature = "render", test)]
+fn software_surface() {
+    use crate::render::{SoftwareFormat, SoftwareSurface};
+
+    let packed = SoftwareSurface::new(3, 2, SoftwareFormat::Rgb0);
+    assert_eq!(12, packed.stride);
+    assert_eq!(24, packed.buffer_len());
+    assert_eq!(Ok(()), packed.check(24));
+    assert_eq!(
+        Err(Error::Raw(ErrorCode::InvalidParameter)),
+        packed.check(23)
+    );
+
+    let rgb24 = SoftwareSurface::new(3, 2, SoftwareFormat::Rgb24);
+    assert_eq!(18, rgb24.buffer_len());
+    assert_eq!(Ok(()), rgb24.check(18));
+
+    // Rows have to fit the width, and start on a pixel boundary
+    let padded = SoftwareSurface {
+        stride: 64,
+        ..packed
+    };
+    assert_eq!(Ok(()), padded.check(128));
+    for stride in &[8, 14] {
+        let invalid = SoftwareSurface {
+            stride: *stride,
+            ..packed
+        };
+        assert_eq!(
+            Err(Error::Raw(ErrorCode::InvalidParameter)),
+            invalid.check(128)
+        );
+    }
+    let empty = SoftwareSurface::new(0, 2, SoftwareFormat::Bgr0);
+    assert_eq!(
+        Err(Error::Raw(ErrorCode::InvalidParameter)),
+        empty.check(128)
+    );
 }
@@ -17,20 +17,98 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
 mod errors {
-    use std::ffi::NulError;
+    use std::ffi::{CStr, NulError};
+    use std::fmt;
     use std::os::raw as ctype;
-    use std::rc::Rc;
     use std::str::Utf8Error;
+    use std::sync::Arc;
 
     #[allow(missing_docs)]
     pub type Result<T> = ::std::result::Result<T, Error>;
 
+    macro_rules! error_codes {
+        ($($(#[$attr:meta])* $name:ident = $raw:ident,)*) => {
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+            /// An error code reported by libmpv.
+            pub enum ErrorCode {
+                $($(#[$attr])* $name,)*
+                /// A code unknown to this crate, e.g. one added by a newer libmpv.
+                Unknown(crate::MpvError),
+            }
+
+            impl ErrorCode {
+                #[inline]
+                /// Convert a raw `mpv_error` code.
+                pub fn from_raw(raw: crate::MpvError) -> ErrorCode {
+                    match raw {
+                        $(mpv_sys::$raw => ErrorCode::$name,)*
+                        _ => ErrorCode::Unknown(raw),
+                    }
+                }
+
+                #[inline]
+                /// The raw `mpv_error` code.
+                pub fn as_raw(self) -> crate::MpvError {
+                    match self {
+                        $(ErrorCode::$name => mpv_sys::$raw,)*
+                        ErrorCode::Unknown(raw) => raw,
+                    }
+                }
+            }
+        };
+    }
+
+    error_codes! {
+        /// The event ringbuffer is full, so events are being dropped.
+        EventQueueFull = mpv_error_MPV_ERROR_EVENT_QUEUE_FULL,
+        /// Memory allocation failed.
+        NoMem = mpv_error_MPV_ERROR_NOMEM,
+        /// The core wasn't configured and initialized yet.
+        Uninitialized = mpv_error_MPV_ERROR_UNINITIALIZED,
+        /// A parameter was invalid.
+        InvalidParameter = mpv_error_MPV_ERROR_INVALID_PARAMETER,
+        /// The option doesn't exist.
+        OptionNotFound = mpv_error_MPV_ERROR_OPTION_NOT_FOUND,
+        /// The option value has an unsupported format.
+        OptionFormat = mpv_error_MPV_ERROR_OPTION_FORMAT,
+        /// Setting the option failed, e.g. because the value was out of range.
+        OptionError = mpv_error_MPV_ERROR_OPTION_ERROR,
+        /// The property doesn't exist.
+        PropertyNotFound = mpv_error_MPV_ERROR_PROPERTY_NOT_FOUND,
+        /// The property value has an unsupported format.
+        PropertyFormat = mpv_error_MPV_ERROR_PROPERTY_FORMAT,
+        /// The property exists, but isn't available, e.g. because no file is loaded.
+        PropertyUnavailable = mpv_error_MPV_ERROR_PROPERTY_UNAVAILABLE,
+        /// Setting or getting the property failed.
+        PropertyError = mpv_error_MPV_ERROR_PROPERTY_ERROR,
+        /// Running the command failed.
+        Command = mpv_error_MPV_ERROR_COMMAND,
+        /// Loading a file failed.
+        LoadingFailed = mpv_error_MPV_ERROR_LOADING_FAILED,
+        /// Initializing the audio output failed.
+        AoInitFailed = mpv_error_MPV_ERROR_AO_INIT_FAILED,
+        /// Initializing the video output failed.
+        VoInitFailed = mpv_error_MPV_ERROR_VO_INIT_FAILED,
+        /// There was no audio or video data to play.
+        NothingToPlay = mpv_error_MPV_ERROR_NOTHING_TO_PLAY,
+        /// The file format couldn't be determined, or the file was too broken to open.
+        UnknownFormat = mpv_error_MPV_ERROR_UNKNOWN_FORMAT,
+        /// Some system requirements aren't met.
+        Unsupported = mpv_error_MPV_ERROR_UNSUPPORTED,
+        /// The API function isn't implemented.
+        NotImplemented = mpv_error_MPV_ERROR_NOT_IMPLEMENTED,
+        /// Unspecified error.
+        Generic = mpv_error_MPV_ERROR_GENERIC,
+    }
+
     #[allow(missing_docs)]
     #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
     pub enum Error {
         Loadfiles {
             index: usize,
-            error: Rc<Error>,
+            error: Arc<Error>,
         },
         VersionMismatch {
             linked: ctype::c_ulong,
@@ -38,7 +116,88 @@ mod errors {
         },
         InvalidUtf8,
         Null,
-        Raw(crate::MpvError),
+        /// The core has shut down, and no further requests can be made.
+        Shutdown,
+        /// No OpenGL context is current on the calling thread.
+        NoCurrentContext,
+        /// The combination of arguments isn't valid, e.g. a subtitle language without a title.
+        InvalidArgument,
+        /// An `EventContext` exists, so the events can't be waited for elsewhere.
+        EventContextExists,
+        /// Getting or setting the property `name` failed.
+        Property {
+            name: String,
+            code: ErrorCode,
+        },
+        /// Running the command `cmd` failed.
+        Command {
+            cmd: String,
+            code: ErrorCode,
+        },
+        Raw(ErrorCode),
+    }
+
+    impl Error {
+        #[inline]
+        /// The error code reported by libmpv, if any, regardless of the context it was reported
+        /// in.
+        pub fn code(&self) -> Option<ErrorCode> {
+            match *self {
+                Error::Loadfiles { ref error, .. } => error.code(),
+                Error::Property { code, .. } | Error::Command { code, .. } | Error::Raw(code) => {
+                    Some(code)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                Error::Loadfiles { index, ref error } => {
+                    write!(f, "Failed to load file at index {}: {}", index, error)
+                }
+                Error::VersionMismatch { linked, loaded } => write!(
+                    f,
+                    "Linked against client API {}.{}, but loaded {}.{}",
+                    linked >> 16,
+                    linked & 0xffff,
+                    loaded >> 16,
+                    loaded & 0xffff
+                ),
+                Error::InvalidUtf8 => f.write_str("Invalid UTF-8"),
+                Error::Null => f.write_str("Unexpected null byte or pointer"),
+                Error::Shutdown => {
+                    f.write_str("The core has shut down, and no further requests can be made")
+                }
+                Error::NoCurrentContext => {
+                    f.write_str("No OpenGL context is current on the calling thread")
+                }
+                Error::InvalidArgument => f.write_str("Invalid combination of arguments"),
+                Error::EventContextExists => f.write_str("An event context already exists"),
+                Error::Property { ref name, code } => {
+                    write!(f, "Property '{}': {}", name, Error::Raw(code))
+                }
+                Error::Command { ref cmd, code } => {
+                    write!(f, "Command '{}': {}", cmd, Error::Raw(code))
+                }
+                Error::Raw(code) => {
+                    // Returns static strings, even for unknown codes
+                    let msg = unsafe { CStr::from_ptr(mpv_sys::mpv_error_string(code.as_raw())) };
+                    f.write_str(&msg.to_string_lossy())
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match *self {
+                Error::Loadfiles { ref error, .. } => Some(&**error),
+                _ => None,
+            }
+        }
     }
 
     impl From<NulError> for Error {
@@ -53,7 +212,7 @@ mod errors {
     }
     impl From<crate::MpvError> for Error {
         fn from(other: crate::MpvError) -> Error {
-            Error::Raw(other)
+            Error::Raw(ErrorCode::from_raw(other))
         }
     }
 }
@@ -90,33 +249,232 @@ macro_rules! mpv_cstr_to_str {
     };
 }
 
+/// Audio equalizer
+pub mod equalizer;
 /// Event handling
 pub mod events;
+#[cfg(feature = "image")]
+/// Grabbing frames as images
+pub mod frame;
+#[cfg(feature = "winit_input")]
+/// Forwarding window input to mpv
+pub mod input;
+/// Borrowed views of mpv nodes
+pub mod node;
+/// A trait for the player functions, and a mock implementing it
+pub mod player;
+/// Playlist file parsing
+pub mod playlist;
 #[cfg(feature = "protocols")]
 /// Custom protocols
 pub mod protocol;
 #[cfg(feature = "render")]
 /// Custom rendering
 pub mod render;
+#[cfg(feature = "test_util")]
+/// Headless cores and synthetic media for tests
+pub mod test_util;
+/// Audio visualizations
+pub mod visualizer;
 
 use super::*;
 
-#[cfg(feature = "events_sync")]
+#[cfg(any(feature = "events_sync", feature = "protocols"))]
 use parking_lot::{self, Mutex};
 
-use std::ffi::CString;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::fmt::{self, Write};
 use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::os::raw as ctype;
+use std::path::{Path, PathBuf};
 use std::ptr::{self, NonNull};
-#[cfg(feature = "protocols")]
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 fn mpv_err<T>(ret: T, err: ctype::c_int) -> Result<T> {
     if err == 0 {
         Ok(ret)
     } else {
-        Err(Error::Raw(err))
+        Err(Error::Raw(ErrorCode::from_raw(err)))
+    }
+}
+
+// mpv can report slightly negative positions, which `Duration` can't represent. Values too
+// large for a `Duration`, which `Duration::from_secs_f64` panics on, saturate.
+fn secs_to_duration(secs: f64) -> Duration {
+    if !secs.is_finite() || secs <= 0. {
+        Duration::from_secs(0)
+    } else if secs >= u64::MAX as f64 {
+        Duration::new(u64::MAX, 999_999_999)
+    } else {
+        Duration::from_secs_f64(secs)
+    }
+}
+
+// Returns `Error::InvalidArgument` if `value` isn't within `min..=max`
+fn check_range(value: f64, min: f64, max: f64) -> Result<f64> {
+    if value >= min && value <= max {
+        Ok(value)
+    } else {
+        Err(Error::InvalidArgument)
+    }
+}
+
+// Returns `Error::InvalidArgument` unless `x` and `y` fit into the C ints of the `mouse` command
+fn check_mouse_pos(x: i64, y: i64) -> Result<()> {
+    let fits = |v: i64| v >= i64::from(i32::MIN) && v <= i64::from(i32::MAX);
+    if fits(x) && fits(y) {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument)
+    }
+}
+
+// Returns `Error::InvalidArgument` unless `label` can label a filter, without being mistaken for
+// the filter or its options
+fn check_label(label: &str) -> Result<&str> {
+    if !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(label)
+    } else {
+        Err(Error::InvalidArgument)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// How the target of the `seek` command is interpreted
+pub(crate) enum SeekMode {
+    Relative,
+    Absolute,
+    RelativePercent,
+    AbsolutePercent,
+}
+
+// The arguments of the `seek` command, with percentages clamped to their valid range
+pub(crate) fn seek_args(target: f64, mode: SeekMode) -> Result<(f64, &'static str)> {
+    if !target.is_finite() {
+        return Err(Error::InvalidArgument);
+    }
+    let (target, mode) = match mode {
+        SeekMode::Relative => (target, "relative"),
+        SeekMode::Absolute => (target, "absolute"),
+        SeekMode::RelativePercent => (target.clamp(-100., 100.), "relative-percent"),
+        SeekMode::AbsolutePercent => (target.clamp(0., 100.), "absolute-percent"),
+    };
+    Ok((target, mode))
+}
+
+// Pass `name` to `f` as a C string. Property names are converted on every access, e.g. when
+// polling `time-pos`, so short ones are copied to the stack instead of allocating.
+pub(crate) fn with_c_name<T, F>(name: &str, f: F) -> Result<T>
+where
+    F: FnOnce(*const ctype::c_char) -> Result<T>,
+{
+    let mut stack = [0u8; 64];
+    if name.len() < stack.len() && !name.as_bytes().contains(&0) {
+        stack[..name.len()].copy_from_slice(name.as_bytes());
+        f(stack.as_ptr() as _)
+    } else {
+        let name = CString::new(name)?;
+        f(name.as_ptr())
+    }
+}
+
+// Attach the name of the property to an error reported by libmpv
+fn property_err(name: &str, err: Error) -> Error {
+    match err {
+        Error::Raw(code) => Error::Property {
+            name: name.to_owned(),
+            code,
+        },
+        err => err,
+    }
+}
+
+// Builds a nul-terminated command string on the stack, only allocating for long commands
+pub(crate) struct CommandBuf {
+    stack: [u8; 256],
+    len: usize,
+    heap: Vec<u8>,
+}
+
+impl CommandBuf {
+    pub(crate) fn new() -> CommandBuf {
+        CommandBuf {
+            stack: [0; 256],
+            len: 0,
+            heap: Vec::new(),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        let bytes = if self.heap.is_empty() {
+            &self.stack[..self.len]
+        } else {
+            &self.heap[..self.heap.len() - 1]
+        };
+        // Only `str`s are written
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    // Write `arg` as a double-quoted argument, escaping quotes and backslashes
+    pub(crate) fn write_quoted(&mut self, arg: &str) -> fmt::Result {
+        self.write_char('"')?;
+        for c in arg.chars() {
+            if c == '"' || c == '\\' {
+                self.write_char('\\')?;
+            }
+            self.write_char(c)?;
+        }
+        self.write_char('"')
+    }
+
+    fn as_ptr(&self) -> *const ctype::c_char {
+        if self.heap.is_empty() {
+            self.stack.as_ptr() as _
+        } else {
+            self.heap.as_ptr() as _
+        }
+    }
+}
+
+impl fmt::Write for CommandBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.as_bytes().contains(&0) {
+            return Err(fmt::Error);
+        }
+        // Keep room for the terminating nul
+        if self.heap.is_empty() && self.len + s.len() < self.stack.len() {
+            self.stack[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+            self.len += s.len();
+        } else {
+            if self.heap.is_empty() {
+                self.heap.reserve(self.len + s.len() + 1);
+                self.heap.extend_from_slice(&self.stack[..self.len]);
+            } else {
+                self.heap.pop();
+            }
+            self.heap.extend_from_slice(s.as_bytes());
+            self.heap.push(0);
+        }
+        Ok(())
+    }
+}
+
+// Attach the command string to an error reported by libmpv
+fn command_err(cmd: &str, err: Error) -> Error {
+    match err {
+        Error::Raw(code) => Error::Command {
+            cmd: cmd.to_owned(),
+            code,
+        },
+        err => err,
     }
 }
 
@@ -230,30 +588,71 @@ unsafe impl SetData for String {
     }
 }
 
-/// Wrapper around an `&str` returned by mpv, that properly deallocates it with mpv's allocator.
-pub struct MpvStr<'a>(&'a str);
-impl<'a> Deref for MpvStr<'a> {
+/// A string returned by mpv, which owns the allocation and frees it with mpv's allocator on
+/// drop, avoiding a copy.
+pub struct MpvString {
+    ptr: NonNull<ctype::c_char>,
+    len: usize,
+}
+
+// The allocation isn't tied to the thread or the `Mpv` it was returned by
+unsafe impl Send for MpvString {}
+unsafe impl Sync for MpvString {}
+
+impl Deref for MpvString {
     type Target = str;
 
     fn deref(&self) -> &str {
-        self.0
+        // Checked to be valid UTF-8 when it was returned
+        unsafe {
+            std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+                self.ptr.as_ptr() as *const u8,
+                self.len,
+            ))
+        }
+    }
+}
+
+impl AsRef<str> for MpvString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Display for MpvString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self)
     }
 }
-impl<'a> Drop for MpvStr<'a> {
+
+impl fmt::Debug for MpvString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for MpvString {
     fn drop(&mut self) {
-        unsafe { mpv_sys::mpv_free(self.0.as_ptr() as *mut u8 as _) };
+        unsafe { mpv_sys::mpv_free(self.ptr.as_ptr() as *mut _) };
     }
 }
 
-unsafe impl<'a> GetData for MpvStr<'a> {
+unsafe impl GetData for MpvString {
     #[inline]
     fn get_from_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(
         mut fun: F,
-    ) -> Result<MpvStr<'a>> {
-        let ptr = &mut ptr::null();
-        let _ = fun(ptr as *mut *const ctype::c_char as _)?;
-
-        Ok(MpvStr(unsafe { mpv_cstr_to_str!(*ptr) }?))
+    ) -> Result<MpvString> {
+        let ptr = &mut ptr::null_mut();
+        let _ = fun(ptr as *mut *mut ctype::c_char as _)?;
+
+        let ptr = NonNull::new(*ptr).ok_or(Error::Null)?;
+        match unsafe { mpv_cstr_to_str!(ptr.as_ptr()) } {
+            Ok(s) => Ok(MpvString { ptr, len: s.len() }),
+            Err(e) => {
+                unsafe { mpv_sys::mpv_free(ptr.as_ptr() as *mut _) };
+                Err(e)
+            }
+        }
     }
 
     #[inline]
@@ -296,6 +695,114 @@ impl Format {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Where a file is loaded from, see `ToUri`.
+pub enum MediaSource {
+    /// A local file. Relative paths are relative to the working directory.
+    Path(PathBuf),
+    #[cfg(feature = "url")]
+    /// A URL, which is passed to mpv as is, as `url::Url` is percent-encoded already.
+    Url(url::Url),
+    /// A file held in memory, passed to mpv with the `hex://` protocol.
+    Memory(Vec<u8>),
+    /// A URI of a custom or builtin protocol, e.g. one registered with the `protocols` feature.
+    /// The payload is passed to the protocol as is.
+    Protocol {
+        /// The scheme, without the `://`
+        scheme: String,
+        /// Everything after the `://`
+        payload: String,
+    },
+}
+
+/// Types that name a file or URL for mpv to load, e.g. via `Mpv::playlist_load_files`.
+///
+/// A `str` is passed to mpv as is, so it may be a path or a URL. Use a `MediaSource` to make
+/// sure that paths aren't mistaken for URLs and vice versa.
+pub trait ToUri {
+    /// The URI passed to `loadfile`, before quoting.
+    fn to_uri(&self) -> Result<Cow<'_, str>>;
+}
+
+impl ToUri for str {
+    #[inline]
+    fn to_uri(&self) -> Result<Cow<'_, str>> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl ToUri for String {
+    #[inline]
+    fn to_uri(&self) -> Result<Cow<'_, str>> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl<T: ToUri + ?Sized> ToUri for &T {
+    #[inline]
+    fn to_uri(&self) -> Result<Cow<'_, str>> {
+        (**self).to_uri()
+    }
+}
+
+fn path_uri(path: &Path) -> Result<Cow<'_, str>> {
+    let uri = path.to_str().ok_or(Error::InvalidUtf8)?;
+    // mpv reads `-` from stdin, and treats anything with a `://` as a URL
+    if path.is_relative() && (uri.starts_with('-') || uri.contains("://")) {
+        Ok(Cow::Owned(format!("./{}", uri)))
+    } else {
+        Ok(Cow::Borrowed(uri))
+    }
+}
+
+impl ToUri for MediaSource {
+    /// Returns `Error::InvalidUtf8` for paths that aren't valid UTF-8, and
+    /// `Error::InvalidArgument` for schemes that aren't valid as of RFC 3986.
+    fn to_uri(&self) -> Result<Cow<'_, str>> {
+        match *self {
+            MediaSource::Path(ref path) => path_uri(path),
+            #[cfg(feature = "url")]
+            MediaSource::Url(ref url) => Ok(Cow::Borrowed(url.as_str())),
+            MediaSource::Memory(ref data) => {
+                let mut uri = String::with_capacity("hex://".len() + data.len() * 2);
+                uri.push_str("hex://");
+                for byte in data {
+                    let _ = write!(uri, "{:02x}", byte);
+                }
+                Ok(Cow::Owned(uri))
+            }
+            MediaSource::Protocol {
+                ref scheme,
+                ref payload,
+            } => {
+                let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+                if !valid_scheme {
+                    return Err(Error::InvalidArgument);
+                }
+                Ok(Cow::Owned(format!("{}://{}", scheme, payload)))
+            }
+        }
+    }
+}
+
+impl From<PathBuf> for MediaSource {
+    #[inline]
+    fn from(path: PathBuf) -> MediaSource {
+        MediaSource::Path(path)
+    }
+}
+
+#[cfg(feature = "url")]
+impl From<url::Url> for MediaSource {
+    #[inline]
+    fn from(url: url::Url) -> MediaSource {
+        MediaSource::Url(url)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// How a `File` is inserted into the playlist.
 pub enum FileState {
@@ -317,286 +824,1758 @@ impl FileState {
     }
 }
 
-pub struct Mpv {
-    /// The handle to the mpv core
-    pub ctx: NonNull<mpv_sys::mpv_handle>,
-    #[cfg(feature = "events_sync")]
-    ev_iter_notification: Box<(Mutex<bool>, parking_lot::Condvar)>,
-    #[cfg(feature = "events_sync")]
-    ev_to_observe: Mutex<Vec<events::sync::Event>>,
-    #[cfg(feature = "events_sync")]
-    ev_to_observe_properties: Mutex<::std::collections::HashMap<String, u64>>,
-    #[cfg(feature = "events_sync")]
-    ev_observed: Mutex<Vec<events::sync::Event>>,
-    #[cfg(feature = "protocols")]
-    protocols_guard: AtomicBool,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How files dropped onto a window are added, see `Mpv::load_dropped`.
+pub enum DropMode {
+    /// Replace the playlist, and play the first file.
+    Replace,
+    /// Append to the playlist, playing the first file if the player is idle.
+    Append,
 }
 
-unsafe impl Send for Mpv {}
-unsafe impl Sync for Mpv {}
+// The extensions of subtitle files, which are added to the current file when dropped
+const SUBTITLE_EXTENSIONS: &[&str] = &[
+    "ass", "idx", "lrc", "mks", "pgs", "rt", "sbv", "scc", "smi", "srt", "ssa", "sub", "sup",
+    "utf", "utf-8", "utf8", "vtt",
+];
+
+fn is_subtitle(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some(ext) if SUBTITLE_EXTENSIONS.iter().any(|sub| sub.eq_ignore_ascii_case(ext))
+    )
+}
 
-impl Drop for Mpv {
-    #[inline]
-    fn drop(&mut self) {
-        unsafe {
-            mpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
-        }
-    }
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A chapter of the current file, as listed by `Mpv::chapters`.
+pub struct Chapter {
+    /// The index to pass to `Mpv::goto_chapter`.
+    pub index: usize,
+    /// The chapter title, if the file declares one.
+    pub title: Option<String>,
+    /// Where the chapter starts.
+    pub start: Duration,
 }
 
-impl Mpv {
-    #[cfg(not(feature = "events_sync"))]
-    #[inline]
-    /// Create a new `Mpv`.
-    /// The default settings can be probed by running: `$ mpv --show-profile=libmpv`
-    pub fn new() -> Result<Mpv> {
-        let api_version = unsafe { mpv_sys::mpv_client_api_version() };
-        if crate::MPV_CLIENT_API_VERSION != api_version {
-            return Err(Error::VersionMismatch {
-                linked: crate::MPV_CLIENT_API_VERSION,
-                loaded: api_version,
-            });
+// The last of the `chapters`, sorted by start, that starts at or before `position`
+pub(crate) fn chapter_at(chapters: &[Chapter], position: Duration) -> Option<&Chapter> {
+    chapters
+        .iter()
+        .take_while(|chapter| chapter.start <= position)
+        .last()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// An edition of the current file, as listed by `Mpv::editions`.
+pub struct Edition {
+    /// The id to pass to `Mpv::set_edition`.
+    pub id: i64,
+    /// The edition title, if the file declares one.
+    pub title: Option<String>,
+    /// Whether this is the edition mpv selects by default.
+    pub default: bool,
+}
+
+macro_rules! hwdecs {
+    ($($(#[$attr:meta])* $name:ident = $api:literal,)*) => {
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        /// A hardware decoding API, as set by the `hwdec` option, see `Mpv::set_hwdec`.
+        ///
+        /// APIs suffixed by `Copy` copy the decoded frames back to system memory, which is
+        /// slower, but works with every video output and allows video filters.
+        pub enum Hwdec {
+            $($(#[$attr])* $name,)*
+            /// An API unknown to this crate, by its mpv name.
+            Other(String),
         }
 
-        let ctx = unsafe { mpv_sys::mpv_create() };
-        if ctx.is_null() {
-            return Err(Error::Null);
+        impl Hwdec {
+            /// The name of the API, as used by mpv.
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $(Hwdec::$name => $api,)*
+                    Hwdec::Other(ref name) => name,
+                }
+            }
+
+            /// Look up an API by its mpv name.
+            pub fn from_name(name: &str) -> Hwdec {
+                match name {
+                    $($api => Hwdec::$name,)*
+                    _ => Hwdec::Other(name.to_owned()),
+                }
+            }
         }
-        mpv_err((), unsafe { mpv_sys::mpv_initialize(ctx) }).or_else(|err| {
-            unsafe { mpv_sys::mpv_terminate_destroy(ctx) };
-            Err(err)
-        })?;
+    };
+}
 
-        Ok(Mpv {
-            ctx: unsafe { NonNull::new_unchecked(ctx) },
-            #[cfg(feature = "protocols")]
-            protocols_guard: AtomicBool::new(false),
-        })
-    }
+hwdecs! {
+    /// Decode in software.
+    No = "no",
+    /// Use the first API that works, which may be one that is known to be buggy.
+    Auto = "auto",
+    /// Use the first API that works out of those considered safe.
+    AutoSafe = "auto-safe",
+    /// Like `Auto`, but only among the copying APIs.
+    AutoCopy = "auto-copy",
+    /// Video Acceleration API, on Linux.
+    Vaapi = "vaapi",
+    #[allow(missing_docs)]
+    VaapiCopy = "vaapi-copy",
+    /// Nvidia's NVDEC.
+    Nvdec = "nvdec",
+    #[allow(missing_docs)]
+    NvdecCopy = "nvdec-copy",
+    /// Video Decode and Presentation API for Unix, which is deprecated in favour of `Vaapi`
+    /// and `Nvdec`.
+    Vdpau = "vdpau",
+    /// Apple's VideoToolbox, on macOS and iOS.
+    Videotoolbox = "videotoolbox",
+    #[allow(missing_docs)]
+    VideotoolboxCopy = "videotoolbox-copy",
+    /// Direct3D 11, on Windows.
+    D3d11va = "d3d11va",
+    #[allow(missing_docs)]
+    D3d11vaCopy = "d3d11va-copy",
+    /// DirectX Video Acceleration 2, on Windows.
+    Dxva2 = "dxva2",
+    #[allow(missing_docs)]
+    Dxva2Copy = "dxva2-copy",
+    /// Android's MediaCodec.
+    Mediacodec = "mediacodec",
+    #[allow(missing_docs)]
+    MediacodecCopy = "mediacodec-copy",
+}
 
-    #[inline]
-    /// Load a configuration file. The path has to be absolute, and a file.
-    pub fn load_config(&self, path: &str) -> Result<()> {
-        let file = CString::new(path)?.into_raw();
-        let ret = mpv_err((), unsafe {
-            mpv_sys::mpv_load_config_file(self.ctx.as_ptr(), file)
-        });
-        unsafe { CString::from_raw(file) };
-        ret
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What happens at the end of a file, see `Mpv::set_keep_open`.
+pub enum KeepOpen {
+    /// Play the next file, or stop playback if it was the last one. This is mpv's default.
+    No,
+    /// Pause on the last frame if it was the last file, otherwise play the next file.
+    Yes,
+    /// Pause on the last frame of every file.
+    Always,
+}
 
-    #[inline]
-    /// Send a command to the `Mpv` instance. This uses `mpv_command_string` internally,
-    /// so that the syntax is the same as described in the [manual for the input.conf]
-    /// (https://mpv.io/manual/master/#list-of-input-commands).
-    ///
-    /// Note that you may have to escape strings with `""` when they contain spaces.
-    pub fn command(&self, name: &str, args: &[&str]) -> Result<()> {
-        let mut cmd =
-            String::with_capacity(name.len() + args.iter().fold(0, |acc, e| acc + e.len() + 1));
-        cmd.push_str(name);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How much the OSD shows, see `Mpv::set_osd_level`.
+pub enum OsdLevel {
+    /// Only subtitles, and messages shown explicitly, e.g. by the `show-text` command.
+    Disabled,
+    /// Show the OSD on user interaction, e.g. when seeking. This is mpv's default.
+    Interactive,
+    /// Like `Interactive`, but also show the current time at all times.
+    Time,
+    /// Like `Time`, but show the status message given by the `osd-status-msg` option.
+    Status,
+}
 
-        for elem in args {
-            cmd.push_str(" ");
-            cmd.push_str(elem);
-        }
-        let raw = CString::new(cmd)?;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What the OSD shows when seeking, see `Mpv::set_osd_on_seek`.
+pub enum OsdOnSeek {
+    /// Nothing.
+    No,
+    /// A progress bar. This is mpv's default.
+    Bar,
+    /// The position as text.
+    Message,
+    /// Both a progress bar, and the position as text.
+    MessageAndBar,
+}
 
-        mpv_err((), unsafe {
-            mpv_sys::mpv_command_string(self.ctx.as_ptr(), raw.as_ptr())
-        })
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A mouse button, see `Mpv::mouse_click`.
+pub enum MouseButton {
+    /// The primary button.
+    Left,
+    /// The wheel button.
+    Middle,
+    /// The secondary button.
+    Right,
+    /// The side button for going back.
+    Back,
+    /// The side button for going forward.
+    Forward,
+}
 
-    #[inline]
-    /// Set the value of a property.
-    pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
-        let name = CString::new(name)?;
-        let format = T::get_format().as_mpv_format() as _;
-        data.call_as_c_void(|ptr| {
-            mpv_err((), unsafe {
-                mpv_sys::mpv_set_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
-            })
-        })
+impl MouseButton {
+    // The button number of the `mouse` command
+    fn number(self) -> u32 {
+        match self {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::Back => 7,
+            MouseButton::Forward => 8,
+        }
     }
+}
 
-    #[inline]
-    /// Get the value of a property.
-    pub fn get_property<T: GetData>(&self, name: &str) -> Result<T> {
-        let name = CString::new(name)?;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Whether mpv deinterlaces the video, see `Mpv::set_deinterlace`.
+pub enum DeinterlaceMode {
+    /// Never deinterlace.
+    No,
+    /// Always deinterlace.
+    Yes,
+    /// Only deinterlace frames flagged as interlaced. Requires mpv 0.38 or newer.
+    Auto,
+}
 
-        let format = T::get_format().as_mpv_format() as _;
-        T::get_from_c_void(|ptr| {
-            mpv_err((), unsafe {
-                mpv_sys::mpv_get_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
-            })
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A libavfilter deinterlacer, see `Mpv::add_deinterlace_filter`.
+pub enum DeinterlaceFilter {
+    /// Bob Weaver, which gives better quality than `Yadif`.
+    Bwdif,
+    /// Yet Another DeInterlacing Filter, which is faster than `Bwdif`.
+    Yadif,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How transitions between playlist items are played, see `Mpv::configure_gapless`.
+pub enum GaplessMode {
+    /// Close and reopen the audio output between files, which causes a short gap.
+    Disabled,
+    /// Only play gaplessly if the files have the same audio format. This is mpv's default.
+    Weak,
+    /// Always play gaplessly, resampling if needed, and open the next file shortly before the
+    /// current one ends, so that no gap is caused by opening it.
+    Full,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// An item of the playlist, as yielded by `Mpv::playlist_iter`.
+pub struct PlaylistEntry {
+    /// The path or URL of the item.
+    pub filename: String,
+    /// The title of the item, if known, e.g. from a playlist file.
+    pub title: Option<String>,
+    /// Whether this is the item being played, or about to be played.
+    pub current: bool,
+    /// Whether this is the item being played right now.
+    pub playing: bool,
+}
+
+/// An iterator over the playlist, see `Mpv::playlist_iter`.
+pub struct PlaylistIter<'parent> {
+    mpv: &'parent Mpv,
+    index: usize,
+    count: usize,
+}
+
+impl<'parent> PlaylistIter<'parent> {
+    fn entry(&self, filename: String) -> Result<PlaylistEntry> {
+        let prefix = format!("playlist/{}/", self.index);
+        let title = self
+            .mpv
+            .get_property_or(&format!("{}title", prefix), String::new())?;
+        Ok(PlaylistEntry {
+            filename,
+            title: if title.is_empty() { None } else { Some(title) },
+            current: self
+                .mpv
+                .get_property_or(&format!("{}current", prefix), false)?,
+            playing: self
+                .mpv
+                .get_property_or(&format!("{}playing", prefix), false)?,
         })
     }
+}
 
-    #[inline]
-    /// Internal time in microseconds, this has an arbitrary offset, and will never go backwards.
-    ///
-    /// This can be called at any time, even if it was stated that no API function should be called.
+impl<'parent> Iterator for PlaylistIter<'parent> {
+    type Item = Result<PlaylistEntry>;
+
+    fn next(&mut self) -> Option<Result<PlaylistEntry>> {
+        if self.index >= self.count {
+            return None;
+        }
+        let entry = match self
+            .mpv
+            .get_property_with(&format!("playlist/{}/filename", self.index), String::new)
+        {
+            // The playlist shrunk since the iterator was created
+            Ok(ref filename) if filename.is_empty() => {
+                self.count = self.index;
+                return None;
+            }
+            Ok(filename) => self.entry(filename),
+            Err(e) => Err(e),
+        };
+        self.index += 1;
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.count - self.index))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Which track of a kind to play, see e.g. `Mpv::select_audio_track`.
+pub enum TrackSelector<'a> {
+    /// The track with the given id, as found in the `track-list` property.
+    Id(i64),
+    /// The first track tagged with the given language, e.g. "eng" or "jpn".
+    Lang(&'a str),
+    /// Let mpv choose, respecting the `alang`/`slang`/`vlang` options.
+    Auto,
+    /// Disable this kind of track.
+    None,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// A `Duration` that may be negative, as used by e.g. `Mpv::set_audio_delay`.
+pub struct SignedDuration {
+    duration: Duration,
+    negative: bool,
+}
+
+impl SignedDuration {
+    #[inline]
+    /// A positive offset of `duration`.
+    pub fn positive(duration: Duration) -> SignedDuration {
+        SignedDuration {
+            duration,
+            negative: false,
+        }
+    }
+
+    #[inline]
+    /// A negative offset of `duration`.
+    pub fn negative(duration: Duration) -> SignedDuration {
+        SignedDuration {
+            duration,
+            // Keep zero unsigned, so that equality holds
+            negative: duration != Duration::from_secs(0),
+        }
+    }
+
+    #[inline]
+    /// Convert from seconds. Values that are not finite are treated as zero, and values too
+    /// large for a `Duration` saturate.
+    pub fn from_secs_f64(secs: f64) -> SignedDuration {
+        if secs < 0. {
+            SignedDuration::negative(secs_to_duration(-secs))
+        } else {
+            SignedDuration::positive(secs_to_duration(secs))
+        }
+    }
+
+    #[inline]
+    /// The offset in seconds.
+    pub fn as_secs_f64(&self) -> f64 {
+        let secs = self.duration.as_secs_f64();
+        if self.negative {
+            -secs
+        } else {
+            secs
+        }
+    }
+
+    #[inline]
+    /// Whether the offset is smaller than zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    #[inline]
+    /// The magnitude of the offset.
+    pub fn abs(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl From<Duration> for SignedDuration {
+    #[inline]
+    fn from(duration: Duration) -> SignedDuration {
+        SignedDuration::positive(duration)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A length in a `GeometrySpec`.
+pub enum Length {
+    /// An absolute length in pixels.
+    Pixels(u32),
+    /// A length relative to the screen size.
+    Percent(u32),
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Length::Pixels(px) => write!(f, "{}", px),
+            Length::Percent(pc) => write!(f, "{}%", pc),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A window position on one axis in a `GeometrySpec`.
+pub enum Offset {
+    /// Distance from the left or top screen edge.
+    Start(Length),
+    /// Distance from the right or bottom screen edge.
+    End(Length),
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Offset::Start(len) => write!(f, "+{}", len),
+            Offset::End(len) => write!(f, "-{}", len),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// The window size and position, as taken by the `geometry` option, e.g. `50%x50%+10+10`.
+///
+/// Dimensions left unset are chosen by mpv, keeping the aspect ratio of the video.
+pub struct GeometrySpec {
+    width: Option<Length>,
+    height: Option<Length>,
+    position: Option<(Offset, Offset)>,
+}
+
+impl GeometrySpec {
+    #[inline]
+    /// A geometry that leaves everything up to mpv.
+    pub fn new() -> GeometrySpec {
+        GeometrySpec::default()
+    }
+
+    #[inline]
+    /// Set the window width.
+    pub fn width(mut self, width: Length) -> GeometrySpec {
+        self.width = Some(width);
+        self
+    }
+
+    #[inline]
+    /// Set the window height. mpv only honours the height if a width is set as well.
+    pub fn height(mut self, height: Length) -> GeometrySpec {
+        self.height = Some(height);
+        self
+    }
+
+    #[inline]
+    /// Set the window width and height.
+    pub fn size(self, width: Length, height: Length) -> GeometrySpec {
+        self.width(width).height(height)
+    }
+
+    #[inline]
+    /// Set the window position.
+    pub fn position(mut self, x: Offset, y: Offset) -> GeometrySpec {
+        self.position = Some((x, y));
+        self
+    }
+}
+
+impl fmt::Display for GeometrySpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+            if let Some(height) = self.height {
+                write!(f, "x{}", height)?;
+            }
+        }
+        if let Some((x, y)) = self.position {
+            write!(f, "{}{}", x, y)?;
+        }
+        Ok(())
+    }
+}
+
+// Append `key=value` to a key-value list option like `ytdl-raw-options`, quoting the value with
+// mpv's `%length%` syntax if it contains characters that would otherwise be parsed.
+pub(crate) fn push_key_value(list: &mut String, key: &str, value: &str) -> Result<()> {
+    if key.is_empty() || key.contains(&['=', ','][..]) {
+        return Err(Error::InvalidArgument);
+    }
+    if !list.is_empty() {
+        list.push(',');
+    }
+    list.push_str(key);
+    list.push('=');
+    if value.contains(&[',', '=', '[', ']', '"', '\'', '%', '\\', ' '][..]) {
+        list.push_str(&format!("%{}%", value.len()));
+    }
+    list.push_str(value);
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Configuration of the youtube-dl hook, which streams from sites like YouTube through
+/// youtube-dl or yt-dlp. See `YtdlOptions::apply`.
+pub struct YtdlOptions {
+    enabled: bool,
+    format: Option<String>,
+    raw_options: Vec<(String, String)>,
+    path: Option<String>,
+}
+
+impl Default for YtdlOptions {
+    fn default() -> YtdlOptions {
+        YtdlOptions {
+            enabled: true,
+            format: None,
+            raw_options: Vec::new(),
+            path: None,
+        }
+    }
+}
+
+impl YtdlOptions {
+    #[inline]
+    /// Options that enable the hook, with mpv's defaults otherwise.
+    pub fn new() -> YtdlOptions {
+        YtdlOptions::default()
+    }
+
+    #[inline]
+    /// Whether URLs are passed to youtube-dl at all (the `ytdl` option).
+    pub fn enabled(mut self, enabled: bool) -> YtdlOptions {
+        self.enabled = enabled;
+        self
+    }
+
+    #[inline]
+    /// The format selection passed as `--format`, e.g. `bestvideo[height<=?1080]+bestaudio/best`
+    /// (the `ytdl-format` option).
+    pub fn format(mut self, format: &str) -> YtdlOptions {
+        self.format = Some(format.to_owned());
+        self
+    }
+
+    #[inline]
+    /// Pass `--key value` to youtube-dl, or just `--key` if `value` is empty, e.g.
+    /// `raw_option("cookies", "/path/to/cookies.txt")` (the `ytdl-raw-options` option).
+    pub fn raw_option(mut self, key: &str, value: &str) -> YtdlOptions {
+        self.raw_options.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    #[inline]
+    /// Run the given binary instead of searching for `yt-dlp` and `youtube-dl` in `PATH`.
+    pub fn ytdl_path(mut self, path: &str) -> YtdlOptions {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Set the options on `mpv`. Raw options set before are replaced, an unset format is left
+    /// as is.
+    ///
+    /// Returns `Error::InvalidArgument` if a raw option key is empty, or contains `=` or `,`.
+    pub fn apply(&self, mpv: &Mpv) -> Result<()> {
+        let mut raw_options = String::new();
+        for (key, value) in &self.raw_options {
+            push_key_value(&mut raw_options, key, value)?;
+        }
+        mpv.set_property("ytdl", self.enabled)?;
+        if let Some(ref format) = self.format {
+            mpv.set_property("ytdl-format", &**format)?;
+        }
+        mpv.set_property("ytdl-raw-options", raw_options)?;
+        if let Some(ref path) = self.path {
+            // `-append` takes a single `key=value` pair verbatim, so the path isn't quoted
+            mpv.set_property(
+                "script-opts-append",
+                format!("ytdl_hook-ytdl_path={}", path),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Cache and network configuration for streaming, see `NetworkConfig::apply`. Options that are
+/// `None` are left as is.
+pub struct NetworkConfig {
+    /// Whether to cache the stream, which allows seeking within the cached part without
+    /// reloading it. Enabled by default.
+    pub cache: bool,
+    /// The maximum amount of memory the demuxer may use to read ahead.
+    pub demuxer_max_bytes: Option<u64>,
+    /// How far the demuxer should read ahead at least, if `demuxer_max_bytes` permits.
+    pub demuxer_readahead_secs: Option<f64>,
+    /// How long to wait for network operations before giving up.
+    pub network_timeout: Option<Duration>,
+    /// The user agent sent with HTTP requests.
+    pub user_agent: Option<String>,
+    /// Additional HTTP headers, as name and value, e.g. `("Referer", "https://example.com")`.
+    pub http_header_fields: Vec<(String, String)>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> NetworkConfig {
+        NetworkConfig {
+            cache: true,
+            demuxer_max_bytes: None,
+            demuxer_readahead_secs: None,
+            network_timeout: None,
+            user_agent: None,
+            http_header_fields: Vec::new(),
+        }
+    }
+}
+
+// Join HTTP headers into a string list, where literal commas and backslashes are escaped
+pub(crate) fn http_header_list(fields: &[(String, String)]) -> Result<String> {
+    let mut list = String::new();
+    for (name, value) in fields {
+        if name.is_empty()
+            || name.contains(&[':', '\r', '\n'][..])
+            || value.contains(&['\r', '\n'][..])
+        {
+            return Err(Error::InvalidArgument);
+        }
+        if !list.is_empty() {
+            list.push(',');
+        }
+        for c in name.chars().chain(": ".chars()).chain(value.chars()) {
+            if c == ',' || c == '\\' {
+                list.push('\\');
+            }
+            list.push(c);
+        }
+    }
+    Ok(list)
+}
+
+impl NetworkConfig {
+    /// Set the options on `mpv`. HTTP headers set before are replaced.
+    ///
+    /// Returns `Error::InvalidArgument` if a header name is empty, or contains `:`, or if a
+    /// header contains a line break.
+    pub fn apply(&self, mpv: &Mpv) -> Result<()> {
+        let headers = http_header_list(&self.http_header_fields)?;
+        mpv.set_property("cache", if self.cache { "yes" } else { "no" })?;
+        if let Some(bytes) = self.demuxer_max_bytes {
+            let bytes = i64::try_from(bytes).unwrap_or(i64::MAX);
+            mpv.set_property("demuxer-max-bytes", bytes)?;
+        }
+        if let Some(secs) = self.demuxer_readahead_secs {
+            mpv.set_property("demuxer-readahead-secs", secs)?;
+        }
+        if let Some(timeout) = self.network_timeout {
+            mpv.set_property("network-timeout", timeout.as_secs_f64())?;
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            mpv.set_property("user-agent", &**user_agent)?;
+        }
+        mpv.set_property("http-header-fields", headers)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What a screenshot shows, see `Mpv::screenshot_to_file`.
+pub enum ScreenshotMode {
+    /// The video in its original resolution, with subtitles.
+    Subtitles,
+    /// The video in its original resolution, without subtitles and OSD.
+    Video,
+    /// The contents of the window, scaled, with subtitles and OSD.
+    Window,
+}
+
+impl ScreenshotMode {
+    fn val(self) -> &'static str {
+        match self {
+            ScreenshotMode::Subtitles => "subtitles",
+            ScreenshotMode::Video => "video",
+            ScreenshotMode::Window => "window",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+/// The image format of screenshots.
+pub enum ScreenshotFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// How and where screenshots are saved, see `ScreenshotConfig::apply`. Options that are `None`
+/// are left as is.
+pub struct ScreenshotConfig {
+    /// The image format, if it isn't given by the file extension.
+    pub format: Option<ScreenshotFormat>,
+    /// The file name template, e.g. `shot%n` or `%F-%P`, see the
+    /// [manual](https://mpv.io/manual/master/#options-screenshot-template).
+    pub template: Option<String>,
+    /// The directory that screenshots without an absolute path are saved to.
+    pub directory: Option<String>,
+    /// The quality of JPEG images, from 0 to 100.
+    pub jpeg_quality: Option<u8>,
+    /// The compression level of PNG images, from 0 to 9.
+    pub png_compression: Option<u8>,
+    /// Whether to tag images with the colorspace of the video.
+    pub tag_colorspace: Option<bool>,
+}
+
+impl ScreenshotConfig {
+    /// Set the `screenshot-*` options on `mpv`.
+    ///
+    /// Returns `Error::InvalidArgument` if the JPEG quality or PNG compression is out of range.
+    pub fn apply(&self, mpv: &Mpv) -> Result<()> {
+        if matches!(self.jpeg_quality, Some(q) if q > 100)
+            || matches!(self.png_compression, Some(c) if c > 9)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        if let Some(format) = self.format {
+            let format = match format {
+                ScreenshotFormat::Jpeg => "jpg",
+                ScreenshotFormat::Png => "png",
+                ScreenshotFormat::Webp => "webp",
+            };
+            mpv.set_property("screenshot-format", format)?;
+        }
+        if let Some(ref template) = self.template {
+            mpv.set_property("screenshot-template", &**template)?;
+        }
+        if let Some(ref directory) = self.directory {
+            mpv.set_property("screenshot-directory", &**directory)?;
+        }
+        if let Some(quality) = self.jpeg_quality {
+            mpv.set_property("screenshot-jpeg-quality", i64::from(quality))?;
+        }
+        if let Some(compression) = self.png_compression {
+            mpv.set_property("screenshot-png-compression", i64::from(compression))?;
+        }
+        if let Some(tag) = self.tag_colorspace {
+            mpv.set_property("screenshot-tag-colorspace", tag)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// An RGBA color, as taken by color options like `sub-color`. It is displayed in mpv's
+/// `#AARRGGBB` notation.
+pub struct Color {
+    #[allow(missing_docs)]
+    pub r: u8,
+    #[allow(missing_docs)]
+    pub g: u8,
+    #[allow(missing_docs)]
+    pub b: u8,
+    /// The opacity, 255 is opaque.
+    pub a: u8,
+}
+
+impl Color {
+    #[inline]
+    /// An opaque color.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.a, self.r, self.g, self.b
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How far the style of ASS subtitles may be overridden, see `SubStyle::ass_override`.
+pub enum AssOverride {
+    /// Keep the styling of the subtitle file, ignoring `SubStyle`.
+    No,
+    /// Only apply the style options that ASS files can't express, e.g. `sub-pos`.
+    Yes,
+    /// Like `Yes`, but also scale the text with `sub-scale`. This is mpv's default.
+    Scale,
+    /// Replace the default style of the file, affecting most dialogue, but not signs.
+    Force,
+    /// Remove all styling, rendering the text like plain text subtitles.
+    Strip,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A style for plain text subtitles, and partially ASS subtitles, see `SubStyle::apply`.
+/// Options that are `None` are left as is.
+pub struct SubStyle {
+    /// The font family, e.g. `Noto Sans`.
+    pub font: Option<String>,
+    /// The font size, in pixels at a window height of 720.
+    pub size: Option<f64>,
+    /// The text color.
+    pub color: Option<Color>,
+    /// The width of the outline around the text, in the same unit as `size`.
+    pub border_size: Option<f64>,
+    /// The color of the outline around the text.
+    pub border_color: Option<Color>,
+    /// The vertical position, from 0 (top) to 100 (bottom, the default). Up to 150 is allowed,
+    /// moving the subtitles off screen.
+    pub position: Option<u8>,
+    /// How the style applies to ASS subtitles.
+    pub ass_override: Option<AssOverride>,
+}
+
+impl SubStyle {
+    /// Set the `sub-*` options on `mpv`.
+    ///
+    /// Returns `Error::InvalidArgument` if a size is negative or not finite, or the position
+    /// is above 150.
+    pub fn apply(&self, mpv: &Mpv) -> Result<()> {
+        let invalid_size = |size| matches!(size, Some(s) if !(s >= 0. && f64::is_finite(s)));
+        if invalid_size(self.size)
+            || invalid_size(self.border_size)
+            || matches!(self.position, Some(pos) if pos > 150)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        if let Some(ref font) = self.font {
+            mpv.set_property("sub-font", &**font)?;
+        }
+        if let Some(size) = self.size {
+            mpv.set_property("sub-font-size", size)?;
+        }
+        if let Some(color) = self.color {
+            mpv.set_property("sub-color", color.to_string())?;
+        }
+        if let Some(size) = self.border_size {
+            mpv.set_property("sub-border-size", size)?;
+        }
+        if let Some(color) = self.border_color {
+            mpv.set_property("sub-border-color", color.to_string())?;
+        }
+        if let Some(pos) = self.position {
+            mpv.set_property("sub-pos", i64::from(pos))?;
+        }
+        if let Some(ass_override) = self.ass_override {
+            let ass_override = match ass_override {
+                AssOverride::No => "no",
+                AssOverride::Yes => "yes",
+                AssOverride::Scale => "scale",
+                AssOverride::Force => "force",
+                AssOverride::Strip => "strip",
+            };
+            mpv.set_property("sub-ass-override", ass_override)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A snapshot of playback statistics, see `Mpv::stats`. Values that are unavailable, e.g.
+/// because no file is loaded, or the file has no video, are `None`.
+pub struct PlaybackStats {
+    /// Frames dropped by the video output, because they were late.
+    pub frame_drops: Option<i64>,
+    /// Frames dropped by the decoder, to catch up after falling behind.
+    pub decoder_frame_drops: Option<i64>,
+    /// The difference between audio and video position in seconds, positive if the audio is
+    /// ahead.
+    pub avsync: Option<f64>,
+    /// The average bitrate of the whole file in bits per second, estimated from its size and
+    /// duration.
+    pub container_bitrate: Option<f64>,
+    /// The recent bitrate of the video stream in bits per second.
+    pub video_bitrate: Option<f64>,
+    /// The recent bitrate of the audio stream in bits per second.
+    pub audio_bitrate: Option<f64>,
+    /// The hardware decoder in use, or `None` if decoding in software.
+    pub hwdec: Option<Hwdec>,
+    /// How much of the stream ahead is cached.
+    pub cache_duration: Option<Duration>,
+    /// While paused for buffering, how far along the buffering is, in percent.
+    pub cache_buffering: Option<i64>,
+}
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// The space between the video and the edges of the OSD in OSD pixels, e.g. black bars.
+pub struct Margins {
+    pub top: i64,
+    pub bottom: i64,
+    pub left: i64,
+    pub right: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// The size of the OSD, see `Mpv::osd_dimensions`. Overlays drawn in these coordinates line up
+/// with mpv's own OSD.
+pub struct OsdDimensions {
+    /// The width in OSD pixels, which are the pixels of the window for video outputs.
+    pub w: i64,
+    /// The height in OSD pixels.
+    pub h: i64,
+    /// The pixel aspect ratio of the OSD, usually 1.
+    pub par: f64,
+    /// Where the video is placed inside the OSD.
+    pub margins: Margins,
+}
+
+pub struct Mpv {
+    /// The handle to the mpv core
+    pub ctx: NonNull<mpv_sys::mpv_handle>,
+    #[cfg(feature = "events_sync")]
+    ev_iter_notification: Box<events::sync::Notification>,
+    #[cfg(feature = "events_sync")]
+    ev_to_observe: Mutex<Vec<events::sync::Event>>,
+    #[cfg(feature = "events_sync")]
+    ev_to_observe_properties: Mutex<::std::collections::HashMap<String, u64>>,
+    #[cfg(feature = "events_sync")]
+    ev_router: events::sync::Router,
+    shutdown: AtomicBool,
+    #[cfg(feature = "events_simple")]
+    events_guard: AtomicBool,
+    #[cfg(feature = "async")]
+    requests: Box<events::requests::Requests>,
+    // Dropped after the core has been destroyed, see `Drop`
+    #[cfg(feature = "protocols")]
+    protocols: Mutex<Vec<protocol::Protocol>>,
+    #[cfg(feature = "protocols")]
+    pending_streams: std::sync::Arc<protocol::PendingStreams>,
+}
+
+// libmpv's client API is thread-safe, except for waiting for events. That is only done by the
+// `events` module, which makes sure only one thread waits at a time, e.g. as only one
+// `EventContext` can exist, which isn't `Sync`.
+unsafe impl Send for Mpv {}
+unsafe impl Sync for Mpv {}
+
+impl Drop for Mpv {
+    #[inline]
+    fn drop(&mut self) {
+        // This has to happen before any field is dropped, as mpv may still use registered
+        // protocols until it returns. It also waits for the client used by asynchronous requests.
+        #[cfg(feature = "async")]
+        self.requests.destroy();
+        #[cfg(feature = "protocols")]
+        self.pending_streams.shutdown();
+        unsafe {
+            mpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
+        }
+    }
+}
+
+impl Mpv {
+    #[cfg(not(feature = "events_sync"))]
+    #[inline]
+    /// Create a new `Mpv`.
+    /// The default settings can be probed by running: `$ mpv --show-profile=libmpv`
+    pub fn new() -> Result<Mpv> {
+        let api_version = unsafe { mpv_sys::mpv_client_api_version() };
+        if crate::MPV_CLIENT_API_VERSION != api_version {
+            return Err(Error::VersionMismatch {
+                linked: crate::MPV_CLIENT_API_VERSION,
+                loaded: api_version,
+            });
+        }
+
+        let ctx = unsafe { mpv_sys::mpv_create() };
+        if ctx.is_null() {
+            return Err(Error::Null);
+        }
+        mpv_err((), unsafe { mpv_sys::mpv_initialize(ctx) }).or_else(|err| {
+            unsafe { mpv_sys::mpv_terminate_destroy(ctx) };
+            Err(err)
+        })?;
+
+        Ok(Mpv {
+            ctx: unsafe { NonNull::new_unchecked(ctx) },
+            shutdown: AtomicBool::new(false),
+            #[cfg(feature = "events_simple")]
+            events_guard: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            requests: events::requests::Requests::new(),
+            #[cfg(feature = "protocols")]
+            protocols: Mutex::new(Vec::new()),
+            #[cfg(feature = "protocols")]
+            pending_streams: std::sync::Arc::new(protocol::PendingStreams::new()),
+        })
+    }
+
+    #[inline]
+    /// Returns `Err(Error::Shutdown)` once the core has shut down.
+    fn check_shutdown(&self) -> Result<()> {
+        if self.shutdown.load(Ordering::Acquire) {
+            Err(Error::Shutdown)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    /// Load a configuration file. The path has to be absolute, and a file.
+    pub fn load_config(&self, path: &str) -> Result<()> {
+        self.check_shutdown()?;
+        let file = CString::new(path)?.into_raw();
+        let ret = mpv_err((), unsafe {
+            mpv_sys::mpv_load_config_file(self.ctx.as_ptr(), file)
+        });
+        unsafe { CString::from_raw(file) };
+        ret
+    }
+
+    #[inline]
+    /// Send a command to the `Mpv` instance. This uses `mpv_command_string` internally,
+    /// so that the syntax is the same as described in the [manual for the input.conf]
+    /// (https://mpv.io/manual/master/#list-of-input-commands).
+    ///
+    /// Note that you may have to escape strings with `""` when they contain spaces.
+    pub fn command(&self, name: &str, args: &[&str]) -> Result<()> {
+        self.run_command(|cmd| {
+            cmd.write_str(name)?;
+            for elem in args {
+                cmd.write_str(" ")?;
+                cmd.write_str(elem)?;
+            }
+            Ok(())
+        })
+    }
+
+    // Like `command`, but for commands with formatted arguments, e.g.
+    // `format_args!("add {} {}", property, value)`.
+    fn command_fmt(&self, cmd: fmt::Arguments) -> Result<()> {
+        self.run_command(|buf| buf.write_fmt(cmd))
+    }
+
+    // Runs the command written by `write`, without allocating unless it is long.
+    fn run_command<F: FnOnce(&mut CommandBuf) -> fmt::Result>(&self, write: F) -> Result<()> {
+        self.check_shutdown()?;
+        let mut cmd = CommandBuf::new();
+        write(&mut cmd).map_err(|_| Error::Null)?;
+
+        mpv_err((), unsafe {
+            mpv_sys::mpv_command_string(self.ctx.as_ptr(), cmd.as_ptr())
+        })
+        .map_err(|e| command_err(cmd.as_str(), e))
+    }
+
+    #[inline]
+    /// Set the value of a property.
+    pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
+        self.check_shutdown()?;
+        let format = T::get_format().as_mpv_format() as _;
+        with_c_name(name, |raw| {
+            data.call_as_c_void(|ptr| {
+                mpv_err((), unsafe {
+                    mpv_sys::mpv_set_property(self.ctx.as_ptr(), raw, format, ptr)
+                })
+            })
+        })
+        .map_err(|e| property_err(name, e))
+    }
+
+    #[inline]
+    /// Get the value of a property.
+    pub fn get_property<T: GetData>(&self, name: &str) -> Result<T> {
+        self.check_shutdown()?;
+        let format = T::get_format().as_mpv_format() as _;
+        with_c_name(name, |raw| {
+            T::get_from_c_void(|ptr| {
+                mpv_err((), unsafe {
+                    mpv_sys::mpv_get_property(self.ctx.as_ptr(), raw, format, ptr)
+                })
+            })
+        })
+        .map_err(|e| property_err(name, e))
+    }
+
+    #[inline]
+    /// Like `get_property`, but returns `default` if the property doesn't exist or is
+    /// unavailable, e.g. `chapter` while no file is loaded.
+    pub fn get_property_or<T: GetData>(&self, name: &str, default: T) -> Result<T> {
+        self.get_property_with(name, || default)
+    }
+
+    #[inline]
+    /// Like `get_property_or`, but only computes the default if it is needed.
+    pub fn get_property_with<T: GetData, F: FnOnce() -> T>(
+        &self,
+        name: &str,
+        default: F,
+    ) -> Result<T> {
+        self.get_optional_property(name)
+            .map(|value| value.unwrap_or_else(default))
+    }
+
+    // Like `get_property`, but returns `None` if the property is unavailable or doesn't exist.
+    fn get_optional_property<T: GetData>(&self, name: &str) -> Result<Option<T>> {
+        match self.get_property(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(ref e)
+                if e.code() == Some(ErrorCode::PropertyUnavailable)
+                    || e.code() == Some(ErrorCode::PropertyNotFound) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    #[inline]
+    /// Internal time in microseconds, this has an arbitrary offset, and will never go backwards.
+    ///
+    /// This can be called at any time, even if it was stated that no API function should be called.
     pub fn get_internal_time(&self) -> i64 {
         unsafe { mpv_sys::mpv_get_time_us(self.ctx.as_ptr()) }
     }
 
-    // --- Convenience property functions ---
+    // --- Convenience property functions ---
+    //
+
+    #[inline]
+    /// Add -or subtract- any value from a property. Over/underflow clamps to max/min.
+    pub fn add_property(&self, property: &str, value: isize) -> Result<()> {
+        self.command_fmt(format_args!("add {} {}", property, value))
+    }
+
+    #[inline]
+    /// Cycle through a given property. `up` specifies direction. On
+    /// overflow, set the property back to the minimum, on underflow set it to the maximum.
+    pub fn cycle_property(&self, property: &str, up: bool) -> Result<()> {
+        self.command("cycle", &[property, if up { "up" } else { "down" }])
+    }
+
+    #[inline]
+    /// Multiply any property with any positive factor.
+    pub fn multiply_property(&self, property: &str, factor: usize) -> Result<()> {
+        self.command_fmt(format_args!("multiply {} {}", property, factor))
+    }
+
+    #[inline]
+    /// Pause playback at runtime.
+    pub fn pause(&self) -> Result<()> {
+        self.set_property("pause", true)
+    }
+
+    #[inline]
+    /// Unpause playback at runtime.
+    pub fn unpause(&self) -> Result<()> {
+        self.set_property("pause", false)
+    }
+
+    // --- Convenience command functions ---
+    //
+
+    #[inline]
+    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
+    /// Enable an event.
+    pub fn enable_event(&self, ev: events::EventId) -> Result<()> {
+        self.check_shutdown()?;
+        mpv_err((), unsafe {
+            mpv_sys::mpv_request_event(self.ctx.as_ptr(), ev.as_raw(), 1)
+        })
+    }
+
+    #[inline]
+    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
+    /// Enable all, except deprecated, events.
+    pub fn enable_all_events(&self) -> Result<()> {
+        for ev in events::EventId::iter() {
+            self.enable_event(ev)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
+    /// Disable an event.
+    pub fn disable_event(&self, ev: events::EventId) -> Result<()> {
+        self.check_shutdown()?;
+        mpv_err((), unsafe {
+            mpv_sys::mpv_request_event(self.ctx.as_ptr(), ev.as_raw(), 0)
+        })
+    }
+
+    #[inline]
+    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
+    /// Diable all deprecated events.
+    pub fn disable_deprecated_events(&self) -> Result<()> {
+        for ev in events::EventId::all().filter(|ev| ev.is_deprecated()) {
+            self.disable_event(ev)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
+    /// Diable all events.
+    pub fn disable_all_events(&self) -> Result<()> {
+        for ev in events::EventId::all() {
+            self.disable_event(ev)?;
+        }
+        Ok(())
+    }
+
+    // --- Seek functions ---
+    //
+
+    #[inline]
+    /// Seek forward relatively from current position.
+    /// This is less exact than `seek_absolute`, see [mpv manual]
+    /// (https://mpv.io/manual/master/#command-interface-
+    /// [relative|absolute|absolute-percent|relative-percent|exact|keyframes]).
+    pub fn seek_forward(&self, time: Duration) -> Result<()> {
+        self.seek(seek_args(time.as_secs_f64(), SeekMode::Relative)?)
+    }
+
+    #[inline]
+    /// See `seek_forward`.
+    pub fn seek_backward(&self, time: Duration) -> Result<()> {
+        self.seek(seek_args(-time.as_secs_f64(), SeekMode::Relative)?)
+    }
+
+    #[inline]
+    /// Seek to a given absolute position.
+    pub fn seek_absolute(&self, time: Duration) -> Result<()> {
+        self.seek(seek_args(time.as_secs_f64(), SeekMode::Absolute)?)
+    }
+
+    #[inline]
+    /// Seek to a given relative percent position (may be negative).
+    /// If `percent` of the playtime is bigger than the remaining playtime, the next file is played.
+    /// Out of bounds values are clamped to either -100 or 100.
+    pub fn seek_percent(&self, percent: isize) -> Result<()> {
+        self.seek(seek_args(percent as f64, SeekMode::RelativePercent)?)
+    }
+
+    #[inline]
+    /// Seek to the given percentage of the playtime. Values above 100 are clamped to 100.
+    pub fn seek_percent_absolute(&self, percent: usize) -> Result<()> {
+        self.seek(seek_args(percent as f64, SeekMode::AbsolutePercent)?)
+    }
+
+    fn seek(&self, (target, mode): (f64, &str)) -> Result<()> {
+        self.command_fmt(format_args!("seek {} {}", target, mode))
+    }
+
+    #[inline]
+    /// Loop between the positions `a` and `b`. `None` disables either end of the loop.
+    pub fn set_ab_loop(&self, a: Option<Duration>, b: Option<Duration>) -> Result<()> {
+        self.set_ab_loop_point("ab-loop-a", a)?;
+        self.set_ab_loop_point("ab-loop-b", b)
+    }
+
+    fn set_ab_loop_point(&self, name: &str, point: Option<Duration>) -> Result<()> {
+        match point {
+            Some(point) => self.set_property(name, point.as_secs_f64()),
+            None => self.set_property(name, "no"),
+        }
+    }
+
+    fn time_property(&self, name: &str) -> Result<Option<Duration>> {
+        self.get_optional_property(name)
+            .map(|secs| secs.map(secs_to_duration))
+    }
+
+    #[inline]
+    /// The current playback position in the file, or `None` if no file is loaded.
+    pub fn position(&self) -> Result<Option<Duration>> {
+        self.time_property("time-pos")
+    }
+
+    #[inline]
+    /// The duration of the current file, or `None` if no file is loaded or the duration is
+    /// unknown, e.g. for live streams.
+    pub fn duration(&self) -> Result<Option<Duration>> {
+        self.time_property("duration")
+    }
+
+    #[inline]
+    /// The time left until the end of the current file at normal speed, or `None` if it is
+    /// unknown.
+    pub fn remaining(&self) -> Result<Option<Duration>> {
+        self.time_property("time-remaining")
+    }
+
+    #[inline]
+    /// The playback position as percentage of the file, from 0 to 100, or `None` if no file
+    /// is loaded.
+    pub fn percent_position(&self) -> Result<Option<f64>> {
+        self.get_optional_property("percent-pos")
+    }
+
+    #[inline]
+    /// Jump to the given percentage of the file, e.g. when a scrub bar is dragged. Unlike
+    /// `seek_percent_absolute`, this sets the `percent-pos` property and accepts fractional
+    /// values. Values outside of 0 to 100 are clamped.
+    ///
+    /// Returns `Error::InvalidArgument` if `percent` is NaN.
+    pub fn seek_percent_pos(&self, percent: f64) -> Result<()> {
+        if percent.is_nan() {
+            return Err(Error::InvalidArgument);
+        }
+        self.set_property("percent-pos", percent.clamp(0., 100.))
+    }
+
+    #[inline]
+    /// Revert the previous `seek_` call, can also revert itself.
+    pub fn seek_revert(&self) -> Result<()> {
+        self.command("revert-seek", &[])
+    }
+
+    #[inline]
+    /// Mark the current position as the position that will be seeked to by `seek_revert`.
+    pub fn seek_revert_mark(&self) -> Result<()> {
+        self.command("revert-seek", &["mark"])
+    }
+
+    #[inline]
+    /// Seek exactly one frame, and pause.
+    /// Noop on audio only streams.
+    pub fn seek_frame(&self) -> Result<()> {
+        self.command("frame-step", &[])
+    }
+
+    #[inline]
+    /// See `seek_frame`.
+    /// [Note performance considerations.](https://mpv.io/manual/master/#command-interface-frame-back-step)
+    pub fn seek_frame_backward(&self) -> Result<()> {
+        self.command("frame-back-step", &[])
+    }
+
+    // --- Video functions ---
+    //
+
+    #[inline]
+    /// Rotate the video clockwise by `degrees`, which has to be below 360. Most video outputs
+    /// only support multiples of 90.
+    pub fn set_video_rotation(&self, degrees: u32) -> Result<()> {
+        if degrees >= 360 {
+            return Err(Error::InvalidArgument);
+        }
+        self.set_property("video-rotate", i64::from(degrees))
+    }
+
+    #[inline]
+    /// Zoom the video by the factor `2^zoom`, e.g. `1.` doubles the size. `zoom` has to be
+    /// within -20 to 20.
+    pub fn set_video_zoom(&self, zoom: f64) -> Result<()> {
+        self.set_property("video-zoom", check_range(zoom, -20., 20.)?)
+    }
+
+    #[inline]
+    /// Move the video by a fraction of its size, e.g. `x = 0.5` moves it right by half its
+    /// width. Both values have to be within -3 to 3.
+    pub fn set_video_pan(&self, x: f64, y: f64) -> Result<()> {
+        let (x, y) = (check_range(x, -3., 3.)?, check_range(y, -3., 3.)?);
+        self.set_property("video-pan-x", x)?;
+        self.set_property("video-pan-y", y)
+    }
+
+    #[inline]
+    /// Align the video within the window if it doesn't fill it, from -1 (left or top) to 1
+    /// (right or bottom). `0.` centers it.
+    pub fn set_video_align(&self, x: f64, y: f64) -> Result<()> {
+        let (x, y) = (check_range(x, -1., 1.)?, check_range(y, -1., 1.)?);
+        self.set_property("video-align-x", x)?;
+        self.set_property("video-align-y", y)
+    }
+
+    #[inline]
+    /// Crop the video to fill the window while keeping the aspect ratio, from 0 (no cropping)
+    /// to 1 (crop as much as needed to fill the window).
+    pub fn set_panscan(&self, panscan: f64) -> Result<()> {
+        self.set_property("panscan", check_range(panscan, 0., 1.)?)
+    }
+
+    #[inline]
+    /// Make the parts of the video output that aren't covered by video transparent, and keep the
+    /// alpha channel of the video, so that it can be composited over other content.
+    ///
+    /// This sets the `background` and `alpha` options, which are only read when the video output
+    /// is initialized, so it has to be called before the first file is loaded. It only has an
+    /// effect if the target framebuffer has an alpha channel: when rendering through a
+    /// `RenderContext`, request a visual with alpha bits for the OpenGL context, e.g. a window
+    /// created with transparency enabled and 8 alpha bits.
+    pub fn set_transparent_background(&self, transparent: bool) -> Result<()> {
+        let (background, alpha) = if transparent {
+            ("#00000000", "yes")
+        } else {
+            ("#000000", "blend-tiles")
+        };
+        self.set_property("background", background)?;
+        self.set_property("alpha", alpha)
+    }
+
+    #[inline]
+    /// Select the hardware decoding API, `Hwdec::No` disables hardware decoding. If the API
+    /// doesn't work for a file, mpv falls back to software decoding.
+    pub fn set_hwdec(&self, hwdec: Hwdec) -> Result<()> {
+        self.set_property("hwdec", hwdec.as_str())
+    }
+
+    #[inline]
+    /// The hardware decoding API that is in use, or `None` if the video is decoded in software,
+    /// or there is no video.
+    pub fn current_hwdec(&self) -> Result<Option<Hwdec>> {
+        let hwdec = self.get_property_or("hwdec-current", String::new())?;
+        Ok(match &*hwdec {
+            "" | "no" => None,
+            name => Some(Hwdec::from_name(name)),
+        })
+    }
+
+    #[inline]
+    /// Set whether mpv deinterlaces the video, using a deinterlacer suited for the current
+    /// hardware decoder or video output.
+    pub fn set_deinterlace(&self, mode: DeinterlaceMode) -> Result<()> {
+        let mode = match mode {
+            DeinterlaceMode::No => "no",
+            DeinterlaceMode::Yes => "yes",
+            DeinterlaceMode::Auto => "auto",
+        };
+        self.set_property("deinterlace", mode)
+    }
+
+    #[inline]
+    /// Insert the given deinterlacer into the video filter chain, labelled `@deinterlace`, for
+    /// when the choice of `set_deinterlace` is not good enough, e.g. for TV captures. Replaces
+    /// a filter inserted before.
+    ///
+    /// This requires software decoding, or a hardware decoder in copy mode.
+    pub fn add_deinterlace_filter(&self, filter: DeinterlaceFilter) -> Result<()> {
+        let filter = match filter {
+            DeinterlaceFilter::Bwdif => "lavfi=[bwdif]",
+            DeinterlaceFilter::Yadif => "lavfi=[yadif]",
+        };
+        self.vf_add("deinterlace", filter)
+    }
+
+    #[inline]
+    /// Remove the deinterlacer inserted by `add_deinterlace_filter`.
+    pub fn remove_deinterlace_filter(&self) -> Result<()> {
+        self.vf_remove("deinterlace")
+    }
+
+    /// Undo any rotation, zoom, pan, alignment and panscan.
+    pub fn reset_video_transform(&self) -> Result<()> {
+        self.set_property("video-rotate", 0)?;
+        self.set_property("video-zoom", 0.)?;
+        self.set_video_pan(0., 0.)?;
+        self.set_video_align(0., 0.)?;
+        self.set_property("panscan", 0.)
+    }
+
+    // --- Filter functions ---
+    //
+
+    /// Append `filter`, e.g. `lavfi=[cropdetect]`, to the video filter chain, labelled `@label`
+    /// so it can be found again. Replaces a filter with the same label.
+    ///
+    /// Labels may only consist of ASCII letters, digits, `-` and `_`, otherwise
+    /// `Error::InvalidArgument` is returned.
+    pub fn vf_add(&self, label: &str, filter: &str) -> Result<()> {
+        let label = check_label(label)?;
+        self.run_command(|cmd| {
+            cmd.write_str("vf add ")?;
+            cmd.write_quoted(&format!("@{}:{}", label, filter))
+        })
+    }
+
+    #[inline]
+    /// Remove the video filter labelled `label`, see `vf_add`.
+    pub fn vf_remove(&self, label: &str) -> Result<()> {
+        let label = check_label(label)?;
+        self.command_fmt(format_args!("vf remove @{}", label))
+    }
+
+    #[inline]
+    /// Enable or disable the video filter labelled `label`, see `vf_add`. A disabled filter stays
+    /// in the chain, but doesn't process any frames.
+    pub fn vf_toggle(&self, label: &str) -> Result<()> {
+        let label = check_label(label)?;
+        self.command_fmt(format_args!("vf toggle @{}", label))
+    }
+
+    /// Whether the video filter chain contains a filter labelled `label`, enabled or not.
+    pub fn vf_exists(&self, label: &str) -> Result<bool> {
+        let label = check_label(label)?;
+        self.with_property_node("vf", |filters| {
+            filters
+                .iter()
+                .any(|filter| filter.get("label").and_then(|l| l.as_str()) == Some(label))
+        })
+    }
+
+    /// The metadata the video filter labelled `label` attached to the last frame, e.g. the
+    /// `lavfi.cropdetect.w` key of `lavfi=[cropdetect]`, which may be empty before the first
+    /// frame was filtered.
+    pub fn vf_metadata(&self, label: &str) -> Result<::std::collections::HashMap<String, String>> {
+        let label = check_label(label)?;
+        self.with_property_node(&format!("vf-metadata/{}", label), |metadata| {
+            metadata
+                .entries()
+                .filter_map(|(key, value)| Some((key?.to_owned(), value.as_str()?.to_owned())))
+                .collect()
+        })
+    }
+
+    // --- Speed functions ---
     //
 
     #[inline]
-    /// Add -or subtract- any value from a property. Over/underflow clamps to max/min.
-    pub fn add_property(&self, property: &str, value: isize) -> Result<()> {
-        self.command("add", &[property, &format!("{}", value)])
+    /// Set the playback speed, e.g. `2.` to play twice as fast. Values outside of mpv's range of
+    /// `0.01` to `100` are clamped.
+    ///
+    /// Returns `Error::InvalidArgument` if `speed` is NaN.
+    pub fn set_speed(&self, speed: f64) -> Result<()> {
+        if speed.is_nan() {
+            return Err(Error::InvalidArgument);
+        }
+        self.set_property("speed", speed.clamp(0.01, 100.))
     }
 
     #[inline]
-    /// Cycle through a given property. `up` specifies direction. On
-    /// overflow, set the property back to the minimum, on underflow set it to the maximum.
-    pub fn cycle_property(&self, property: &str, up: bool) -> Result<()> {
-        self.command("cycle", &[property, if up { "up" } else { "down" }])
+    /// The current playback speed.
+    pub fn speed(&self) -> Result<f64> {
+        self.get_property("speed")
     }
 
     #[inline]
-    /// Multiply any property with any positive factor.
-    pub fn multiply_property(&self, property: &str, factor: usize) -> Result<()> {
-        self.command("multiply", &[property, &format!("{}", factor)])
+    /// Change the playback speed by `delta`, e.g. `-0.25` to slow down by a quarter.
+    pub fn nudge_speed(&self, delta: f64) -> Result<()> {
+        if !delta.is_finite() {
+            return Err(Error::InvalidArgument);
+        }
+        self.command_fmt(format_args!("add speed {}", delta))
     }
 
     #[inline]
-    /// Pause playback at runtime.
-    pub fn pause(&self) -> Result<()> {
-        self.set_property("pause", true)
+    /// Whether to keep the pitch of the audio when the speed is changed, which is the default.
+    pub fn set_pitch_correction(&self, enable: bool) -> Result<()> {
+        self.set_property("audio-pitch-correction", enable)
     }
 
-    #[inline]
-    /// Unpause playback at runtime.
-    pub fn unpause(&self) -> Result<()> {
-        self.set_property("pause", false)
+    // --- Audio functions ---
+    //
+
+    /// Configure the transitions between playlist items, by setting the `gapless-audio` and
+    /// `prefetch-playlist` options, which only give truly gapless playback when combined.
+    ///
+    /// Prefetching only opens the next file, so the amount of memory used for caching is
+    /// unaffected.
+    pub fn configure_gapless(&self, mode: GaplessMode) -> Result<()> {
+        let (gapless, prefetch) = match mode {
+            GaplessMode::Disabled => ("no", false),
+            GaplessMode::Weak => ("weak", false),
+            GaplessMode::Full => ("yes", true),
+        };
+        self.set_property("gapless-audio", gapless)?;
+        self.set_property("prefetch-playlist", prefetch)
     }
 
-    // --- Convenience command functions ---
+    // --- Delay functions ---
     //
 
     #[inline]
-    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
-    /// Enable an event.
-    pub fn enable_event(&self, ev: events::EventId) -> Result<()> {
-        mpv_err((), unsafe {
-            mpv_sys::mpv_request_event(self.ctx.as_ptr(), ev, 1)
-        })
+    /// Delay the audio by `delay`. A negative delay plays the audio earlier.
+    pub fn set_audio_delay<D: Into<SignedDuration>>(&self, delay: D) -> Result<()> {
+        self.set_property("audio-delay", delay.into().as_secs_f64())
     }
 
     #[inline]
-    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
-    /// Enable all, except deprecated, events.
-    pub fn enable_all_events(&self) -> Result<()> {
-        for i in (1..9)
-            .chain(11..12)
-            .chain(14..15)
-            .chain(16..19)
-            .chain(20..23)
-            .chain(23..26)
-        {
-            self.enable_event(i)?;
-        }
-        Ok(())
+    /// Add `delta` to the current audio delay.
+    pub fn adjust_audio_delay<D: Into<SignedDuration>>(&self, delta: D) -> Result<()> {
+        let delta = delta.into().as_secs_f64();
+        self.command_fmt(format_args!("add audio-delay {}", delta))
     }
 
     #[inline]
-    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
-    /// Disable an event.
-    pub fn disable_event(&self, ev: events::EventId) -> Result<()> {
-        mpv_err((), unsafe {
-            mpv_sys::mpv_request_event(self.ctx.as_ptr(), ev, 0)
-        })
+    /// The current audio delay.
+    pub fn audio_delay(&self) -> Result<SignedDuration> {
+        self.get_property("audio-delay")
+            .map(SignedDuration::from_secs_f64)
     }
 
     #[inline]
-    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
-    /// Diable all deprecated events.
-    pub fn disable_deprecated_events(&self) -> Result<()> {
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_TRACKS_CHANGED)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_TRACK_SWITCHED)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_PAUSE)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_UNPAUSE)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_SCRIPT_INPUT_DISPATCH)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_METADATA_UPDATE)?;
-        self.disable_event(mpv_sys::mpv_event_id_MPV_EVENT_CHAPTER_CHANGE)?;
-        Ok(())
+    /// Delay the subtitles by `delay`. A negative delay shows the subtitles earlier.
+    pub fn set_subtitle_delay<D: Into<SignedDuration>>(&self, delay: D) -> Result<()> {
+        self.set_property("sub-delay", delay.into().as_secs_f64())
     }
 
     #[inline]
-    #[cfg(any(feature = "events_simple", feature = "events_complex"))]
-    /// Diable all events.
-    pub fn disable_all_events(&self) -> Result<()> {
-        for i in 1..26 {
-            self.disable_event(i as _)?;
-        }
-        Ok(())
+    /// Add `delta` to the current subtitle delay.
+    pub fn adjust_subtitle_delay<D: Into<SignedDuration>>(&self, delta: D) -> Result<()> {
+        let delta = delta.into().as_secs_f64();
+        self.command_fmt(format_args!("add sub-delay {}", delta))
     }
 
-    // --- Seek functions ---
+    #[inline]
+    /// The current subtitle delay.
+    pub fn subtitle_delay(&self) -> Result<SignedDuration> {
+        self.get_property("sub-delay")
+            .map(SignedDuration::from_secs_f64)
+    }
+
+    // --- Chapter functions ---
     //
 
     #[inline]
-    /// Seek forward relatively from current position in seconds.
-    /// This is less exact than `seek_absolute`, see [mpv manual]
-    /// (https://mpv.io/manual/master/#command-interface-
-    /// [relative|absolute|absolute-percent|relative-percent|exact|keyframes]).
-    pub fn seek_forward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "relative"])
+    /// The number of chapters of the current file.
+    pub fn chapter_count(&self) -> Result<usize> {
+        self.get_property_or::<i64>("chapters", 0)
+            .map(|count| count.max(0) as usize)
+    }
+
+    /// The chapters of the current file, sorted by their start.
+    pub fn chapters(&self) -> Result<Vec<Chapter>> {
+        let count = self.get_property_or::<i64>("chapter-list/count", 0)?;
+        let mut chapters = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count {
+            let title =
+                self.get_property_or(&format!("chapter-list/{}/title", i), String::new())?;
+            let start: f64 = self.get_property(&format!("chapter-list/{}/time", i))?;
+            chapters.push(Chapter {
+                index: i as usize,
+                title: if title.is_empty() { None } else { Some(title) },
+                start: secs_to_duration(start),
+            });
+        }
+        Ok(chapters)
+    }
+
+    /// The chapter that contains `position`, e.g. to label the hovered part of a scrub bar.
+    /// This is `None` if the file has no chapters, or `position` is before the first one.
+    pub fn chapter_at(&self, position: Duration) -> Result<Option<Chapter>> {
+        Ok(chapter_at(&self.chapters()?, position).cloned())
+    }
+
+    /// The metadata tags of the current chapter as key and value, e.g. `("title", "Intro")`.
+    /// Empty if there is no current chapter, or it has no tags.
+    pub fn chapter_metadata(&self) -> Result<Vec<(String, String)>> {
+        let count = self.get_property_or::<i64>("chapter-metadata/list/count", 0)?;
+        let mut tags = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count {
+            tags.push((
+                self.get_property(&format!("chapter-metadata/list/{}/key", i))?,
+                self.get_property(&format!("chapter-metadata/list/{}/value", i))?,
+            ));
+        }
+        Ok(tags)
     }
 
     #[inline]
-    /// See `seek_forward`.
-    pub fn seek_backward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("-{}", secs), "relative"])
+    /// The index of the current chapter, or `None` if the file has no chapters, or the
+    /// position is before the first one.
+    pub fn current_chapter(&self) -> Result<Option<usize>> {
+        let chapter = self.get_property_or::<i64>("chapter", -1)?;
+        Ok(if chapter < 0 {
+            None
+        } else {
+            Some(chapter as usize)
+        })
     }
 
     #[inline]
-    /// Seek to a given absolute secs.
-    pub fn seek_absolute(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "absolute"])
+    /// Seek to the chapter at `index`.
+    ///
+    /// Returns `Error::InvalidArgument` if the file has no chapter at `index`.
+    pub fn goto_chapter(&self, index: usize) -> Result<()> {
+        if index >= self.chapter_count()? {
+            return Err(Error::InvalidArgument);
+        }
+        self.set_property("chapter", index as i64)
     }
 
     #[inline]
-    /// Seek to a given relative percent position (may be negative).
-    /// If `percent` of the playtime is bigger than the remaining playtime, the next file is played.
-    /// out of bounds values are clamped to either 0 or 100.
-    pub fn seek_percent(&self, percent: isize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
+    /// Seek to the next chapter. Like mpv's default key binding, this plays the next file if
+    /// the current chapter is the last one.
+    pub fn next_chapter(&self) -> Result<()> {
+        self.command("add", &["chapter", "1"])
     }
 
     #[inline]
-    /// Seek to the given percentage of the playtime.
-    pub fn seek_percent_absolute(&self, percent: usize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
+    /// Seek to the start of the previous chapter. If playback is more than
+    /// `chapter-seek-threshold` seconds (5 by default) into the current chapter, this seeks to the
+    /// start of the current chapter instead, like mpv's default key binding.
+    pub fn previous_chapter(&self) -> Result<()> {
+        self.command("add", &["chapter", "-1"])
+    }
+
+    // --- Track functions ---
+    //
+
+    fn select_track(&self, property: &str, kind: &str, track: TrackSelector) -> Result<()> {
+        match track {
+            TrackSelector::Id(id) => self.set_property(property, id),
+            TrackSelector::Auto => self.set_property(property, "auto"),
+            TrackSelector::None => self.set_property(property, "no"),
+            TrackSelector::Lang(lang) => {
+                let count = self.get_property_or::<i64>("track-list/count", 0)?;
+                for i in 0..count {
+                    let prefix = format!("track-list/{}/", i);
+                    if &*self.get_property::<MpvString>(&format!("{}type", prefix))? != kind {
+                        continue;
+                    }
+                    let track_lang =
+                        self.get_property_or(&format!("{}lang", prefix), String::new())?;
+                    if track_lang.eq_ignore_ascii_case(lang) {
+                        let id: i64 = self.get_property(&format!("{}id", prefix))?;
+                        return self.set_property(property, id);
+                    }
+                }
+                Err(Error::InvalidArgument)
+            }
+        }
     }
 
     #[inline]
-    /// Revert the previous `seek_` call, can also revert itself.
-    pub fn seek_revert(&self) -> Result<()> {
-        self.command("revert-seek", &[])
+    /// Select the audio track to play (the `aid` property).
+    ///
+    /// Returns `Error::InvalidArgument` if `TrackSelector::Lang` matches no audio track.
+    pub fn select_audio_track(&self, track: TrackSelector) -> Result<()> {
+        self.select_track("aid", "audio", track)
     }
 
     #[inline]
-    /// Mark the current position as the position that will be seeked to by `seek_revert`.
-    pub fn seek_revert_mark(&self) -> Result<()> {
-        self.command("revert-seek", &["mark"])
+    /// Select the subtitle track to display (the `sid` property).
+    ///
+    /// Returns `Error::InvalidArgument` if `TrackSelector::Lang` matches no subtitle track.
+    pub fn select_subtitle_track(&self, track: TrackSelector) -> Result<()> {
+        self.select_track("sid", "sub", track)
     }
 
     #[inline]
-    /// Seek exactly one frame, and pause.
-    /// Noop on audio only streams.
-    pub fn seek_frame(&self) -> Result<()> {
-        self.command("frame-step", &[])
+    /// Select the video track to display (the `vid` property).
+    ///
+    /// Returns `Error::InvalidArgument` if `TrackSelector::Lang` matches no video track.
+    pub fn select_video_track(&self, track: TrackSelector) -> Result<()> {
+        self.select_track("vid", "video", track)
+    }
+
+    // --- Edition functions ---
+    //
+
+    /// The editions of the current file, e.g. the theatrical and director's cut of a Matroska
+    /// file. Files without editions yield an empty list.
+    pub fn editions(&self) -> Result<Vec<Edition>> {
+        let count = self.get_property_or::<i64>("edition-list/count", 0)?;
+        let mut editions = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count {
+            let title =
+                self.get_property_or(&format!("edition-list/{}/title", i), String::new())?;
+            editions.push(Edition {
+                id: self.get_property(&format!("edition-list/{}/id", i))?,
+                title: if title.is_empty() { None } else { Some(title) },
+                default: self.get_property_or(&format!("edition-list/{}/default", i), false)?,
+            });
+        }
+        Ok(editions)
     }
 
     #[inline]
-    /// See `seek_frame`.
-    /// [Note performance considerations.](https://mpv.io/manual/master/#command-interface-frame-back-step)
-    pub fn seek_frame_backward(&self) -> Result<()> {
-        self.command("frame-back-step", &[])
+    /// Switch to the edition with the given `id`, as found in `Mpv::editions`. This restarts
+    /// playback of the current file.
+    pub fn set_edition(&self, id: i64) -> Result<()> {
+        self.set_property("edition", id)
     }
 
     // --- Screenshot functions ---
@@ -642,6 +2621,27 @@ impl Mpv {
         }
     }
 
+    #[inline]
+    /// Save a screenshot to `path`, ignoring the template and directory of the
+    /// `ScreenshotConfig`. The image format is guessed from the extension, and an existing file
+    /// is overwritten.
+    ///
+    /// If `asynchronous`, this returns before the image is encoded and written, and errors
+    /// writing it are only logged.
+    pub fn screenshot_to_file(
+        &self,
+        path: &str,
+        mode: ScreenshotMode,
+        asynchronous: bool,
+    ) -> Result<()> {
+        let prefix = if asynchronous { "async " } else { "" };
+        self.run_command(|cmd| {
+            write!(cmd, "{}screenshot-to-file ", prefix)?;
+            cmd.write_quoted(path)?;
+            write!(cmd, " {}", mode.val())
+        })
+    }
+
     // --- Playlist functions ---
     //
 
@@ -677,35 +2677,91 @@ impl Mpv {
     ///
     /// # Arguments
     /// The `files` tuple slice consists of:
-    ///     * a string slice - the path
+    ///     * a `ToUri`, e.g. a string slice or a `MediaSource` - the path or URL
     ///     * a `FileState` - how the file will be opened
     ///     * an optional string slice - any additional options that will be set for this file
     ///
+    /// The path and the options are quoted, so they may contain spaces. Use `playlist::parse` and
+    /// `playlist::load_args` to load the entries of playlist files.
+    ///
     /// # Peculiarities
     /// `loadfile` is kind of asynchronous, any additional option is set during loading,
     /// [specifics](https://github.com/mpv-player/mpv/issues/4089).
-    pub fn playlist_load_files<'a, A>(&self, files: &[(&str, FileState, A)]) -> Result<()>
+    pub fn playlist_load_files<'a, S, A>(&self, files: &[(S, FileState, A)]) -> Result<()>
     where
+        S: ToUri,
         A: Into<Option<&'a str>> + Clone,
     {
         for (i, elem) in files.iter().enumerate() {
-            let args = elem.2.clone().into().unwrap_or("");
-
-            let ret = self.command(
-                "loadfile",
-                &[&format!("\"{}\"", elem.0), elem.1.val(), args],
-            );
+            let ret = self.loadfile(&elem.0, elem.1, elem.2.clone().into());
 
             if ret.is_err() {
                 return Err(Error::Loadfiles {
                     index: i,
-                    error: ::std::rc::Rc::new(ret.unwrap_err()),
+                    error: ::std::sync::Arc::new(ret.unwrap_err()),
                 });
             }
         }
         Ok(())
     }
 
+    // Runs `loadfile` with the source and options quoted.
+    fn loadfile<S: ToUri + ?Sized>(
+        &self,
+        source: &S,
+        state: FileState,
+        options: Option<&str>,
+    ) -> Result<()> {
+        let uri = source.to_uri()?;
+        self.run_command(|cmd| {
+            cmd.write_str("loadfile ")?;
+            cmd.write_quoted(&uri)?;
+            write!(cmd, " {}", state.val())?;
+            if let Some(options) = options {
+                cmd.write_str(" ")?;
+                cmd.write_quoted(options)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Load the `paths` dropped onto a window, as mpv does for its own windows: if all of them are
+    /// subtitles, they are added to the current file, otherwise they are added to the playlist
+    /// according to `mode`.
+    ///
+    /// Returns the result of loading each path, in the same order. Failing to load one doesn't
+    /// prevent the others from being loaded, e.g. with `DropMode::Replace` the first file that
+    /// could be loaded replaces the playlist.
+    pub fn load_dropped(&self, paths: &[PathBuf], mode: DropMode) -> Vec<Result<()>> {
+        if !paths.is_empty() && paths.iter().all(|path| is_subtitle(path)) {
+            return paths
+                .iter()
+                .map(|path| {
+                    let uri = path_uri(path)?;
+                    self.run_command(|cmd| {
+                        cmd.write_str("sub-add ")?;
+                        cmd.write_quoted(&uri)
+                    })
+                })
+                .collect();
+        }
+
+        let mut state = match mode {
+            DropMode::Replace => FileState::Replace,
+            DropMode::Append => FileState::AppendPlay,
+        };
+        paths
+            .iter()
+            .map(|path| {
+                let ret = path_uri(path).and_then(|uri| self.loadfile(&*uri, state, None));
+                if ret.is_ok() {
+                    state = FileState::Append;
+                }
+                ret
+            })
+            .collect()
+    }
+
     #[inline]
     /// Load the given playlist file, that either replaces the current playlist, or appends to it.
     pub fn playlist_load_list(&self, path: &str, replace: bool) -> Result<()> {
@@ -731,13 +2787,13 @@ impl Mpv {
     #[inline]
     /// Remove item at `position` from the playlist.
     pub fn playlist_remove_index(&self, position: usize) -> Result<()> {
-        self.command("playlist-remove", &[&format!("{}", position)])
+        self.command_fmt(format_args!("playlist-remove {}", position))
     }
 
     #[inline]
     /// Move item `old` to the position of item `new`.
     pub fn playlist_move(&self, old: usize, new: usize) -> Result<()> {
-        self.command("playlist-move", &[&format!("{}", new), &format!("{}", old)])
+        self.command_fmt(format_args!("playlist-move {} {}", new, old))
     }
 
     #[inline]
@@ -746,6 +2802,65 @@ impl Mpv {
         self.command("playlist-shuffle", &[])
     }
 
+    #[inline]
+    /// Iterate over the playlist, reading each item only when it is reached, which keeps
+    /// refreshing the view of a huge playlist cheap.
+    ///
+    /// The length of the playlist is determined once, if items are removed while iterating, the
+    /// iteration ends early. Items that are added meanwhile aren't yielded.
+    pub fn playlist_iter(&self) -> Result<PlaylistIter> {
+        Ok(PlaylistIter {
+            mpv: self,
+            index: 0,
+            count: self.get_property_or::<i64>("playlist-count", 0)?.max(0) as usize,
+        })
+    }
+
+    // --- Resume functions ---
+    //
+
+    #[inline]
+    /// Save the position in the current file to mpv's `watch_later` directory, from where it is
+    /// restored when the file is played again, see `resume_from_saved`.
+    pub fn save_position(&self) -> Result<()> {
+        self.command("write-watch-later-config", &[])
+    }
+
+    /// Pass the path of the current file and the position in it to `store`, e.g. to save it to
+    /// a database instead of the `watch_later` directory, see `resume_with`.
+    ///
+    /// Returns `Ok(false)` without calling `store` if no file is playing.
+    pub fn save_position_with<F: FnOnce(&str, Duration)>(&self, store: F) -> Result<bool> {
+        let path = self.get_optional_property::<MpvString>("path")?;
+        match (path, self.position()?) {
+            (Some(path), Some(position)) => {
+                store(&path, position);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    #[inline]
+    /// Play `path`, replacing the playlist, and continue at the position saved by
+    /// `save_position`, if any. This only enables resuming for this file, regardless of the
+    /// `resume-playback` option.
+    pub fn resume_from_saved(&self, path: &str) -> Result<()> {
+        self.loadfile(path, FileState::Replace, Some("resume-playback=yes"))
+    }
+
+    /// Play `path`, replacing the playlist, and continue at the position returned by `load`,
+    /// or from the start if it returns `None`. Positions saved in the `watch_later` directory
+    /// are ignored for this file.
+    pub fn resume_with<F>(&self, path: &str, load: F) -> Result<()>
+    where
+        F: FnOnce(&str) -> Option<Duration>,
+    {
+        let start = load(path).map_or(0., |position| position.as_secs_f64());
+        let options = format!("resume-playback=no,start={}", start);
+        self.loadfile(path, FileState::Replace, Some(&options))
+    }
+
     // --- Subtitle functions ---
     //
 
@@ -753,45 +2868,43 @@ impl Mpv {
     /// Add and select the subtitle immediately.
     /// Specifying a language requires specifying a title.
     ///
-    /// # Panics
-    /// If a language but not title was specified.
+    /// Returns `Error::InvalidArgument` if a language, but not a title, was provided.
     pub fn subtitle_add_select<'a, 'b, A: Into<Option<&'a str>>, B: Into<Option<&'b str>>>(
         &self,
         path: &str,
         title: A,
         lang: B,
     ) -> Result<()> {
-        match (title.into(), lang.into()) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "select"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "select", t]),
-            (None, Some(_)) => panic!("Given subtitle language, but missing title"),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "select", t, l])
-            }
-        }
+        self.subtitle_add(path, "select", title.into(), lang.into())
     }
 
     #[inline]
     /// See `AddSelect`. "Don't select the subtitle.
     /// (Or in some special situations, let the default stream selection mechanism decide.)".
     ///
-    /// Returns an `Error::InvalidArgument` if a language, but not a title, was provided.
-    ///
-    /// # Panics
-    /// If a language but not title was specified.
+    /// Returns `Error::InvalidArgument` if a language, but not a title, was provided.
     pub fn subtitle_add_auto<'a, 'b, A: Into<Option<&'a str>>, B: Into<Option<&'b str>>>(
         &self,
         path: &str,
         title: A,
         lang: B,
     ) -> Result<()> {
-        match (title.into(), lang.into()) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto", t]),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "auto", t, l])
-            }
-            (None, Some(_)) => panic!("Given subtitle language, but missing title"),
+        self.subtitle_add(path, "auto", title.into(), lang.into())
+    }
+
+    fn subtitle_add(
+        &self,
+        path: &str,
+        flag: &str,
+        title: Option<&str>,
+        lang: Option<&str>,
+    ) -> Result<()> {
+        let path = format!("\"{}\"", path);
+        match (title, lang) {
+            (None, None) => self.command("sub-add", &[&path, flag]),
+            (Some(t), None) => self.command("sub-add", &[&path, flag, t]),
+            (Some(t), Some(l)) => self.command("sub-add", &[&path, flag, t, l]),
+            (None, Some(_)) => Err(Error::InvalidArgument),
         }
     }
 
@@ -809,7 +2922,7 @@ impl Mpv {
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_remove<A: Into<Option<usize>>>(&self, index: A) -> Result<()> {
         if let Some(idx) = index.into() {
-            self.command("sub-remove", &[&format!("{}", idx)])
+            self.command_fmt(format_args!("sub-remove {}", idx))
         } else {
             self.command("sub-remove", &[])
         }
@@ -820,7 +2933,7 @@ impl Mpv {
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_reload<A: Into<Option<usize>>>(&self, index: A) -> Result<()> {
         if let Some(idx) = index.into() {
-            self.command("sub-reload", &[&format!("{}", idx)])
+            self.command_fmt(format_args!("sub-reload {}", idx))
         } else {
             self.command("sub-reload", &[])
         }
@@ -830,7 +2943,7 @@ impl Mpv {
     /// "Change subtitle timing such, that the subtitle event after the next `isize` subtitle
     /// events is displayed. `isize` can be negative to step backwards."
     pub fn subtitle_step(&self, skip: isize) -> Result<()> {
-        self.command("sub-step", &[&format!("{}", skip)])
+        self.command_fmt(format_args!("sub-step {}", skip))
     }
 
     #[inline]
@@ -847,4 +2960,272 @@ impl Mpv {
     pub fn subtitle_seek_backward(&self) -> Result<()> {
         self.command("sub-seek", &["-1"])
     }
+
+    #[inline]
+    /// Set what happens at the end of a file. If playback pauses, the `eof-reached` property
+    /// becomes `true`, see `EventContext::observe_eof_reached`.
+    pub fn set_keep_open(&self, keep_open: KeepOpen) -> Result<()> {
+        let keep_open = match keep_open {
+            KeepOpen::No => "no",
+            KeepOpen::Yes => "yes",
+            KeepOpen::Always => "always",
+        };
+        self.set_property("keep-open", keep_open)
+    }
+
+    // --- OSD functions ---
+    //
+
+    #[inline]
+    /// Set how much the on-screen display shows.
+    pub fn set_osd_level(&self, level: OsdLevel) -> Result<()> {
+        let level = match level {
+            OsdLevel::Disabled => 0,
+            OsdLevel::Interactive => 1,
+            OsdLevel::Time => 2,
+            OsdLevel::Status => 3,
+        };
+        self.set_property("osd-level", level)
+    }
+
+    #[inline]
+    /// Set what the OSD shows when seeking.
+    pub fn set_osd_on_seek(&self, on_seek: OsdOnSeek) -> Result<()> {
+        let on_seek = match on_seek {
+            OsdOnSeek::No => "no",
+            OsdOnSeek::Bar => "bar",
+            OsdOnSeek::Message => "msg",
+            OsdOnSeek::MessageAndBar => "msg-bar",
+        };
+        self.set_property("osd-on-seek", on_seek)
+    }
+
+    #[inline]
+    /// Whether the OSD may show progress bars, e.g. when seeking or changing the volume. If
+    /// disabled, messages are shown instead.
+    pub fn set_osd_bar(&self, enable: bool) -> Result<()> {
+        self.set_property("osd-bar", enable)
+    }
+
+    #[inline]
+    /// Set how long OSD messages and bars stay visible, which is a second by default.
+    pub fn set_osd_duration(&self, duration: Duration) -> Result<()> {
+        let millis = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+        self.set_property("osd-duration", millis)
+    }
+
+    /// The current size of the OSD, and the position of the video in it.
+    pub fn osd_dimensions(&self) -> Result<OsdDimensions> {
+        Ok(OsdDimensions {
+            w: self.get_property("osd-dimensions/w")?,
+            h: self.get_property("osd-dimensions/h")?,
+            par: self.get_property("osd-dimensions/par")?,
+            margins: Margins {
+                top: self.get_property("osd-dimensions/mt")?,
+                bottom: self.get_property("osd-dimensions/mb")?,
+                left: self.get_property("osd-dimensions/ml")?,
+                right: self.get_property("osd-dimensions/mr")?,
+            },
+        })
+    }
+
+    // --- Script functions ---
+    //
+
+    #[inline]
+    /// The name of this client, which scripts address with `script-message-to`, usually `main`.
+    pub fn client_name(&self) -> &str {
+        // The name is set by libmpv, and consists of ASCII characters
+        unsafe { CStr::from_ptr(mpv_sys::mpv_client_name(self.ctx.as_ptr())) }
+            .to_str()
+            .unwrap_or("")
+    }
+
+    #[inline]
+    /// Load a Lua or JavaScript script, or a C plugin, at runtime.
+    pub fn load_script(&self, path: &str) -> Result<()> {
+        self.run_command(|cmd| {
+            cmd.write_str("load-script ")?;
+            cmd.write_quoted(path)
+        })
+    }
+
+    /// Send a message to the script or client named `target`, which receives `args` as a
+    /// `ClientMessage` event, or through the handler its Lua scripts registered with
+    /// `mp.register_script_message`. The first argument is usually the name of the message.
+    pub fn send_script_message(&self, target: &str, args: &[&str]) -> Result<()> {
+        self.run_command(|cmd| {
+            cmd.write_str("script-message-to ")?;
+            cmd.write_quoted(target)?;
+            for arg in args {
+                cmd.write_char(' ')?;
+                cmd.write_quoted(arg)?;
+            }
+            Ok(())
+        })
+    }
+
+    // --- IPC functions ---
+    //
+
+    #[inline]
+    /// Serve mpv's [JSON IPC protocol](https://mpv.io/manual/master/#json-ipc) on the unix
+    /// socket at `path`, or on Windows the named pipe, e.g. `\\.\pipe\mpv`, so that tools
+    /// written for `mpv --input-ipc-server` can control this instance. `None` stops the server.
+    ///
+    /// The server is part of the core, so it works with libmpv as well, and every connection
+    /// behaves like a separate client.
+    ///
+    /// Requires mpv 0.32 or newer, as `Mpv::new` already initializes the core, and older versions
+    /// only read `input-ipc-server` during initialization. They accept the path, but don't
+    /// start or stop the server.
+    pub fn set_ipc_server(&self, path: Option<&str>) -> Result<()> {
+        self.set_property("input-ipc-server", path.unwrap_or(""))
+    }
+
+    // --- Statistics functions ---
+    //
+
+    /// Collect playback statistics, e.g. for a diagnostics overlay.
+    pub fn stats(&self) -> Result<PlaybackStats> {
+        let file_size = self.get_optional_property::<i64>("file-size")?;
+        let duration = self.get_optional_property::<f64>("duration")?;
+        let container_bitrate = match (file_size, duration) {
+            (Some(size), Some(secs)) if secs > 0. => Some(size as f64 * 8. / secs),
+            _ => None,
+        };
+        Ok(PlaybackStats {
+            frame_drops: self.get_optional_property("frame-drop-count")?,
+            decoder_frame_drops: self.get_optional_property("decoder-frame-drop-count")?,
+            avsync: self.get_optional_property("avsync")?,
+            container_bitrate,
+            video_bitrate: self.get_optional_property("video-bitrate")?,
+            audio_bitrate: self.get_optional_property("audio-bitrate")?,
+            hwdec: self.current_hwdec()?,
+            cache_duration: self.time_property("demuxer-cache-duration")?,
+            cache_buffering: self.get_optional_property("cache-buffering-state")?,
+        })
+    }
+
+    /// Toggle the statistics overlay of mpv's built-in `stats` script, or with `Some(page)`,
+    /// show `page` of it instead, e.g. 2 for frame timings. Pages start at 1.
+    ///
+    /// Selecting a page relies on the `display-page-N` bindings of the script, which only newer
+    /// mpv versions (0.36 and later) have. Older versions ignore it.
+    ///
+    /// Returns `Error::InvalidArgument` for page 0, and pages above 9, which the script has no
+    /// bindings for.
+    pub fn toggle_stats(&self, page: Option<u8>) -> Result<()> {
+        match page {
+            Some(page) if page == 0 || page > 9 => Err(Error::InvalidArgument),
+            Some(page) => {
+                self.command_fmt(format_args!("script-binding stats/display-page-{}", page))
+            }
+            None => self.command("script-binding", &["stats/display-stats-toggle"]),
+        }
+    }
+
+    // --- Window functions ---
+    //
+
+    #[inline]
+    /// Enter or leave fullscreen mode.
+    pub fn set_fullscreen(&self, enable: bool) -> Result<()> {
+        self.set_property("fullscreen", enable)
+    }
+
+    #[inline]
+    /// Toggle fullscreen mode.
+    pub fn toggle_fullscreen(&self) -> Result<()> {
+        self.command("cycle", &["fullscreen"])
+    }
+
+    #[inline]
+    /// Minimize or restore the window. Not supported by all video outputs.
+    pub fn set_window_minimized(&self, enable: bool) -> Result<()> {
+        self.set_property("window-minimized", enable)
+    }
+
+    #[inline]
+    /// Maximize or restore the window. Not supported by all video outputs.
+    pub fn set_window_maximized(&self, enable: bool) -> Result<()> {
+        self.set_property("window-maximized", enable)
+    }
+
+    #[inline]
+    /// Keep the window above other windows.
+    pub fn set_ontop(&self, enable: bool) -> Result<()> {
+        self.set_property("ontop", enable)
+    }
+
+    #[inline]
+    /// Resize and move the window.
+    pub fn set_geometry(&self, geometry: GeometrySpec) -> Result<()> {
+        self.set_property("geometry", geometry.to_string())
+    }
+
+    // --- Mouse functions ---
+    //
+
+    #[inline]
+    /// Move the mouse pointer to `x`, `y` in OSD pixels, e.g. to forward pointer input of a
+    /// window mpv is embedded into, so the on-screen controller reacts to hovering.
+    ///
+    /// Returns `Error::InvalidArgument` if a coordinate doesn't fit into an `i32`.
+    pub fn set_mouse_pos(&self, x: i64, y: i64) -> Result<()> {
+        check_mouse_pos(x, y)?;
+        self.command_fmt(format_args!("mouse {} {}", x, y))
+    }
+
+    #[inline]
+    /// Move the mouse pointer to `x`, `y` and click `button` there, as a double click if `double`.
+    ///
+    /// Returns `Error::InvalidArgument` if a coordinate doesn't fit into an `i32`.
+    pub fn mouse_click(&self, x: i64, y: i64, button: MouseButton, double: bool) -> Result<()> {
+        check_mouse_pos(x, y)?;
+        let mode = if double { "double" } else { "single" };
+        self.command_fmt(format_args!(
+            "mouse {} {} {} {}",
+            x,
+            y,
+            button.number(),
+            mode
+        ))
+    }
+
+    /// The position of the mouse pointer in OSD pixels, or `None` if it isn't over the window.
+    ///
+    /// Requires mpv 0.33 or newer, which added the `mouse-pos` property.
+    pub fn mouse_pos(&self) -> Result<Option<(i64, i64)>> {
+        if !self.get_property::<bool>("mouse-pos/hover")? {
+            return Ok(None);
+        }
+        Ok(Some((
+            self.get_property("mouse-pos/x")?,
+            self.get_property("mouse-pos/y")?,
+        )))
+    }
+
+    #[cfg(feature = "raw_window_handle")]
+    #[inline]
+    /// Embed the video into `window` by setting the `wid` option, which has to happen before
+    /// any file is loaded.
+    ///
+    /// X11, Win32 and AppKit (`NSView`) windows are supported, mpv can't embed into other kinds
+    /// of windows, and `Error::Raw(ErrorCode::Unsupported)` is returned for them.
+    pub fn set_window_handle<W: raw_window_handle::HasRawWindowHandle>(
+        &self,
+        window: &W,
+    ) -> Result<()> {
+        use raw_window_handle::RawWindowHandle;
+
+        let wid = match window.raw_window_handle() {
+            RawWindowHandle::Xlib(handle) => handle.window as i64,
+            RawWindowHandle::Xcb(handle) => i64::from(handle.window),
+            RawWindowHandle::Win32(handle) => handle.hwnd as isize as i64,
+            RawWindowHandle::AppKit(handle) => handle.ns_view as isize as i64,
+            _ => return Err(Error::Raw(ErrorCode::Unsupported)),
+        };
+        self.set_property("wid", wid)
+    }
 }
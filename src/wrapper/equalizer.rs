@@ -0,0 +1,114 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! An audio equalizer, implemented with libavfilter's `firequalizer` filter. It is inserted into
+//! the audio filter chain under its label, so that it doesn't interfere with other filters.
+
+use super::*;
+
+/// The center frequencies of the ISO octave bands, commonly found on 10-band equalizers.
+pub const TEN_BAND_FREQUENCIES: [f64; 10] = [
+    31.25, 62.5, 125., 250., 500., 1000., 2000., 4000., 8000., 16000.,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+/// An equalizer with a gain in dB per frequency band. Changes only take effect once `apply` is
+/// called.
+pub struct Equalizer {
+    label: String,
+    bands: Vec<(f64, f64)>,
+}
+
+impl Equalizer {
+    /// Create an equalizer with a band at each of the `frequencies` in Hz, and all gains at 0 dB.
+    /// The equalizer is identified by `label` in the audio filter chain, as in `@label`.
+    ///
+    /// Returns `Error::InvalidArgument` if `label` is empty, or contains characters other than
+    /// ASCII alphanumerics, `-` and `_`, or if a frequency isn't positive.
+    pub fn new(label: &str, frequencies: &[f64]) -> Result<Equalizer> {
+        check_label(label)?;
+        if frequencies.iter().any(|f| !(f.is_finite() && *f > 0.)) {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(Equalizer {
+            label: label.to_owned(),
+            bands: frequencies.iter().map(|&f| (f, 0.)).collect(),
+        })
+    }
+
+    #[inline]
+    /// Create a 10-band equalizer with the `TEN_BAND_FREQUENCIES`.
+    pub fn ten_band(label: &str) -> Result<Equalizer> {
+        Equalizer::new(label, &TEN_BAND_FREQUENCIES)
+    }
+
+    #[inline]
+    /// The label of the equalizer in the audio filter chain.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    #[inline]
+    /// The frequency in Hz and gain in dB of each band.
+    pub fn bands(&self) -> &[(f64, f64)] {
+        &self.bands
+    }
+
+    /// Set the gain of the band at `index` to `gain` dB. Call `apply` to hear the change.
+    ///
+    /// Returns `Error::InvalidArgument` if there is no band at `index`, or `gain` isn't finite.
+    pub fn update_band(&mut self, index: usize, gain: f64) -> Result<()> {
+        match self.bands.get_mut(index) {
+            Some(band) if gain.is_finite() => {
+                band.1 = gain;
+                Ok(())
+            }
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// The entry in the audio filter chain, e.g.
+    /// `@eq:lavfi=[firequalizer=gain_entry='entry(31.25,0);entry(62.5,-3)']`.
+    pub fn filter(&self) -> String {
+        let mut filter = format!("@{}:lavfi=[firequalizer=gain_entry='", self.label);
+        for (i, (frequency, gain)) in self.bands.iter().enumerate() {
+            if i != 0 {
+                filter.push(';');
+            }
+            filter.push_str(&format!("entry({},{})", frequency, gain));
+        }
+        filter.push_str("']");
+        filter
+    }
+
+    #[inline]
+    /// Insert the equalizer into the audio filter chain of `mpv`, or replace the entry with the
+    /// same label, updating the gains.
+    pub fn apply(&self, mpv: &Mpv) -> Result<()> {
+        mpv.run_command(|cmd| {
+            cmd.write_str("af add ")?;
+            cmd.write_quoted(&self.filter())
+        })
+    }
+
+    #[inline]
+    /// Remove the equalizer from the audio filter chain of `mpv`.
+    pub fn remove(&self, mpv: &Mpv) -> Result<()> {
+        mpv.command("af", &["remove", &format!("@{}", self.label)])
+    }
+}
@@ -16,13 +16,22 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
+#[cfg(feature = "event_replay")]
+/// Recording events of a session and replaying them without a core
+pub mod replay;
+#[cfg(feature = "async")]
+/// Asynchronous commands and property access via futures
+pub mod requests;
 #[cfg(feature = "events_simple")]
 pub mod simple;
+#[cfg(feature = "async")]
+/// Event handling via `futures`
+pub mod stream;
 #[cfg(feature = "events_sync")]
 pub mod sync;
 
-/// An `Event`'s ID.
-pub use mpv_sys::mpv_event_id as EventId;
+/// A raw `Event` ID, as used by libmpv.
+pub use mpv_sys::mpv_event_id as MpvEventId;
 pub mod mpv_event_id {
     #![allow(missing_docs)]
     pub use mpv_sys::mpv_event_id_MPV_EVENT_AUDIO_RECONFIG as AudioReconfig;
@@ -45,3 +54,103 @@ pub mod mpv_event_id {
     pub use mpv_sys::mpv_event_id_MPV_EVENT_TICK as Tick;
     pub use mpv_sys::mpv_event_id_MPV_EVENT_VIDEO_RECONFIG as VideoReconfig;
 }
+
+macro_rules! event_ids {
+    ($($name:ident = $raw:ident $(, $deprecated:ident)*;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[allow(missing_docs)]
+        /// An `Event`'s ID.
+        pub enum EventId {
+            $($name,)*
+        }
+
+        const ALL_EVENT_IDS: &[EventId] = &[$(EventId::$name,)*];
+
+        impl EventId {
+            /// The raw ID used by libmpv.
+            pub fn as_raw(self) -> MpvEventId {
+                match self {
+                    $(EventId::$name => mpv_sys::$raw,)*
+                }
+            }
+
+            /// Returns `None` for `MPV_EVENT_NONE` and IDs unknown to this crate.
+            pub fn from_raw(raw: MpvEventId) -> Option<EventId> {
+                $(
+                    if raw == mpv_sys::$raw {
+                        return Some(EventId::$name);
+                    }
+                )*
+                None
+            }
+
+            /// Whether libmpv considers this event deprecated.
+            pub fn is_deprecated(self) -> bool {
+                match self {
+                    $(EventId::$name => event_ids!(@deprecated $($deprecated)*),)*
+                }
+            }
+        }
+    };
+    (@deprecated deprecated) => { true };
+    (@deprecated) => { false };
+}
+
+event_ids! {
+    Shutdown = mpv_event_id_MPV_EVENT_SHUTDOWN;
+    LogMessage = mpv_event_id_MPV_EVENT_LOG_MESSAGE;
+    GetPropertyReply = mpv_event_id_MPV_EVENT_GET_PROPERTY_REPLY;
+    SetPropertyReply = mpv_event_id_MPV_EVENT_SET_PROPERTY_REPLY;
+    CommandReply = mpv_event_id_MPV_EVENT_COMMAND_REPLY;
+    StartFile = mpv_event_id_MPV_EVENT_START_FILE;
+    EndFile = mpv_event_id_MPV_EVENT_END_FILE;
+    FileLoaded = mpv_event_id_MPV_EVENT_FILE_LOADED;
+    TracksChanged = mpv_event_id_MPV_EVENT_TRACKS_CHANGED, deprecated;
+    TrackSwitched = mpv_event_id_MPV_EVENT_TRACK_SWITCHED, deprecated;
+    Idle = mpv_event_id_MPV_EVENT_IDLE;
+    Pause = mpv_event_id_MPV_EVENT_PAUSE, deprecated;
+    Unpause = mpv_event_id_MPV_EVENT_UNPAUSE, deprecated;
+    Tick = mpv_event_id_MPV_EVENT_TICK;
+    ScriptInputDispatch = mpv_event_id_MPV_EVENT_SCRIPT_INPUT_DISPATCH, deprecated;
+    ClientMessage = mpv_event_id_MPV_EVENT_CLIENT_MESSAGE;
+    VideoReconfig = mpv_event_id_MPV_EVENT_VIDEO_RECONFIG;
+    AudioReconfig = mpv_event_id_MPV_EVENT_AUDIO_RECONFIG;
+    MetadataUpdate = mpv_event_id_MPV_EVENT_METADATA_UPDATE, deprecated;
+    Seek = mpv_event_id_MPV_EVENT_SEEK;
+    PlaybackRestart = mpv_event_id_MPV_EVENT_PLAYBACK_RESTART;
+    PropertyChange = mpv_event_id_MPV_EVENT_PROPERTY_CHANGE;
+    ChapterChange = mpv_event_id_MPV_EVENT_CHAPTER_CHANGE, deprecated;
+    QueueOverflow = mpv_event_id_MPV_EVENT_QUEUE_OVERFLOW;
+    Hook = mpv_event_id_MPV_EVENT_HOOK;
+}
+
+#[cfg(any(feature = "events_sync", feature = "log_messages"))]
+// The name of `lvl` as accepted by `mpv_request_log_messages`
+pub(crate) fn mpv_log_level_as_str(lvl: crate::LogLevel) -> Option<&'static str> {
+    use crate::mpv_log_level;
+
+    Some(match lvl {
+        mpv_log_level::None => "no",
+        mpv_log_level::Fatal => "fatal",
+        mpv_log_level::Error => "error",
+        mpv_log_level::Warn => "warn",
+        mpv_log_level::Info => "info",
+        mpv_log_level::V => "v",
+        mpv_log_level::Debug => "debug",
+        mpv_log_level::Trace => "trace",
+        _ => return None,
+    })
+}
+
+impl EventId {
+    /// Iterate over all, including deprecated, `EventId`s.
+    pub fn all() -> impl Iterator<Item = EventId> {
+        ALL_EVENT_IDS.iter().cloned()
+    }
+
+    /// Iterate over all, except deprecated, `EventId`s.
+    pub fn iter() -> impl Iterator<Item = EventId> {
+        EventId::all().filter(|id| !id.is_deprecated())
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Recording the events of a real session with an `EventRecorder`, and feeding them back through
+//! the same `Event` types with an `EventReplayer`, e.g. to test the state machine of a UI
+//! deterministically without a core.
+//!
+//! A recording is a `Vec<RecordedEvent>`, which can be stored with any `serde` format.
+
+use super::simple::{Event, MessageIter, OwnedEvent};
+use crate::*;
+
+use std::ffi::CString;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+/// An event as recorded by an `EventRecorder`.
+pub struct RecordedEvent {
+    /// When the event was received, relative to the start of the recording.
+    pub offset: Duration,
+    /// The event, or the code of the error it failed with.
+    pub event: ::std::result::Result<OwnedEvent, ErrorCode>,
+}
+
+/// Records the events returned by `EventContext::wait_event` or `EventContext::drain`.
+pub struct EventRecorder {
+    start: Instant,
+    events: Vec<RecordedEvent>,
+}
+
+impl Default for EventRecorder {
+    #[inline]
+    fn default() -> EventRecorder {
+        EventRecorder::new()
+    }
+}
+
+impl EventRecorder {
+    #[inline]
+    /// Start a new recording, the offsets of the events are relative to now.
+    pub fn new() -> EventRecorder {
+        EventRecorder {
+            start: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Record an event returned by `EventContext::wait_event`.
+    ///
+    /// Errors without an `ErrorCode`, like invalid utf-8, aren't recorded.
+    pub fn record(&mut self, event: &Result<Event>) {
+        let event = event.clone().and_then(Event::into_owned);
+        self.push(event);
+    }
+
+    /// Record an event returned by `EventContext::drain`, see `record`.
+    pub fn record_owned(&mut self, event: &Result<OwnedEvent>) {
+        self.push(event.clone());
+    }
+
+    fn push(&mut self, event: Result<OwnedEvent>) {
+        let event = match event {
+            Ok(event) => Ok(event),
+            Err(e) => match e.code() {
+                Some(code) => Err(code),
+                None => return,
+            },
+        };
+        self.events.push(RecordedEvent {
+            offset: self.start.elapsed(),
+            event,
+        });
+    }
+
+    #[inline]
+    /// The events recorded so far.
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    #[inline]
+    /// Stop recording, and return the recorded events.
+    pub fn finish(self) -> Vec<RecordedEvent> {
+        self.events
+    }
+}
+
+/// Returns recorded events like `EventContext::wait_event` would, in order and without waiting
+/// for their offsets.
+pub struct EventReplayer {
+    events: ::std::vec::IntoIter<RecordedEvent>,
+    current: Option<RecordedEvent>,
+    // The messages of the current `ClientMessage`, which its `MessageIter` points into
+    messages: Vec<CString>,
+    message_ptrs: Vec<*const i8>,
+}
+
+impl EventReplayer {
+    #[inline]
+    /// Replay `events`, e.g. as returned by `EventRecorder::finish`.
+    pub fn new(events: Vec<RecordedEvent>) -> EventReplayer {
+        EventReplayer {
+            events: events.into_iter(),
+            current: None,
+            messages: Vec::new(),
+            message_ptrs: Vec::new(),
+        }
+    }
+
+    #[inline]
+    /// The number of events left to replay.
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    #[inline]
+    /// The offset of the next event, or `None` if all events were replayed.
+    pub fn next_offset(&self) -> Option<Duration> {
+        self.events.as_slice().first().map(|event| event.offset)
+    }
+
+    /// Return the next recorded event, or `None` if all events were replayed.
+    ///
+    /// Failed events are returned as `Error::Raw` with the recorded code.
+    pub fn next_event(&mut self) -> Option<Result<Event>> {
+        self.current = Some(self.events.next()?);
+        self.messages.clear();
+        self.message_ptrs.clear();
+
+        let event = match self.current.as_ref()?.event {
+            Ok(ref event) => event,
+            Err(code) => return Some(Err(Error::Raw(code))),
+        };
+        if let OwnedEvent::ClientMessage(ref messages) = *event {
+            for message in messages {
+                match CString::new(&message[..]) {
+                    Ok(message) => self.messages.push(message),
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+            self.message_ptrs
+                .extend(self.messages.iter().map(|message| message.as_ptr()));
+        }
+
+        Some(Ok(match *event {
+            OwnedEvent::Shutdown => Event::Shutdown,
+            OwnedEvent::LogMessage {
+                ref prefix,
+                ref level,
+                ref text,
+                log_level,
+            } => Event::LogMessage {
+                prefix,
+                level,
+                text,
+                log_level,
+            },
+            OwnedEvent::GetPropertyReply {
+                ref name,
+                ref result,
+                reply_userdata,
+            } => Event::GetPropertyReply {
+                name,
+                result: result.as_property_data(),
+                reply_userdata,
+            },
+            OwnedEvent::SetPropertyReply(u) => Event::SetPropertyReply(u),
+            OwnedEvent::CommandReply(u) => Event::CommandReply(u),
+            OwnedEvent::StartFile => Event::StartFile,
+            OwnedEvent::EndFile(r) => Event::EndFile(r),
+            OwnedEvent::FileLoaded => Event::FileLoaded,
+            OwnedEvent::Idle => Event::Idle,
+            OwnedEvent::Unpause => Event::Unpause,
+            OwnedEvent::Tick => Event::Tick,
+            // The pointers stay valid until the next call, which needs `&mut self`
+            OwnedEvent::ClientMessage(_) => {
+                Event::ClientMessage(unsafe { MessageIter::new(&self.message_ptrs) })
+            }
+            OwnedEvent::VideoReconfig => Event::VideoReconfig,
+            OwnedEvent::AudioReconfig => Event::AudioReconfig,
+            OwnedEvent::Seek => Event::Seek,
+            OwnedEvent::PlaybackRestart => Event::PlaybackRestart,
+            OwnedEvent::PropertyChange {
+                ref name,
+                ref change,
+                reply_userdata,
+            } => Event::PropertyChange {
+                name,
+                change: change.as_property_data(),
+                reply_userdata,
+            },
+            OwnedEvent::QueueOverflow => Event::QueueOverflow,
+            OwnedEvent::Deprecated(id) => Event::Deprecated(id),
+        }))
+    }
+}
@@ -0,0 +1,381 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use parking_lot::Mutex;
+
+use super::simple::{OwnedPropertyData, PropertyData};
+use super::{mpv_event_id, EventId};
+use crate::wrapper::{command_err, mpv_err, property_err, with_c_name};
+use crate::*;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw as ctype;
+use std::pin::Pin;
+use std::ptr::{self, NonNull};
+use std::task::{Context, Poll, Waker};
+
+type ReplyResult = Result<Option<OwnedPropertyData>>;
+
+unsafe extern "C" fn wakeup_callback(d: *mut ctype::c_void) {
+    // Wake outside of the lock, in case a waker polls inline
+    let wakers = mem::take(&mut *(*(d as *const Requests)).wakers.lock());
+    for (_, waker) in wakers {
+        waker.wake();
+    }
+}
+
+struct State {
+    // A separate client, so that replies don't end up in the `EventContext`
+    client: Option<NonNull<mpv_sys::mpv_handle>>,
+    next_id: u64,
+    shutdown: bool,
+    // `None` until the reply for the request was received
+    pending: HashMap<u64, Option<ReplyResult>>,
+}
+
+// The requests of an `Mpv` that are waiting for their reply.
+pub(crate) struct Requests {
+    state: Mutex<State>,
+    // libmpv holds the client's lock while invoking the wakeup callback, so this must never be
+    // locked while calling into libmpv.
+    wakers: Mutex<HashMap<u64, Waker>>,
+}
+
+// Waiting for events of the client is guarded by the `state` lock.
+unsafe impl Send for Requests {}
+unsafe impl Sync for Requests {}
+
+impl Requests {
+    pub(crate) fn new() -> Box<Requests> {
+        Box::new(Requests {
+            state: Mutex::new(State {
+                client: None,
+                next_id: 0,
+                shutdown: false,
+                pending: HashMap::new(),
+            }),
+            wakers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Must be called before the core is destroyed, as `mpv_terminate_destroy` waits for all
+    // clients.
+    pub(crate) fn destroy(&self) {
+        if let Some(client) = self.state.lock().client.take() {
+            unsafe { mpv_sys::mpv_destroy(client.as_ptr()) };
+        }
+    }
+
+    // Starts a request with a new reply id, creating the client on first use.
+    fn submit<F>(&self, mpv: &Mpv, request: F) -> Result<u64>
+    where
+        F: FnOnce(*mut mpv_sys::mpv_handle, u64) -> Result<()>,
+    {
+        mpv.check_shutdown()?;
+        let mut state = self.state.lock();
+        if state.shutdown {
+            return Err(Error::Shutdown);
+        }
+        let client = match state.client {
+            Some(client) => client,
+            None => {
+                let client = self.create_client(mpv)?;
+                state.client = Some(client);
+                client
+            }
+        };
+
+        let id = state.next_id;
+        state.next_id += 1;
+        request(client.as_ptr(), id)?;
+        state.pending.insert(id, None);
+        Ok(id)
+    }
+
+    fn create_client(&self, mpv: &Mpv) -> Result<NonNull<mpv_sys::mpv_handle>> {
+        let client = unsafe { mpv_sys::mpv_create_client(mpv.ctx.as_ptr(), ptr::null()) };
+        let client = NonNull::new(client).ok_or(Error::Null)?;
+        for id in EventId::all().filter(|id| {
+            !matches!(
+                id,
+                EventId::Shutdown
+                    | EventId::CommandReply
+                    | EventId::GetPropertyReply
+                    | EventId::SetPropertyReply
+            )
+        }) {
+            if let Err(e) = mpv_err((), unsafe {
+                mpv_sys::mpv_request_event(client.as_ptr(), id.as_raw(), 0)
+            }) {
+                unsafe { mpv_sys::mpv_destroy(client.as_ptr()) };
+                return Err(e);
+            }
+        }
+        unsafe {
+            mpv_sys::mpv_set_wakeup_callback(
+                client.as_ptr(),
+                Some(wakeup_callback),
+                self as *const Requests as *mut _,
+            );
+        }
+        Ok(client)
+    }
+
+    fn poll(&self, id: u64, waker: &Waker) -> Poll<ReplyResult> {
+        // Register before draining, so that a reply received in between isn't missed.
+        self.wakers.lock().insert(id, waker.clone());
+        let mut state = self.state.lock();
+        state.drain();
+
+        let reply = match state.pending.get_mut(&id) {
+            Some(reply) => reply.take(),
+            None => Some(Err(Error::Shutdown)),
+        };
+        match reply {
+            Some(reply) => {
+                state.pending.remove(&id);
+                drop(state);
+                self.wakers.lock().remove(&id);
+                Poll::Ready(reply)
+            }
+            None if state.shutdown => {
+                state.pending.remove(&id);
+                drop(state);
+                self.wakers.lock().remove(&id);
+                Poll::Ready(Err(Error::Shutdown))
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    // The reply to a dropped request is discarded.
+    fn cancel(&self, id: u64) {
+        self.state.lock().pending.remove(&id);
+        self.wakers.lock().remove(&id);
+    }
+}
+
+impl State {
+    // Stores the replies queued for the client.
+    fn drain(&mut self) {
+        let client = match self.client {
+            Some(client) => client,
+            None => return,
+        };
+        loop {
+            let event = unsafe { &*mpv_sys::mpv_wait_event(client.as_ptr(), 0.) };
+            let reply = match event.event_id {
+                mpv_event_id::None => return,
+                mpv_event_id::Shutdown => {
+                    self.shutdown = true;
+                    continue;
+                }
+                mpv_event_id::CommandReply | mpv_event_id::SetPropertyReply => {
+                    mpv_err(None, event.error)
+                }
+                mpv_event_id::GetPropertyReply => mpv_err((), event.error).and_then(|_| {
+                    let property = unsafe { *(event.data as *mut mpv_sys::mpv_event_property) };
+                    PropertyData::from_raw(property.format, property.data)
+                        .map(|data| Some(data.into_owned()))
+                }),
+                _ => continue,
+            };
+            if let Some(pending) = self.pending.get_mut(&event.reply_userdata) {
+                *pending = Some(reply);
+            }
+        }
+    }
+}
+
+/// Types that can be the result of a `Reply`.
+pub trait FromReply: Sized {
+    #[doc(hidden)]
+    fn from_reply(data: Option<OwnedPropertyData>) -> Result<Self>;
+}
+
+impl FromReply for () {
+    #[inline]
+    fn from_reply(_: Option<OwnedPropertyData>) -> Result<()> {
+        Ok(())
+    }
+}
+
+macro_rules! from_reply {
+    ($($ty:ty => $variant:ident,)*) => {
+        $(
+            impl FromReply for $ty {
+                #[inline]
+                fn from_reply(data: Option<OwnedPropertyData>) -> Result<$ty> {
+                    match data {
+                        Some(OwnedPropertyData::$variant(v)) => Ok(v),
+                        _ => Err(Error::Raw(ErrorCode::PropertyFormat)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_reply! {
+    f64 => Double,
+    i64 => Int64,
+    bool => Flag,
+    String => Str,
+}
+
+enum ReplyState {
+    Pending(u64),
+    Failed(Error),
+    Done,
+}
+
+/// A `Future` that resolves once libmpv replied to an asynchronous request, created by e.g.
+/// `Mpv::command_async`.
+///
+/// Dropping it before it resolved doesn't cancel the request, but discards its reply.
+#[must_use = "futures do nothing unless polled"]
+pub struct Reply<'parent, T> {
+    requests: &'parent Requests,
+    state: ReplyState,
+    // The command or property name, to attach to errors
+    context: String,
+    map_err: fn(&str, Error) -> Error,
+    _result: PhantomData<fn() -> T>,
+}
+
+impl<'parent, T> Reply<'parent, T> {
+    fn new(
+        mpv: &'parent Mpv,
+        id: Result<u64>,
+        context: String,
+        map_err: fn(&str, Error) -> Error,
+    ) -> Reply<'parent, T> {
+        let state = match id {
+            Ok(id) => ReplyState::Pending(id),
+            Err(e) => ReplyState::Failed(map_err(&context, e)),
+        };
+        Reply {
+            requests: &mpv.requests,
+            state,
+            context,
+            map_err,
+            _result: PhantomData,
+        }
+    }
+}
+
+impl<'parent, T> Drop for Reply<'parent, T> {
+    fn drop(&mut self) {
+        if let ReplyState::Pending(id) = self.state {
+            self.requests.cancel(id);
+        }
+    }
+}
+
+impl<'parent, T: FromReply> Future for Reply<'parent, T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>> {
+        let this = self.get_mut();
+        match mem::replace(&mut this.state, ReplyState::Done) {
+            ReplyState::Pending(id) => match this.requests.poll(id, cx.waker()) {
+                Poll::Ready(reply) => Poll::Ready(
+                    reply
+                        .and_then(T::from_reply)
+                        .map_err(|e| (this.map_err)(&this.context, e)),
+                ),
+                Poll::Pending => {
+                    this.state = ReplyState::Pending(id);
+                    Poll::Pending
+                }
+            },
+            ReplyState::Failed(e) => Poll::Ready(Err(e)),
+            ReplyState::Done => panic!("`Reply` polled after completion"),
+        }
+    }
+}
+
+impl Mpv {
+    // --- Asynchronous functions ---
+    //
+
+    #[inline]
+    /// Like `command`, but returns a `Future` that resolves once the command finished, instead of
+    /// blocking until then.
+    ///
+    /// Unlike with `command`, each argument is passed to mpv as is, so it must not be quoted.
+    pub fn command_async(&self, name: &str, args: &[&str]) -> Reply<'_, ()> {
+        let context = args.iter().fold(name.to_owned(), |mut cmd, arg| {
+            cmd.push(' ');
+            cmd.push_str(arg);
+            cmd
+        });
+        let id = self.requests.submit(self, |client, id| {
+            let args = Some(name)
+                .iter()
+                .chain(args)
+                .map(|arg| CString::new(*arg))
+                .collect::<::std::result::Result<Vec<_>, _>>()?;
+            let mut argv = args
+                .iter()
+                .map(|arg| arg.as_ptr())
+                .chain(Some(ptr::null()))
+                .collect::<Vec<_>>();
+            mpv_err((), unsafe {
+                mpv_sys::mpv_command_async(client, id, argv.as_mut_ptr())
+            })
+        });
+        Reply::new(self, id, context, command_err)
+    }
+
+    #[inline]
+    /// Like `set_property`, but returns a `Future` that resolves once the property was set,
+    /// instead of blocking until then.
+    pub fn set_property_async<T: SetData>(&self, name: &str, data: T) -> Reply<'_, ()> {
+        let format = T::get_format().as_mpv_format() as _;
+        let id = self.requests.submit(self, |client, id| {
+            with_c_name(name, |raw| {
+                data.call_as_c_void(|ptr| {
+                    mpv_err((), unsafe {
+                        mpv_sys::mpv_set_property_async(client, id, raw, format, ptr)
+                    })
+                })
+            })
+        });
+        Reply::new(self, id, name.to_owned(), property_err)
+    }
+
+    #[inline]
+    /// Like `get_property`, but returns a `Future` that resolves to the value of the property,
+    /// instead of blocking until it was retrieved.
+    pub fn get_property_async<T: GetData + FromReply>(&self, name: &str) -> Reply<'_, T> {
+        let format = T::get_format().as_mpv_format() as _;
+        let id = self.requests.submit(self, |client, id| {
+            with_c_name(name, |raw| {
+                mpv_err((), unsafe {
+                    mpv_sys::mpv_get_property_async(client, id, raw, format)
+                })
+            })
+        });
+        Reply::new(self, id, name.to_owned(), property_err)
+    }
+}
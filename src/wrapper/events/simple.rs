@@ -16,16 +16,24 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
-use super::mpv_event_id;
+#[cfg(feature = "log_messages")]
+use super::mpv_log_level_as_str;
+use super::{mpv_event_id, EventId, MpvEventId};
 use crate::{wrapper::mpv_err, *};
 
-use std::ffi::CString;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CStr, CString};
 use std::iter::Map;
+use std::marker::PhantomData;
 use std::os::raw as ctype;
 use std::slice;
 use std::slice::Iter;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 /// Data that is returned by both `GetPropertyReply` and `PropertyChange` events.
 pub enum PropertyData<'a> {
@@ -37,7 +45,7 @@ pub enum PropertyData<'a> {
 }
 
 impl<'a> PropertyData<'a> {
-    fn from_raw(format: MpvFormat, ptr: *mut ctype::c_void) -> Result<PropertyData<'a>> {
+    pub(crate) fn from_raw(format: MpvFormat, ptr: *mut ctype::c_void) -> Result<PropertyData<'a>> {
         assert!(!ptr.is_null());
         match format {
             mpv_format::Flag => Ok(PropertyData::Flag(unsafe { *(ptr as *mut bool) })),
@@ -55,6 +63,17 @@ impl<'a> PropertyData<'a> {
             _ => unimplemented!(),
         }
     }
+
+    /// Copy the borrowed data into an `OwnedPropertyData`.
+    pub fn into_owned(self) -> OwnedPropertyData {
+        match self {
+            PropertyData::Str(s) => OwnedPropertyData::Str(s.to_owned()),
+            PropertyData::OsdStr(s) => OwnedPropertyData::OsdStr(s.to_owned()),
+            PropertyData::Flag(b) => OwnedPropertyData::Flag(b),
+            PropertyData::Int64(i) => OwnedPropertyData::Int64(i),
+            PropertyData::Double(d) => OwnedPropertyData::Double(d),
+        }
+    }
 }
 
 // TODO: This could be an existencial type once stable
@@ -65,6 +84,13 @@ pub struct MessageIter<'a>(
     usize,
 );
 
+impl<'a> MessageIter<'a> {
+    // Every pointer has to be a valid C string for `'a`
+    pub(crate) unsafe fn new(args: &'a [*const i8]) -> MessageIter<'a> {
+        MessageIter(args.iter().map(|msg| mpv_cstr_to_str!(*msg)), args.len())
+    }
+}
+
 impl<'a> Iterator for MessageIter<'a> {
     type Item = Result<&'a str>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -91,7 +117,8 @@ impl<'a> PartialEq for MessageIter<'a> {
 #[allow(missing_docs)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Event<'a> {
-    /// Received when the player is shutting down
+    /// Received when the player is shutting down. Afterwards, requests to the `Mpv` return
+    /// `Err(Error::Shutdown)` instead of calling into the dying core.
     Shutdown,
     /// *Has not been tested*, received when explicitly asked to MPV
     LogMessage {
@@ -121,10 +148,14 @@ pub enum Event<'a> {
     Unpause,
     Tick,
     ClientMessage(MessageIter<'a>),
+    /// Received when the video output or filter chain was reconfigured, e.g. because the video
+    /// size changed. Query `dwidth` and `dheight` to find out the new display size.
     VideoReconfig,
+    /// Like `VideoReconfig`, but for the audio output and filter chain
     AudioReconfig,
     /// The player changed current position
     Seek,
+    /// Received when playback (re)starts after a seek or after a file was loaded
     PlaybackRestart,
     /// Received when used with observe_property
     PropertyChange {
@@ -132,34 +163,658 @@ pub enum Event<'a> {
         change: PropertyData<'a>,
         reply_userdata: u64,
     },
-    /// Received when the Event Queue is full
+    /// Received when the event queue is full, in which case events were dropped.
+    /// Observed properties may be stale afterwards, use `EventContext::resync` to recover.
     QueueOverflow,
     /// A deprecated or unknown event
-    Deprecated(super::EventId),
+    Deprecated(super::MpvEventId),
+}
+
+impl<'a> Event<'a> {
+    #[inline]
+    /// Whether this is the change of `eof-reached` to `true` that is observed by
+    /// `EventContext::observe_eof_reached`, i.e. playback paused at the end of the file.
+    pub fn is_eof_reached(&self) -> bool {
+        match *self {
+            Event::PropertyChange {
+                name: "eof-reached",
+                change: PropertyData::Flag(eof),
+                ..
+            } => eof,
+            _ => false,
+        }
+    }
+
+    /// Copy all borrowed data into an `OwnedEvent`, so that it can outlive the next call to
+    /// `EventContext::wait_event`.
+    ///
+    /// Returns `Err(Error::InvalidUtf8)` if a message of `Event::ClientMessage` was invalid utf-8.
+    pub fn into_owned(self) -> Result<OwnedEvent> {
+        Ok(match self {
+            Event::Shutdown => OwnedEvent::Shutdown,
+            Event::LogMessage {
+                prefix,
+                level,
+                text,
+                log_level,
+            } => OwnedEvent::LogMessage {
+                prefix: prefix.to_owned(),
+                level: level.to_owned(),
+                text: text.to_owned(),
+                log_level,
+            },
+            Event::GetPropertyReply {
+                name,
+                result,
+                reply_userdata,
+            } => OwnedEvent::GetPropertyReply {
+                name: name.to_owned(),
+                result: result.into_owned(),
+                reply_userdata,
+            },
+            Event::SetPropertyReply(u) => OwnedEvent::SetPropertyReply(u),
+            Event::CommandReply(u) => OwnedEvent::CommandReply(u),
+            Event::StartFile => OwnedEvent::StartFile,
+            Event::EndFile(r) => OwnedEvent::EndFile(r),
+            Event::FileLoaded => OwnedEvent::FileLoaded,
+            Event::Idle => OwnedEvent::Idle,
+            Event::Unpause => OwnedEvent::Unpause,
+            Event::Tick => OwnedEvent::Tick,
+            Event::ClientMessage(iter) => OwnedEvent::ClientMessage(
+                iter.map(|msg| msg.map(str::to_owned))
+                    .collect::<Result<_>>()?,
+            ),
+            Event::VideoReconfig => OwnedEvent::VideoReconfig,
+            Event::AudioReconfig => OwnedEvent::AudioReconfig,
+            Event::Seek => OwnedEvent::Seek,
+            Event::PlaybackRestart => OwnedEvent::PlaybackRestart,
+            Event::PropertyChange {
+                name,
+                change,
+                reply_userdata,
+            } => OwnedEvent::PropertyChange {
+                name: name.to_owned(),
+                change: change.into_owned(),
+                reply_userdata,
+            },
+            Event::QueueOverflow => OwnedEvent::QueueOverflow,
+            Event::Deprecated(id) => OwnedEvent::Deprecated(id),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+// Serialized like the `OwnedEvent` it converts into, which can be deserialized
+impl<'a> serde::Serialize for Event<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> ::std::result::Result<S::Ok, S::Error> {
+        let owned = self
+            .clone()
+            .into_owned()
+            .map_err(serde::ser::Error::custom)?;
+        serde::Serialize::serialize(&owned, serializer)
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An owned version of `Event`, see `Event::into_owned`.
+pub enum OwnedEvent {
+    Shutdown,
+    LogMessage {
+        prefix: String,
+        level: String,
+        text: String,
+        log_level: LogLevel,
+    },
+    GetPropertyReply {
+        name: String,
+        result: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    SetPropertyReply(u64),
+    CommandReply(u64),
+    StartFile,
+    EndFile(EndFileReason),
+    FileLoaded,
+    Idle,
+    Unpause,
+    Tick,
+    ClientMessage(Vec<String>),
+    VideoReconfig,
+    AudioReconfig,
+    Seek,
+    PlaybackRestart,
+    PropertyChange {
+        name: String,
+        change: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    QueueOverflow,
+    Deprecated(super::MpvEventId),
 }
 
 impl Mpv {
+    #[inline]
+    /// Create a context with which events can be waited on and properties observed.
+    ///
+    /// Returns `None` if a context already exists.
+    pub fn create_event_context(&self) -> Option<EventContext> {
+        if self
+            .events_guard
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            None
+        } else {
+            Some(EventContext::new(self))
+        }
+    }
+
+    /// Block until an event of kind `id` is received, or `timeout` has passed, without the need
+    /// to set up an `EventContext`. Any other events, including those queued before the call,
+    /// are discarded. `None` waits indefinitely.
+    ///
+    /// Returns `Ok(false)` on timeout, and the error of the awaited event if it failed, e.g.
+    /// `EndFile` if the file couldn't be played. Returns `Err(Error::Shutdown)` if the core shut
+    /// down first, and `Err(Error::EventContextExists)` if an `EventContext` exists.
+    pub fn wait_for_event(&self, id: EventId, timeout: Option<Duration>) -> Result<bool> {
+        let mut ctx = self
+            .create_event_context()
+            .ok_or(Error::EventContextExists)?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        ctx.wait_for(&[id], deadline).map(|id| id.is_some())
+    }
+
+    /// Replace the playlist with `source`, which may be a file or URL, unpause, and block until
+    /// the file is loaded, or `timeout` has passed. `None` waits indefinitely.
+    ///
+    /// Returns `Ok(false)` on timeout, and the error mpv reported if the file couldn't be
+    /// played. Otherwise, see `wait_for_event`, which can be used to wait for the end of playback.
+    pub fn play<S: ToUri + ?Sized>(&self, source: &S, timeout: Option<Duration>) -> Result<bool> {
+        let mut ctx = self
+            .create_event_context()
+            .ok_or(Error::EventContextExists)?;
+        // Don't mistake the events of a previous file for those of this one
+        ctx.wait_for(&[], Some(Instant::now()))?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        self.loadfile(source, FileState::Replace, None)?;
+        self.unpause()?;
+        loop {
+            // An `EndFile` without error means a playlist or redirect was resolved
+            match ctx.wait_for(&[EventId::FileLoaded, EventId::EndFile], deadline)? {
+                Some(EventId::FileLoaded) => return Ok(true),
+                Some(_) => continue,
+                None => return Ok(false),
+            }
+        }
+    }
+
+    #[inline]
+    /// Block until the current file stops playing, see `wait_for_event`.
+    pub fn wait_for_playback_end(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.wait_for_event(EventId::EndFile, timeout)
+    }
+
+    #[inline]
+    /// Block until the player is idle, i.e. has nothing left to play, see `wait_for_event`.
+    /// Returns immediately if it already is.
+    ///
+    /// This requires the `idle` option to be enabled, otherwise mpv shuts down instead.
+    pub fn wait_until_idle(&self, timeout: Option<Duration>) -> Result<bool> {
+        if self.get_property("idle-active")? {
+            return Ok(true);
+        }
+        self.wait_for_event(EventId::Idle, timeout)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+/// An owned version of `PropertyData`.
+pub enum OwnedPropertyData {
+    Str(String),
+    OsdStr(String),
+    Flag(bool),
+    Int64(i64),
+    Double(ctype::c_double),
+}
+
+impl OwnedPropertyData {
+    /// Borrow this as `PropertyData`.
+    pub fn as_property_data(&self) -> PropertyData {
+        match *self {
+            OwnedPropertyData::Str(ref s) => PropertyData::Str(s),
+            OwnedPropertyData::OsdStr(ref s) => PropertyData::OsdStr(s),
+            OwnedPropertyData::Flag(b) => PropertyData::Flag(b),
+            OwnedPropertyData::Int64(i) => PropertyData::Int64(i),
+            OwnedPropertyData::Double(d) => PropertyData::Double(d),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SyntheticChange {
+    name: String,
+    data: OwnedPropertyData,
+    reply_userdata: u64,
+}
+
+// A property observed with `observe_throttled`
+#[derive(Debug)]
+struct Throttle {
+    name: String,
+    id: u64,
+    interval: Duration,
+    // When the last change was returned
+    last: Option<Instant>,
+    // The latest change that was held back
+    pending: Option<OwnedPropertyData>,
+}
+
+impl Throttle {
+    // When the held back change should be returned
+    fn due(&self) -> Option<Instant> {
+        match (self.pending.as_ref(), self.last) {
+            (Some(_), Some(last)) => Some(last + self.interval),
+            _ => None,
+        }
+    }
+}
+
+type ScriptMessageHandler<'parent> = Box<dyn FnMut(&[&str]) + Send + 'parent>;
+
+/// This context holds state relevant to event handling, like observed properties.
+/// It is created by calling `Mpv::create_event_context`.
+///
+/// Only one context can exist per `Mpv` at a time, which makes waiting for events safe.
+/// Once the context is dropped, all properties observed through it are unobserved again.
+///
+/// The context can be moved to a dedicated event thread, but it isn't `Sync`, as libmpv only
+/// allows one thread to wait for the events of a handle. The `Mpv` itself stays usable from
+/// any thread. As the context owns all of its state, handling events doesn't take any locks.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<mpv::events::simple::EventContext>();
+/// ```
+pub struct EventContext<'parent> {
+    pub(super) mpv: &'parent Mpv,
+    observed: Vec<(String, Format, u64)>,
+    synthetic: VecDeque<SyntheticChange>,
+    current_synthetic: Option<SyntheticChange>,
+    throttled: Vec<Throttle>,
+    filter: Option<Vec<EventId>>,
+    script_handlers: HashMap<String, ScriptMessageHandler<'parent>>,
+    #[cfg(feature = "log_messages")]
+    forward_logs: bool,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<'parent> Drop for EventContext<'parent> {
+    fn drop(&mut self) {
+        for &(_, _, id) in &self.observed {
+            unsafe { mpv_sys::mpv_unobserve_property(self.mpv.ctx.as_ptr(), id) };
+        }
+        self.mpv.events_guard.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(feature = "log_messages")]
+unsafe fn forward_log_message(event: &mpv_sys::mpv_event) {
+    let message = &*(event.data as *const mpv_sys::mpv_event_log_message);
+    let level = match message.log_level {
+        mpv_log_level::Fatal | mpv_log_level::Error => log::Level::Error,
+        mpv_log_level::Warn => log::Level::Warn,
+        mpv_log_level::Info => log::Level::Info,
+        mpv_log_level::V => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    let prefix = CStr::from_ptr(message.prefix).to_string_lossy();
+    let text = CStr::from_ptr(message.text).to_string_lossy();
+    // mpv terminates every message with a newline
+    log::log!(target: &prefix, level, "{}", text.trim_end());
+}
+
+// `slice::from_raw_parts` requires a non-null pointer, but mpv may pass a null `args` for
+// messages without arguments
+unsafe fn client_message_args(
+    message: &mpv_sys::mpv_event_client_message,
+) -> &[*const ctype::c_char] {
+    if message.num_args <= 0 || message.args.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(message.args, message.num_args as _)
+    }
+}
+
+impl<'parent> EventContext<'parent> {
+    fn new(mpv: &'parent Mpv) -> EventContext<'parent> {
+        EventContext {
+            mpv,
+            observed: Vec::with_capacity(10),
+            synthetic: VecDeque::new(),
+            current_synthetic: None,
+            throttled: Vec::new(),
+            filter: None,
+            script_handlers: HashMap::new(),
+            #[cfg(feature = "log_messages")]
+            forward_logs: false,
+            _not_sync: PhantomData,
+        }
+    }
+
+    fn is_filtered(&self, id: MpvEventId) -> bool {
+        match (self.filter.as_ref(), EventId::from_raw(id)) {
+            (Some(filter), Some(id)) => id != EventId::Shutdown && !filter.contains(&id),
+            _ => false,
+        }
+    }
+
+    /// Only receive the given events, both by disabling all other events in libmpv, and by
+    /// dropping events that are delivered regardless, e.g. replies to asynchronous requests.
+    ///
+    /// `Event::Shutdown` can not be filtered.
+    pub fn set_event_filter(&mut self, events: &[EventId]) -> Result<()> {
+        for id in EventId::all().filter(|id| *id != EventId::Shutdown) {
+            if events.contains(&id) {
+                self.mpv.enable_event(id)?;
+            } else {
+                self.mpv.disable_event(id)?;
+            }
+        }
+        self.filter = Some(events.to_vec());
+        Ok(())
+    }
+
+    #[cfg(feature = "log_messages")]
+    /// Request log messages of at least `min_level`, and republish them through the `log` crate
+    /// instead of returning them from `wait_event`. The prefix of a message, i.e. the mpv module
+    /// it originates from, is used as target, e.g. `ffmpeg/demuxer`.
+    ///
+    /// Passing `mpv_log_level::None` stops the forwarding.
+    pub fn forward_log_messages(&mut self, min_level: LogLevel) -> Result<()> {
+        let level = mpv_log_level_as_str(min_level).ok_or(Error::InvalidArgument)?;
+        let level = CString::new(level)?;
+        mpv_err((), unsafe {
+            mpv_sys::mpv_request_log_messages(self.mpv.ctx.as_ptr(), level.as_ptr())
+        })?;
+        self.forward_logs = min_level != mpv_log_level::None;
+        Ok(())
+    }
+
+    /// Call `handler` with the remaining arguments of every `ClientMessage` whose first argument
+    /// is `name`, e.g. one sent by a Lua script with
+    /// `mp.commandv("script-message-to", "main", name, ...)`, instead of returning it from
+    /// `wait_event`. A handler registered before under the same `name` is replaced.
+    ///
+    /// Handlers only run while `wait_event` is called.
+    pub fn on_script_message<F>(&mut self, name: &str, handler: F)
+    where
+        F: FnMut(&[&str]) + Send + 'parent,
+    {
+        self.script_handlers
+            .insert(name.to_owned(), Box::new(handler));
+    }
+
+    #[inline]
+    /// Remove the handler registered for `name`, returning whether there was one.
+    pub fn remove_script_message_handler(&mut self, name: &str) -> bool {
+        self.script_handlers.remove(name).is_some()
+    }
+
+    /// Remove a filter set by `set_event_filter`, enabling all, except deprecated, events.
+    pub fn clear_event_filter(&mut self) -> Result<()> {
+        self.mpv.enable_all_events()?;
+        self.filter = None;
+        Ok(())
+    }
+
     /// Wait for `timeout` seconds for an `Event`. Passing `0` as `timeout` will poll.
     /// For more information, as always, see the mpv-sys docs of `mpv_wait_event`.
     ///
-    /// Returns `Some(Err(...))` if there was invalid utf-8, or if either an
-    /// `MPV_EVENT_GET_PROPERTY_REPLY`, `MPV_EVENT_SET_PROPERTY_REPLY`, `MPV_EVENT_COMMAND_REPLY`,
-    /// or `MPV_EVENT_PROPERTY_CHANGE` event failed, or if `MPV_EVENT_END_FILE` reported an error.
+    /// Property changes queued by `resync` are returned before any new event is waited for.
+    /// Events excluded by `set_event_filter` are skipped without resetting the timeout.
     ///
-    /// # Safety
-    /// An internally used API function is not thread-safe, thus using this method from multiple
-    /// threads is UB.
-    pub unsafe fn wait_event(&self, timeout: f64) -> Option<Result<Event>> {
-        let event = &*mpv_sys::mpv_wait_event(self.ctx.as_ptr(), timeout);
-        if event.event_id != mpv_event_id::None {
-            if let Err(e) = mpv_err((), event.error) {
-                return Some(Err(e));
+    /// Returns `None` if no event was received before the timeout, and `Some(Err(...))` if there
+    /// was invalid utf-8, or if either an `MPV_EVENT_GET_PROPERTY_REPLY`,
+    /// `MPV_EVENT_SET_PROPERTY_REPLY`, `MPV_EVENT_COMMAND_REPLY`, or `MPV_EVENT_PROPERTY_CHANGE`
+    /// event failed, or if `MPV_EVENT_END_FILE` reported an error.
+    pub fn wait_event(&mut self, timeout: f64) -> Option<Result<Event>> {
+        self.next_event(timeout, false)
+    }
+
+    // Like `wait_event`, but keeps waiting after events without information if `skip_empty` is
+    // set, so that draining only stops once the queue is empty.
+    pub(super) fn next_event(&mut self, timeout: f64, skip_empty: bool) -> Option<Result<Event>> {
+        if self.is_filtered(mpv_event_id::PropertyChange) {
+            self.synthetic.clear();
+        }
+        self.queue_due_changes();
+        if !self.synthetic.is_empty() {
+            return self.pop_synthetic().map(Ok);
+        }
+
+        // The returned event borrows `self` mutably, so it can't outlive the next call, which is
+        // when libmpv frees its data. Only one `EventContext` can exist, so nobody else waits.
+        let start = Instant::now();
+        loop {
+            let mut remaining = if timeout > 0. {
+                let elapsed = start.elapsed();
+                let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+                (timeout - elapsed).max(0.)
+            } else {
+                timeout
+            };
+            // Wake up in time to return changes held back by `observe_throttled`
+            let mut throttled = false;
+            if let Some(due) = self.throttled.iter().filter_map(Throttle::due).min() {
+                let until = due.saturating_duration_since(Instant::now()).as_secs_f64();
+                if remaining < 0. || until < remaining {
+                    remaining = until;
+                    throttled = true;
+                }
+            }
+            let event = unsafe { &*mpv_sys::mpv_wait_event(self.mpv.ctx.as_ptr(), remaining) };
+            if event.event_id == mpv_event_id::None {
+                self.queue_due_changes();
+                if !self.synthetic.is_empty() {
+                    return self.pop_synthetic().map(Ok);
+                } else if throttled && (timeout < 0. || start.elapsed().as_secs_f64() < timeout) {
+                    continue;
+                }
+                return None;
+            } else if self.is_filtered(event.event_id) {
+                continue;
+            }
+            #[cfg(feature = "log_messages")]
+            {
+                if self.forward_logs && event.event_id == mpv_event_id::LogMessage {
+                    unsafe { forward_log_message(event) };
+                    continue;
+                }
+            }
+            if event.event_id == mpv_event_id::ClientMessage
+                && unsafe { self.dispatch_script_message(event) }
+            {
+                continue;
+            }
+            if event.event_id == mpv_event_id::PropertyChange && unsafe { self.hold_back(event) } {
+                continue;
+            }
+            match unsafe { self.convert_event(event) } {
+                None if skip_empty => continue,
+                ev => return ev,
             }
         }
+    }
+
+    fn pop_synthetic(&mut self) -> Option<Event> {
+        self.current_synthetic = Some(self.synthetic.pop_front()?);
+        let change = self.current_synthetic.as_ref()?;
+        Some(Event::PropertyChange {
+            name: &change.name,
+            change: change.data.as_property_data(),
+            reply_userdata: change.reply_userdata,
+        })
+    }
+
+    // Queues the latest changes held back by `observe_throttled` whose interval has passed
+    fn queue_due_changes(&mut self) {
+        let filtered = self.is_filtered(mpv_event_id::PropertyChange);
+        let now = Instant::now();
+        for throttle in &mut self.throttled {
+            match throttle.due() {
+                Some(_) if filtered => throttle.pending = None,
+                Some(due) if due <= now => {
+                    throttle.last = Some(now);
+                    if let Some(data) = throttle.pending.take() {
+                        self.synthetic.push_back(SyntheticChange {
+                            name: throttle.name.clone(),
+                            data,
+                            reply_userdata: throttle.id,
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Returns whether the change was held back, as it came too soon after the last one of a
+    // property observed with `observe_throttled`.
+    unsafe fn hold_back(&mut self, event: &mpv_sys::mpv_event) -> bool {
+        if self.throttled.is_empty() || event.error < 0 {
+            return false;
+        }
+        let property = &*(event.data as *const mpv_sys::mpv_event_property);
+        let name = match mpv_cstr_to_str!(property.name) {
+            Ok(name) if property.format != mpv_format::None => name,
+            _ => return false,
+        };
+        let throttle = match self
+            .throttled
+            .iter_mut()
+            .find(|throttle| throttle.id == event.reply_userdata && throttle.name == name)
+        {
+            Some(throttle) => throttle,
+            None => return false,
+        };
+
+        let now = Instant::now();
+        match throttle.last {
+            Some(last) if now < last + throttle.interval => {
+                match PropertyData::from_raw(property.format, property.data) {
+                    Ok(data) => {
+                        throttle.pending = Some(data.into_owned());
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            _ => {
+                throttle.last = Some(now);
+                throttle.pending = None;
+                false
+            }
+        }
+    }
+
+    /// Append every pending event to `events` without waiting, e.g. once per frame of a UI.
+    /// Returns the number of events appended.
+    ///
+    /// The events are converted to `OwnedEvent`s, as the data of an `Event` is only valid until
+    /// the next event is waited for. Draining stops after `OwnedEvent::Shutdown`.
+    pub fn drain_into(&mut self, events: &mut Vec<Result<OwnedEvent>>) -> usize {
+        let len = events.len();
+        while let Some(event) = self.next_event(0., true) {
+            let shutdown = matches!(event, Ok(Event::Shutdown));
+            events.push(event.and_then(Event::into_owned));
+            if shutdown {
+                break;
+            }
+        }
+        events.len() - len
+    }
+
+    #[inline]
+    /// Like `drain_into`, but returns the events in a new `Vec`.
+    pub fn drain(&mut self) -> Vec<Result<OwnedEvent>> {
+        let mut events = Vec::new();
+        self.drain_into(&mut events);
+        events
+    }
+
+    // Returns whether a handler registered with `on_script_message` consumed the message
+    unsafe fn dispatch_script_message(&mut self, event: &mpv_sys::mpv_event) -> bool {
+        if self.script_handlers.is_empty() {
+            return false;
+        }
+        let message = &*(event.data as *const mpv_sys::mpv_event_client_message);
+        let args = client_message_args(message)
+            .iter()
+            .map(|arg| CStr::from_ptr(*arg).to_str())
+            .collect::<::std::result::Result<Vec<_>, _>>();
+        // Invalid UTF-8 is reported by `wait_event` instead
+        let args = match args {
+            Ok(args) => args,
+            Err(_) => return false,
+        };
+        match args.split_first() {
+            Some((name, args)) => match self.script_handlers.get_mut(*name) {
+                Some(handler) => {
+                    handler(args);
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    // Returns the kind of the first of the `ids` received before `deadline`, or `None`.
+    fn wait_for(&mut self, ids: &[EventId], deadline: Option<Instant>) -> Result<Option<EventId>> {
+        loop {
+            let remaining = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64(),
+                None => -1.,
+            };
+            let event = unsafe { &*mpv_sys::mpv_wait_event(self.mpv.ctx.as_ptr(), remaining) };
+            if let Some(id) = ids.iter().find(|id| id.as_raw() == event.event_id) {
+                return match unsafe { self.convert_event(event) } {
+                    Some(Err(e)) => Err(e),
+                    _ => Ok(Some(*id)),
+                };
+            } else if event.event_id == mpv_event_id::Shutdown {
+                self.mpv.shutdown.store(true, Ordering::Release);
+                return Err(Error::Shutdown);
+            } else if event.event_id == mpv_event_id::None
+                && matches!(deadline, Some(deadline) if Instant::now() >= deadline)
+            {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Returns `None` if the event carries no useful information.
+    unsafe fn convert_event<'a>(&self, event: &'a mpv_sys::mpv_event) -> Option<Result<Event<'a>>> {
+        if let Err(e) = mpv_err((), event.error) {
+            return Some(Err(e));
+        }
 
         match event.event_id {
             mpv_event_id::None => None,
-            mpv_event_id::Shutdown => Some(Ok(Event::Shutdown)),
+            mpv_event_id::Shutdown => {
+                self.mpv.shutdown.store(true, Ordering::Release);
+                Some(Ok(Event::Shutdown))
+            }
             mpv_event_id::LogMessage => {
                 let log_message = *(event.data as *mut mpv_sys::mpv_event_log_message);
                 Some(mpv_cstr_to_str!(log_message.prefix).and_then(|prefix| {
@@ -205,12 +860,9 @@ impl Mpv {
             mpv_event_id::Idle => Some(Ok(Event::Idle)),
             mpv_event_id::Tick => Some(Ok(Event::Tick)),
             mpv_event_id::ClientMessage => {
-                let client_message = *(event.data as *mut mpv_sys::mpv_event_client_message);
-                Some(Ok(Event::ClientMessage(MessageIter(
-                    slice::from_raw_parts_mut(client_message.args, client_message.num_args as _)
-                        .iter()
-                        .map(|msg| mpv_cstr_to_str!(*msg)),
-                    client_message.num_args as _,
+                let client_message = &*(event.data as *const mpv_sys::mpv_event_client_message);
+                Some(Ok(Event::ClientMessage(MessageIter::new(
+                    client_message_args(client_message),
                 ))))
             }
             mpv_event_id::VideoReconfig => Some(Ok(Event::VideoReconfig)),
@@ -241,22 +893,89 @@ impl Mpv {
 
     /// Observe `name` property for changes. `id` can be used to unobserve this (or many) properties
     /// again.
-    pub fn observe_property(&self, name: &str, format: Format, id: u64) -> Result<()> {
-        let name = CString::new(name)?;
+    pub fn observe_property(&mut self, name: &str, format: Format, id: u64) -> Result<()> {
+        self.mpv.check_shutdown()?;
+        let c_name = CString::new(name)?;
         mpv_err((), unsafe {
             mpv_sys::mpv_observe_property(
-                self.ctx.as_ptr(),
+                self.mpv.ctx.as_ptr(),
                 id,
-                name.as_ptr(),
+                c_name.as_ptr(),
                 format.as_mpv_format() as _,
             )
-        })
+        })?;
+        self.observed.push((name.to_owned(), format, id));
+        Ok(())
+    }
+
+    #[inline]
+    /// Observe the `eof-reached` property, which becomes `true` once playback paused at the end
+    /// of a file because of `Mpv::set_keep_open`, e.g. to show a replay button. The changes can
+    /// be recognized with `Event::is_eof_reached`.
+    pub fn observe_eof_reached(&mut self, id: u64) -> Result<()> {
+        self.observe_property("eof-reached", Format::Flag, id)
+    }
+
+    /// Like `observe_property`, but changes that come within `interval` of the last returned
+    /// one are held back, and only the latest of them is returned once `interval` has passed.
+    /// This keeps properties like `time-pos` from waking the event loop at every frame.
+    ///
+    /// A stream created by `into_stream` only returns held back changes once it is woken by
+    /// another event.
+    pub fn observe_throttled(
+        &mut self,
+        name: &str,
+        format: Format,
+        id: u64,
+        interval: Duration,
+    ) -> Result<()> {
+        self.observe_property(name, format, id)?;
+        self.throttled.push(Throttle {
+            name: name.to_owned(),
+            id,
+            interval,
+            last: None,
+            pending: None,
+        });
+        Ok(())
     }
 
     /// Unobserve any property associated with `id`.
-    pub fn unobserve_property(&self, id: u64) -> Result<()> {
+    pub fn unobserve_property(&mut self, id: u64) -> Result<()> {
+        self.mpv.check_shutdown()?;
         mpv_err((), unsafe {
-            mpv_sys::mpv_unobserve_property(self.ctx.as_ptr(), id)
-        })
+            mpv_sys::mpv_unobserve_property(self.mpv.ctx.as_ptr(), id)
+        })?;
+        self.observed.retain(|&(_, _, oid)| oid != id);
+        self.throttled.retain(|throttle| throttle.id != id);
+        Ok(())
+    }
+
+    /// Re-read all currently observed properties, and queue a `PropertyChange` event for each of
+    /// them, which will be returned by the next calls to `wait_event`.
+    ///
+    /// Use this after receiving `Event::QueueOverflow`, as property changes may have been lost.
+    /// Properties that are currently unavailable are skipped.
+    pub fn resync(&mut self) -> Result<()> {
+        let mut changes = Vec::with_capacity(self.observed.len());
+        for &(ref name, format, id) in &self.observed {
+            let data = match format {
+                Format::String => self.mpv.get_property(name).map(OwnedPropertyData::Str),
+                Format::Flag => self.mpv.get_property(name).map(OwnedPropertyData::Flag),
+                Format::Int64 => self.mpv.get_property(name).map(OwnedPropertyData::Int64),
+                Format::Double => self.mpv.get_property(name).map(OwnedPropertyData::Double),
+            };
+            match data {
+                Ok(data) => changes.push(SyntheticChange {
+                    name: name.clone(),
+                    data,
+                    reply_userdata: id,
+                }),
+                Err(ref e) if e.code() == Some(ErrorCode::PropertyUnavailable) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.synthetic.extend(changes);
+        Ok(())
     }
 }
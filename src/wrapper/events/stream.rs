@@ -0,0 +1,98 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use futures_core::stream::Stream;
+use parking_lot::Mutex;
+
+use super::simple::{Event, EventContext, OwnedEvent};
+use crate::*;
+
+use std::os::raw as ctype;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll, Waker};
+
+unsafe extern "C" fn wakeup_callback(d: *mut ctype::c_void) {
+    let waker = (*(d as *const Mutex<Option<Waker>>)).lock().take();
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+impl<'parent> EventContext<'parent> {
+    #[inline]
+    /// Turn this context into a `Stream` of events.
+    ///
+    /// The stream is woken directly by libmpv's wakeup callback and drains events when polled,
+    /// so no thread is spawned. This replaces any other wakeup callback of the `Mpv`.
+    pub fn into_stream(self) -> EventStream<'parent> {
+        let waker = Box::new(Mutex::new(None));
+        unsafe {
+            mpv_sys::mpv_set_wakeup_callback(
+                self.mpv.ctx.as_ptr(),
+                Some(wakeup_callback),
+                &*waker as *const Mutex<Option<Waker>> as *mut _,
+            );
+        }
+        EventStream {
+            ctx: self,
+            waker,
+            finished: false,
+        }
+    }
+}
+
+/// A `Stream` over the events of an `EventContext`, created by `EventContext::into_stream`.
+///
+/// The stream ends after `OwnedEvent::Shutdown` was yielded.
+pub struct EventStream<'parent> {
+    ctx: EventContext<'parent>,
+    waker: Box<Mutex<Option<Waker>>>,
+    finished: bool,
+}
+
+impl<'parent> Drop for EventStream<'parent> {
+    fn drop(&mut self) {
+        // libmpv holds a lock while invoking the callback, so it won't be called after this.
+        unsafe {
+            mpv_sys::mpv_set_wakeup_callback(self.ctx.mpv.ctx.as_ptr(), None, ptr::null_mut());
+        }
+    }
+}
+
+impl<'parent> Stream for EventStream<'parent> {
+    type Item = Result<OwnedEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        // Register before draining, so that an event queued in between isn't missed.
+        *this.waker.lock() = Some(cx.waker().clone());
+        match this.ctx.next_event(0., true) {
+            Some(Ok(Event::Shutdown)) => {
+                this.finished = true;
+                Poll::Ready(Some(Ok(OwnedEvent::Shutdown)))
+            }
+            Some(ev) => Poll::Ready(Some(ev.and_then(Event::into_owned))),
+            None => Poll::Pending,
+        }
+    }
+}
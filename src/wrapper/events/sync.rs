@@ -16,16 +16,210 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
+use crossbeam_queue::ArrayQueue;
 use parking_lot::{Condvar, Mutex};
 
 use crate::{events::*, wrapper::mpv_err, *};
 
 use std::{
-    collections::HashMap, ffi::CString, marker::PhantomData, os::raw as ctype, ptr::NonNull,
+    collections::HashMap,
+    ffi::CString,
+    marker::PhantomData,
+    os::raw as ctype,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    sync::Arc,
+    time::Duration,
 };
 
+/// The default capacity of the event queue of each `EventIter`, matching mpv's own queue.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1000;
+
+// How long a waiting `EventIter` sleeps at most, in case a notification arrived in between it
+// checking the generation and starting to wait, as notifying doesn't take the lock.
+const LOST_WAKEUP_TIMEOUT: Duration = Duration::from_millis(20);
+
 unsafe extern "C" fn event_callback(d: *mut ctype::c_void) {
-    (*(d as *mut Condvar)).notify_one();
+    let notification = &*(d as *const Notification);
+    notification.notify();
+}
+
+// Wakes up `EventIter`s waiting for events. mpv's wakeup callback notifies without locking, so
+// it never blocks on consumers.
+pub(crate) struct Notification {
+    generation: AtomicU64,
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl Notification {
+    pub(crate) fn new() -> Notification {
+        Notification {
+            generation: AtomicU64::new(0),
+            lock: Mutex::new(()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    fn notify(&self) {
+        self.generation.fetch_add(1, Ordering::Release);
+        self.condvar.notify_all();
+    }
+
+    // Waits until the generation differs from `seen`, or `LOST_WAKEUP_TIMEOUT` has passed.
+    fn wait(&self, seen: u64) {
+        let mut guard = self.lock.lock();
+        if self.generation() == seen {
+            self.condvar.wait_for(&mut guard, LOST_WAKEUP_TIMEOUT);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What happens to a new event if the queue of the `EventIter` observing it is full.
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room for the new one.
+    DropOldest,
+    /// Discard the new event.
+    DropNewest,
+}
+
+// The bounded queue of an `EventIter`.
+pub(crate) struct Queue {
+    events: ArrayQueue<Event>,
+    // The number of events discarded since the `EventIter` last returned
+    dropped: AtomicUsize,
+}
+
+impl Queue {
+    pub(crate) fn new(capacity: usize) -> Queue {
+        Queue {
+            events: ArrayQueue::new(capacity),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<Event> {
+        self.events.pop()
+    }
+
+    // Returns the number of events discarded since the last call
+    pub(crate) fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Ordering::AcqRel)
+    }
+}
+
+#[derive(Clone)]
+// The queue of an `EventIter`, together with the events it observes.
+struct Route {
+    events: Vec<Event>,
+    queue: Arc<Queue>,
+}
+
+/// Distributes the events of mpv's queue to the queues of the `EventIter`s observing them.
+///
+/// Consumers pop from their own lock-free queue. Adding or removing an `EventIter` replaces the
+/// routes as a whole, so draining only holds their lock to clone them, and a consumer that
+/// finds another thread draining waits for it to be notified instead of blocking.
+pub(crate) struct Router {
+    routes: Mutex<Arc<Vec<Route>>>,
+    // Held while draining, as only one thread may wait for mpv's events at a time
+    draining: Mutex<()>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+}
+
+impl Router {
+    pub(crate) fn new(capacity: usize, overflow: OverflowPolicy) -> Router {
+        Router {
+            routes: Mutex::new(Arc::new(Vec::new())),
+            draining: Mutex::new(()),
+            capacity,
+            overflow,
+        }
+    }
+
+    fn add(&self, events: Vec<Event>) -> Arc<Queue> {
+        let queue = Arc::new(Queue::new(self.capacity));
+        let mut routes = self.routes.lock();
+        let mut new = (**routes).clone();
+        new.push(Route {
+            events,
+            queue: queue.clone(),
+        });
+        *routes = Arc::new(new);
+        queue
+    }
+
+    fn remove(&self, queue: &Arc<Queue>) {
+        let mut routes = self.routes.lock();
+        let mut new = (**routes).clone();
+        new.retain(|route| !Arc::ptr_eq(&route.queue, queue));
+        *routes = Arc::new(new);
+    }
+
+    pub(crate) fn push(&self, queue: &Queue, mut event: Event) {
+        while let Err(rejected) = queue.events.push(event) {
+            queue.dropped.fetch_add(1, Ordering::AcqRel);
+            match self.overflow {
+                OverflowPolicy::DropNewest => return,
+                OverflowPolicy::DropOldest => {
+                    queue.events.pop();
+                    event = rejected;
+                }
+            }
+        }
+    }
+
+    // Empties mpv's queue into the queues of the observing `EventIter`s, unless another thread
+    // is already doing so. Returns true if any event was queued for another `EventIter` than
+    // `own`.
+    fn drain(&self, ctx: NonNull<mpv_sys::mpv_handle>, own: &Arc<Queue>) -> bool {
+        let _draining = match self.draining.try_lock() {
+            Some(guard) => guard,
+            None => return false,
+        };
+        let routes = self.routes.lock().clone();
+        let mut others = false;
+        let mut last = false;
+        loop {
+            let event = unsafe { &*mpv_sys::mpv_wait_event(ctx.as_ptr(), 0f32 as _) };
+            let ev_id = event.event_id;
+
+            if ev_id == mpv_event_id::QueueOverflow {
+                // The queue needs to be emptied asap to prevent loss of events
+                // This should happen very rarely, as the queue size is 1k (2016-10-12)
+                break;
+            } else if ev_id == mpv_event_id::None {
+                if last {
+                    break;
+                } else {
+                    last = true;
+                    continue;
+                }
+            }
+            if !routes
+                .iter()
+                .any(|route| route.events.iter().any(|ev| ev.as_id() == ev_id))
+            {
+                continue;
+            }
+
+            let event = Event::from_raw(event);
+            if let Some(route) = routes
+                .iter()
+                .find(|route| route.events.iter().any(|ev| ev.observes(&event)))
+            {
+                others |= !Arc::ptr_eq(&route.queue, own);
+                self.push(&route.queue, event);
+            }
+        }
+        others
+    }
 }
 
 impl Mpv {
@@ -33,8 +227,23 @@ impl Mpv {
     /// Create a new `Mpv`.
     /// The default settings can be probed by running: `$ mpv --show-profile=libmpv`
     ///
-    /// This disables all events.
+    /// This disables all events. Each `EventIter` queues up to `DEFAULT_QUEUE_CAPACITY` events,
+    /// dropping the oldest ones once full, which is reported by `Event::QueueOverflow`.
     pub fn new() -> Result<Mpv> {
+        Mpv::with_event_queue(DEFAULT_QUEUE_CAPACITY, OverflowPolicy::DropOldest)
+    }
+
+    #[inline]
+    /// Create a new `Mpv`, whose `EventIter`s each queue up to `capacity` events, handling
+    /// further events according to `overflow`. Discarded events are reported by
+    /// `Event::QueueOverflow`.
+    ///
+    /// This disables all events.
+    pub fn with_event_queue(capacity: usize, overflow: OverflowPolicy) -> Result<Mpv> {
+        if capacity == 0 {
+            return Err(Error::InvalidArgument);
+        }
+
         let api_version = unsafe { mpv_sys::mpv_client_api_version() };
         if crate::MPV_CLIENT_API_VERSION != api_version {
             return Err(Error::VersionMismatch {
@@ -48,13 +257,13 @@ impl Mpv {
             return Err(Error::Null);
         }
 
-        let (ev_iter_notification, ev_to_observe, ev_to_observe_properties, ev_observed) = {
-            let ev_iter_notification = Box::new((Mutex::new(false), Condvar::new()));
+        let (ev_iter_notification, ev_to_observe, ev_to_observe_properties, ev_router) = {
+            let ev_iter_notification = Box::new(Notification::new());
             unsafe {
                 mpv_sys::mpv_set_wakeup_callback(
                     ctx,
                     Some(event_callback),
-                    &ev_iter_notification.1 as *const Condvar as *mut Condvar as *mut _,
+                    &*ev_iter_notification as *const Notification as *mut _,
                 );
             }
 
@@ -62,12 +271,17 @@ impl Mpv {
                 ev_iter_notification,
                 Mutex::new(Vec::with_capacity(10)),
                 Mutex::new(HashMap::with_capacity(10)),
-                Mutex::new(Vec::with_capacity(15)),
+                Router::new(capacity, overflow),
             )
         };
 
-        for i in 2..24 {
-            if let Err(e) = mpv_err((), unsafe { mpv_sys::mpv_request_event(ctx, i, 0) }) {
+        // Shutdown, QueueOverflow and Hook events are always handled internally.
+        for id in EventId::all().filter(|id| {
+            *id != EventId::Shutdown && *id != EventId::QueueOverflow && *id != EventId::Hook
+        }) {
+            if let Err(e) = mpv_err((), unsafe {
+                mpv_sys::mpv_request_event(ctx, id.as_raw(), 0)
+            }) {
                 unsafe { mpv_sys::mpv_terminate_destroy(ctx) };
                 return Err(e);
             }
@@ -83,9 +297,14 @@ impl Mpv {
             ev_iter_notification,
             ev_to_observe,
             ev_to_observe_properties,
-            ev_observed,
+            ev_router,
+            shutdown: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            requests: events::requests::Requests::new(),
+            #[cfg(feature = "protocols")]
+            protocols: Mutex::new(Vec::new()),
             #[cfg(feature = "protocols")]
-            protocols_guard: AtomicBool::new(false),
+            pending_streams: std::sync::Arc::new(protocol::PendingStreams::new()),
         })
     }
 
@@ -122,7 +341,8 @@ impl Mpv {
                 }
 
                 if let Event::LogMessage { level: lvl, .. } = *elem {
-                    let min_level = CString::new(mpv_log_level_as_str(lvl))?;
+                    let min_level =
+                        CString::new(mpv_log_level_as_str(lvl).ok_or(Error::InvalidArgument)?)?;
                     mpv_err((), unsafe {
                         mpv_sys::mpv_request_log_messages(self.ctx.as_ptr(), min_level.as_ptr())
                     })?;
@@ -162,12 +382,12 @@ impl Mpv {
 
         Ok(EventIter {
             ctx: self.ctx,
-            first_iteration: true,
             notification: &self.ev_iter_notification,
             all_to_observe: &self.ev_to_observe,
             all_to_observe_properties: &self.ev_to_observe_properties,
+            queue: self.ev_router.add(evs.clone()),
             local_to_observe: evs,
-            all_observed: &self.ev_observed,
+            router: &self.ev_router,
             _does_not_outlive: PhantomData::<&Self>,
         })
     }
@@ -192,14 +412,24 @@ pub enum Event {
     FileLoaded,
     Idle,
     Tick,
+    /// The video output or filter chain was reconfigured, e.g. because the video size changed.
     VideoReconfig,
+    /// The audio output or filter chain was reconfigured.
     AudioReconfig,
+    /// The player changed current position.
     Seek,
+    /// Playback (re)started after a seek or after a file was loaded.
     PlaybackRestart,
     PropertyChange {
         name: String,
         data: PropertyData,
     },
+    /// `dropped` events were discarded according to the `OverflowPolicy`, as the queue of the
+    /// `EventIter` was full. This is returned before the remaining events, and doesn't need to
+    /// be observed.
+    QueueOverflow {
+        dropped: usize,
+    },
 }
 
 impl Event {
@@ -221,7 +451,7 @@ impl Event {
         }
     }
 
-    fn as_id(&self) -> EventId {
+    fn as_id(&self) -> MpvEventId {
         match *self {
             Event::LogMessage { .. } => mpv_event_id::LogMessage,
             Event::StartFile => mpv_event_id::StartFile,
@@ -234,6 +464,7 @@ impl Event {
             Event::Seek => mpv_event_id::Seek,
             Event::PlaybackRestart => mpv_event_id::PlaybackRestart,
             Event::PropertyChange { .. } => mpv_event_id::PropertyChange,
+            Event::QueueOverflow { .. } => mpv_event_id::QueueOverflow,
         }
     }
 
@@ -249,11 +480,22 @@ impl Event {
             | (&Event::AudioReconfig, &Event::AudioReconfig)
             | (&Event::Seek, &Event::Seek)
             | (&Event::PlaybackRestart, &Event::PlaybackRestart)
-            | (&Event::PropertyChange { .. }, &Event::PropertyChange { .. }) => true,
+            | (&Event::PropertyChange { .. }, &Event::PropertyChange { .. })
+            | (&Event::QueueOverflow { .. }, &Event::QueueOverflow { .. }) => true,
             _ => false,
         }
     }
 
+    // Whether observing `self` means observing `event`, property changes are matched by name.
+    fn observes(&self, event: &Event) -> bool {
+        match (self, event) {
+            (Event::PropertyChange { name, .. }, Event::PropertyChange { name: ev_name, .. }) => {
+                name == ev_name
+            }
+            _ => self.structural_eq(event),
+        }
+    }
+
     fn from_raw(raw: &mpv_sys::mpv_event) -> Event {
         assert!(mpv_err((), raw.error).is_ok());
         match raw.event_id {
@@ -343,38 +585,27 @@ impl PropertyData {
     }
 }
 
-fn mpv_log_level_as_str(lvl: LogLevel) -> &'static str {
-    match lvl {
-        mpv_log_level::None => "no",
-        mpv_log_level::Fatal => "fatal",
-        mpv_log_level::Error => "error",
-        mpv_log_level::Warn => "warn",
-        mpv_log_level::Info => "info",
-        mpv_log_level::V => "v",
-        mpv_log_level::Debug => "debug",
-        mpv_log_level::Trace => "trace",
-        _ => unreachable!(),
-    }
-}
-
 /// A blocking `Iterator` over some observed events of an `Mpv` instance.
 /// Once the `EventIter` is dropped, it's `Event`s are removed from
 /// the "to be observed" queue, therefore new `Event` invocations won't be observed.
+///
+/// Each `EventIter` has its own bounded queue, see `Mpv::with_event_queue`.
 pub struct EventIter<'parent> {
     ctx: NonNull<mpv_sys::mpv_handle>,
-    first_iteration: bool,
-    notification: &'parent (Mutex<bool>, Condvar),
+    notification: &'parent Notification,
     all_to_observe: &'parent Mutex<Vec<Event>>,
     all_to_observe_properties: &'parent Mutex<HashMap<String, u64>>,
     local_to_observe: Vec<Event>,
-    all_observed: &'parent Mutex<Vec<Event>>,
+    router: &'parent Router,
+    queue: Arc<Queue>,
     _does_not_outlive: PhantomData<&'parent Mpv>,
 }
 
 impl<'parent> Drop for EventIter<'parent> {
     fn drop(&mut self) {
+        self.router.remove(&self.queue);
+
         let mut all_to_observe = self.all_to_observe.lock();
-        let mut all_observed = self.all_observed.lock();
         let mut all_to_observe_properties = self.all_to_observe_properties.lock();
 
         // Returns true if outer and inner event match, if so, the event is unobserved.
@@ -413,7 +644,6 @@ impl<'parent> Drop for EventIter<'parent> {
         // This removes all events for which compare_ev_unobserve returns true.
         for outer_ev in &self.local_to_observe {
             all_to_observe.retain(|inner_ev| !compare_ev_unobserve(outer_ev, inner_ev));
-            all_observed.retain(|inner_ev| !compare_ev_unobserve(outer_ev, inner_ev));
         }
     }
 }
@@ -424,84 +654,26 @@ impl<'parent> Iterator for EventIter<'parent> {
     fn next(&mut self) -> Option<Self::Item> {
         // Loop until some events can be returned
         loop {
-            let mut observed = self.all_observed.lock();
-            if observed.is_empty() && !self.first_iteration {
-                drop(observed);
-                self.notification.1.wait(&mut self.notification.0.lock());
-                observed = self.all_observed.lock();
-            }
+            // Read before draining, so that events arriving meanwhile are not waited for
+            let generation = self.notification.generation();
 
-            let mut ret_events = Vec::with_capacity(observed.len());
-            if observed.is_empty() || self.first_iteration {
-                let all_to_observe = self.all_to_observe.lock();
-                let mut last = false;
-                'events: loop {
-                    let event = unsafe { &*mpv_sys::mpv_wait_event(self.ctx.as_ptr(), 0f32 as _) };
-                    let ev_id = event.event_id;
-
-                    if ev_id == mpv_event_id::QueueOverflow {
-                        // The queue needs to be emptied asap to prevent loss of events
-                        // This should happen very rarely, as the queue size is 1k (2016-10-12)
-                        break;
-                    } else if ev_id == mpv_event_id::None {
-                        if last {
-                            break;
-                        } else {
-                            last = true;
-                            continue;
-                        }
-                    }
-                    for local_ob_ev in &self.local_to_observe {
-                        if ev_id == local_ob_ev.as_id() {
-                            ret_events.push(Event::from_raw(event));
-                            continue 'events;
-                        }
-                    }
-                    for all_ob_ev in &*all_to_observe {
-                        if ev_id == all_ob_ev.as_id() {
-                            observed.push(Event::from_raw(event));
-                            continue 'events;
-                        }
-                    }
-                }
-                if !observed.is_empty() {
-                    drop(observed);
-                    self.notification.1.notify_all();
-                }
-            } else {
-                // Return true where outer_ev == inner_ev, and push inner_ev to ret_events
-                let mut compare_ev = |outer_ev: &Event, inner_ev: &Event| {
-                    if let Event::PropertyChange { ref name, .. } = *outer_ev {
-                        let oname = name;
-                        if let Event::PropertyChange { ref name, .. } = *inner_ev {
-                            if oname == name {
-                                ret_events.push(inner_ev.clone());
-                                return true;
-                            }
-                        }
-                    } else if outer_ev.structural_eq(inner_ev) {
-                        ret_events.push(inner_ev.clone());
-                        return true;
-                    }
-                    false
-                };
-                // Remove events belonging to this EventIter from observed
-                for outer_ev in &self.local_to_observe {
-                    observed.retain(|inner_ev| !compare_ev(outer_ev, inner_ev));
-                }
-
-                if !observed.is_empty() {
-                    drop(observed);
-                    self.notification.1.notify_all();
-                }
+            if self.queue.events.is_empty() && self.router.drain(self.ctx, &self.queue) {
+                self.notification.notify();
             }
 
-            self.first_iteration = false;
-
+            let mut ret_events = Vec::with_capacity(self.queue.events.len() + 1);
+            let dropped = self.queue.take_dropped();
+            if dropped > 0 {
+                ret_events.push(Event::QueueOverflow { dropped });
+            }
+            while let Some(event) = self.queue.pop() {
+                ret_events.push(event);
+            }
             if !ret_events.is_empty() {
-                ret_events.shrink_to_fit();
                 return Some(ret_events);
             }
+
+            self.notification.wait(generation);
         }
     }
 }
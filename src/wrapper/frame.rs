@@ -0,0 +1,149 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Grabbing frames via the `screenshot-raw` command, converted to `image::RgbaImage`s so they can
+//! be resized or encoded by the `image` crate directly.
+
+use super::node::NodeGuard;
+use super::*;
+
+use image::RgbaImage;
+
+// Converts the raw image returned by `screenshot-raw`, whose rows are `stride` bytes apart.
+pub(crate) fn to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: &str,
+    data: &[u8],
+) -> Result<RgbaImage> {
+    let pixel_size = match format {
+        "bgr0" | "bgra" | "rgba" => 4,
+        "rgba64" => 8,
+        _ => return Err(Error::Raw(ErrorCode::Unsupported)),
+    };
+    let row_size = width as usize * pixel_size;
+    let size = match height as usize {
+        0 => 0,
+        height => stride * (height - 1) + row_size,
+    };
+    if stride < row_size || data.len() < size {
+        return Err(Error::Raw(ErrorCode::PropertyFormat));
+    }
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in (0..height as usize).map(|y| &data[y * stride..y * stride + row_size]) {
+        for pixel in row.chunks_exact(pixel_size) {
+            match format {
+                // The padding byte of `bgr0` is undefined
+                "bgr0" => rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]),
+                "bgra" => rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]),
+                "rgba" => rgba.extend_from_slice(pixel),
+                // 16 bits per component in native endianness, of which the high byte is kept
+                _ => rgba.extend(
+                    pixel
+                        .chunks_exact(2)
+                        .map(|c| (u16::from_ne_bytes([c[0], c[1]]) >> 8) as u8),
+                ),
+            }
+        }
+    }
+    RgbaImage::from_raw(width, height, rgba).ok_or(Error::Raw(ErrorCode::PropertyFormat))
+}
+
+// Reads the map returned by `screenshot-raw`
+unsafe fn node_to_rgba(node: &mpv_sys::mpv_node) -> Result<RgbaImage> {
+    if node.format != mpv_sys::mpv_format_MPV_FORMAT_NODE_MAP || node.u.list.is_null() {
+        return Err(Error::Raw(ErrorCode::PropertyFormat));
+    }
+    let list = &*node.u.list;
+    let (mut width, mut height, mut stride, mut format, mut data) = (None, None, None, None, None);
+    for i in 0..list.num as usize {
+        let key = mpv_cstr_to_str!(*list.keys.add(i))?;
+        let value = &*list.values.add(i);
+        match (key, value.format) {
+            ("w", mpv_sys::mpv_format_MPV_FORMAT_INT64) => width = Some(value.u.int64),
+            ("h", mpv_sys::mpv_format_MPV_FORMAT_INT64) => height = Some(value.u.int64),
+            ("stride", mpv_sys::mpv_format_MPV_FORMAT_INT64) => stride = Some(value.u.int64),
+            ("format", mpv_sys::mpv_format_MPV_FORMAT_STRING) => {
+                format = Some(mpv_cstr_to_str!(value.u.string)?)
+            }
+            ("data", mpv_sys::mpv_format_MPV_FORMAT_BYTE_ARRAY)
+                if !value.u.ba.is_null() && !(*value.u.ba).data.is_null() =>
+            {
+                let ba = &*value.u.ba;
+                data = Some(std::slice::from_raw_parts(ba.data as *const u8, ba.size));
+            }
+            _ => (),
+        }
+    }
+
+    match (width, height, stride, format, data) {
+        (Some(width), Some(height), Some(stride), Some(format), Some(data)) => to_rgba(
+            u32::try_from(width).map_err(|_| Error::Raw(ErrorCode::PropertyFormat))?,
+            u32::try_from(height).map_err(|_| Error::Raw(ErrorCode::PropertyFormat))?,
+            usize::try_from(stride).map_err(|_| Error::Raw(ErrorCode::PropertyFormat))?,
+            format,
+            data,
+        ),
+        _ => Err(Error::Raw(ErrorCode::PropertyFormat)),
+    }
+}
+
+impl Mpv {
+    // --- Frame grabbing functions ---
+    //
+
+    /// Take a screenshot of the current frame as shown by `mode`, and return it as an image
+    /// instead of saving it to a file.
+    pub fn screenshot_raw(&self, mode: ScreenshotMode) -> Result<RgbaImage> {
+        self.check_shutdown()?;
+        let name = CString::new("screenshot-raw")?;
+        let flags = CString::new(mode.val())?;
+        let string_node = |arg: &CString| mpv_sys::mpv_node {
+            u: mpv_sys::mpv_node__bindgen_ty_1 {
+                string: arg.as_ptr() as *mut _,
+            },
+            format: mpv_sys::mpv_format_MPV_FORMAT_STRING,
+        };
+        let mut values = [string_node(&name), string_node(&flags)];
+        let mut list = mpv_sys::mpv_node_list {
+            num: values.len() as _,
+            values: values.as_mut_ptr(),
+            keys: ptr::null_mut(),
+        };
+        let mut command = mpv_sys::mpv_node {
+            u: mpv_sys::mpv_node__bindgen_ty_1 { list: &mut list },
+            format: mpv_sys::mpv_format_MPV_FORMAT_NODE_ARRAY,
+        };
+
+        let mut result = MaybeUninit::uninit();
+        mpv_err((), unsafe {
+            mpv_sys::mpv_command_node(self.ctx.as_ptr(), &mut command, result.as_mut_ptr())
+        })
+        .map_err(|e| command_err("screenshot-raw", e))?;
+        let result = NodeGuard(unsafe { result.assume_init() });
+        unsafe { node_to_rgba(&result.0) }
+    }
+
+    #[inline]
+    /// Grab the current video frame in its original resolution, without subtitles and OSD.
+    pub fn grab_frame(&self) -> Result<RgbaImage> {
+        self.screenshot_raw(ScreenshotMode::Video)
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Forwarding the input events of `winit` windows to mpv's input system, so that the on-screen
+//! controller and key bindings work in embedded windows, just as in mpv's own window.
+//!
+//! Keys that produce text are sent as the character they produce, so that bindings follow the
+//! keyboard layout, e.g. `#` instead of `Shift+3`. Other keys are looked up in a table of mpv's
+//! key names.
+
+use super::*;
+
+use glutin::event::{
+    ElementState, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+};
+
+use std::collections::HashMap;
+
+// Pixels of smooth scrolling per wheel step
+const PIXELS_PER_STEP: f64 = 50.;
+
+/// The name mpv uses for `key`, e.g. `ESC` or `KP0`. Returns `None` for keys that are sent as
+/// the text they produce, like letters and digits.
+pub fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    use glutin::event::VirtualKeyCode::*;
+
+    Some(match key {
+        Escape => "ESC",
+        F1 => "F1",
+        F2 => "F2",
+        F3 => "F3",
+        F4 => "F4",
+        F5 => "F5",
+        F6 => "F6",
+        F7 => "F7",
+        F8 => "F8",
+        F9 => "F9",
+        F10 => "F10",
+        F11 => "F11",
+        F12 => "F12",
+        F13 => "F13",
+        F14 => "F14",
+        F15 => "F15",
+        F16 => "F16",
+        F17 => "F17",
+        F18 => "F18",
+        F19 => "F19",
+        F20 => "F20",
+        F21 => "F21",
+        F22 => "F22",
+        F23 => "F23",
+        F24 => "F24",
+        Snapshot => "PRINT",
+        Pause => "PAUSE",
+        Insert => "INS",
+        Home => "HOME",
+        Delete => "DEL",
+        End => "END",
+        PageDown => "PGDWN",
+        PageUp => "PGUP",
+        Left => "LEFT",
+        Up => "UP",
+        Right => "RIGHT",
+        Down => "DOWN",
+        Back => "BS",
+        Return => "ENTER",
+        Space => "SPACE",
+        Tab => "TAB",
+        Numpad0 => "KP0",
+        Numpad1 => "KP1",
+        Numpad2 => "KP2",
+        Numpad3 => "KP3",
+        Numpad4 => "KP4",
+        Numpad5 => "KP5",
+        Numpad6 => "KP6",
+        Numpad7 => "KP7",
+        Numpad8 => "KP8",
+        Numpad9 => "KP9",
+        NumpadAdd => "KP_ADD",
+        NumpadSubtract => "KP_SUBTRACT",
+        NumpadMultiply => "KP_MULTIPLY",
+        NumpadDivide => "KP_DIVIDE",
+        NumpadDecimal => "KP_DEC",
+        NumpadEnter => "KP_ENTER",
+        Apps => "MENU",
+        Power => "POWER",
+        Sleep => "SLEEP",
+        Mute => "MUTE",
+        VolumeDown => "VOLUME_DOWN",
+        VolumeUp => "VOLUME_UP",
+        PlayPause => "PLAYPAUSE",
+        MediaStop => "STOP",
+        NextTrack => "NEXT",
+        PrevTrack => "PREV",
+        _ => return None,
+    })
+}
+
+// The character of letter and digit keys, for combinations with modifiers that don't produce
+// text, like `Ctrl+a`.
+fn key_char(key: VirtualKeyCode) -> Option<char> {
+    use glutin::event::VirtualKeyCode::*;
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] =
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    if let Some(i) = LETTERS.iter().position(|k| *k == key) {
+        Some((b'a' + i as u8) as char)
+    } else {
+        DIGITS
+            .iter()
+            .position(|k| *k == key)
+            .map(|i| (b'0' + i as u8) as char)
+    }
+}
+
+fn button_name(button: MouseButton) -> Option<&'static str> {
+    match button {
+        MouseButton::Left => Some("MBTN_LEFT"),
+        MouseButton::Right => Some("MBTN_RIGHT"),
+        MouseButton::Middle => Some("MBTN_MID"),
+        MouseButton::Other(8) => Some("MBTN_BACK"),
+        MouseButton::Other(9) => Some("MBTN_FORWARD"),
+        MouseButton::Other(_) => None,
+    }
+}
+
+// An input command for mpv, with the key names including the modifiers
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Input {
+    KeyDown(String),
+    KeyUp(String),
+    KeyPress(String),
+    // A wheel key pressed the given number of times
+    Wheel(String, u32),
+    ReleaseAll,
+    Mouse(i64, i64),
+}
+
+impl Input {
+    fn run(&self, mpv: &Mpv) -> Result<()> {
+        let (command, key) = match *self {
+            Input::KeyDown(ref key) => ("keydown", key),
+            Input::KeyUp(ref key) => ("keyup", key),
+            Input::KeyPress(ref key) => ("keypress", key),
+            Input::Wheel(ref key, steps) => {
+                for _ in 0..steps {
+                    Input::KeyPress(key.clone()).run(mpv)?;
+                }
+                return Ok(());
+            }
+            Input::ReleaseAll => return mpv.command("keyup", &[]),
+            Input::Mouse(x, y) => return mpv.set_mouse_pos(x, y),
+        };
+        mpv.run_command(|cmd| {
+            cmd.write_str(command)?;
+            cmd.write_str(" ")?;
+            cmd.write_quoted(key)
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+/// Translates the `WindowEvent`s of a window into mpv input commands. It tracks the state of the
+/// modifier keys and of held keys, so there should be one per window.
+pub struct InputBridge {
+    modifiers: ModifiersState,
+    // The names keys were pressed with, so that releasing them works after modifiers changed
+    held: HashMap<VirtualKeyCode, String>,
+    // Scrolling in pixels that didn't add up to a full step yet
+    scrolled: (f64, f64),
+}
+
+impl InputBridge {
+    #[inline]
+    /// Create a bridge without any keys held.
+    pub fn new() -> InputBridge {
+        InputBridge::default()
+    }
+
+    /// Forward `event` to mpv, if it is an input event. Returns whether it was forwarded.
+    ///
+    /// Cursor positions are sent in physical pixels, as mpv renders at the framebuffer's size.
+    pub fn handle(&mut self, mpv: &Mpv, event: &WindowEvent) -> Result<bool> {
+        match self.translate(event) {
+            Some(input) => input.run(mpv).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    fn prefixed(&self, key: &str, include_shift: bool) -> String {
+        let mut name = String::new();
+        if include_shift && self.modifiers.shift() {
+            name.push_str("Shift+");
+        }
+        if self.modifiers.ctrl() {
+            name.push_str("Ctrl+");
+        }
+        if self.modifiers.alt() {
+            name.push_str("Alt+");
+        }
+        if self.modifiers.logo() {
+            name.push_str("Meta+");
+        }
+        name.push_str(key);
+        name
+    }
+
+    // Whether the held modifiers keep keys from producing text
+    fn has_command_modifiers(&self) -> bool {
+        self.modifiers.ctrl() || self.modifiers.alt() || self.modifiers.logo()
+    }
+
+    pub(crate) fn translate(&mut self, event: &WindowEvent) -> Option<Input> {
+        match *event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                None
+            }
+            WindowEvent::KeyboardInput { ref input, .. } => {
+                let key = input.virtual_keycode?;
+                if input.state == ElementState::Released {
+                    return self.held.remove(&key).map(Input::KeyUp);
+                } else if self.held.contains_key(&key) {
+                    // mpv repeats held keys itself
+                    return None;
+                }
+                let name = match (key_name(key), key_char(key)) {
+                    (Some(name), _) => self.prefixed(name, true),
+                    // mpv expects uppercase letters instead of `Shift+`
+                    (None, Some(c)) if self.has_command_modifiers() => {
+                        let c = if self.modifiers.shift() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            c
+                        };
+                        self.prefixed(&c.to_string(), !c.is_ascii_alphabetic())
+                    }
+                    // Sent as `ReceivedCharacter`
+                    _ => return None,
+                };
+                self.held.insert(key, name.clone());
+                Some(Input::KeyDown(name))
+            }
+            WindowEvent::ReceivedCharacter(c) => {
+                // Control characters and space are handled as keys
+                if c.is_control() || c == ' ' || self.has_command_modifiers() {
+                    return None;
+                }
+                // The character already reflects shift
+                let name = if c == '#' {
+                    "SHARP".to_owned()
+                } else {
+                    c.to_string()
+                };
+                Some(Input::KeyPress(self.prefixed(&name, false)))
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let name = self.prefixed(button_name(button)?, true);
+                Some(match state {
+                    ElementState::Pressed => Input::KeyDown(name),
+                    ElementState::Released => Input::KeyUp(name),
+                })
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                // Lines are whole steps, fractions of them add up like smooth scrolling
+                match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        self.scrolled.0 += f64::from(x) * PIXELS_PER_STEP;
+                        self.scrolled.1 += f64::from(y) * PIXELS_PER_STEP;
+                    }
+                    MouseScrollDelta::PixelDelta(position) => {
+                        self.scrolled.0 += position.x;
+                        self.scrolled.1 += position.y;
+                    }
+                }
+                let (x, y) = (
+                    (self.scrolled.0 / PIXELS_PER_STEP).trunc(),
+                    (self.scrolled.1 / PIXELS_PER_STEP).trunc(),
+                );
+                self.scrolled.0 -= x * PIXELS_PER_STEP;
+                self.scrolled.1 -= y * PIXELS_PER_STEP;
+                // Vertical scrolling takes precedence, as mpv has no diagonal wheel keys
+                let (name, steps) = if y > 0. {
+                    ("WHEEL_UP", y)
+                } else if y < 0. {
+                    ("WHEEL_DOWN", -y)
+                } else if x > 0. {
+                    ("WHEEL_RIGHT", x)
+                } else if x < 0. {
+                    ("WHEEL_LEFT", -x)
+                } else {
+                    return None;
+                };
+                Some(Input::Wheel(self.prefixed(name, true), steps as u32))
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                Some(Input::Mouse(position.x as i64, position.y as i64))
+            }
+            WindowEvent::CursorLeft { .. } => Some(Input::KeyPress("MOUSE_LEAVE".to_owned())),
+            // Keys released while unfocused won't be reported
+            WindowEvent::Focused(false) => {
+                self.held.clear();
+                Some(Input::ReleaseAll)
+            }
+            _ => None,
+        }
+    }
+}
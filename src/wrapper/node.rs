@@ -0,0 +1,233 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Borrowed views of the `mpv_node` trees mpv returns for structured properties like
+//! `track-list`, so they can be inspected without converting them into owned values first.
+
+use super::*;
+
+// Frees the contents of a node returned by mpv on drop
+pub(crate) struct NodeGuard(pub(crate) mpv_sys::mpv_node);
+
+impl Drop for NodeGuard {
+    fn drop(&mut self) {
+        unsafe { mpv_sys::mpv_free_node_contents(&mut self.0) };
+    }
+}
+
+#[derive(Clone, Copy)]
+/// A borrowed view of a node returned by mpv, see `Mpv::with_property_node`.
+///
+/// Accessors return `None` if the node has a different type, strings that aren't valid UTF-8
+/// are treated the same.
+pub struct NodeRef<'a>(&'a mpv_sys::mpv_node);
+
+impl<'a> NodeRef<'a> {
+    /// # Safety
+    /// The node has to be valid for `'a`, as returned by mpv.
+    pub(crate) unsafe fn new(node: &'a mpv_sys::mpv_node) -> NodeRef<'a> {
+        NodeRef(node)
+    }
+
+    #[inline]
+    /// Whether the node is empty, e.g. a property without a value.
+    pub fn is_none(&self) -> bool {
+        self.0.format == mpv_sys::mpv_format_MPV_FORMAT_NONE
+    }
+
+    #[inline]
+    /// The string this node holds.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.0.format {
+            mpv_sys::mpv_format_MPV_FORMAT_STRING | mpv_sys::mpv_format_MPV_FORMAT_OSD_STRING => {
+                // mpv never returns null strings
+                unsafe { mpv_cstr_to_str!(self.0.u.string) }.ok()
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    /// The flag this node holds.
+    pub fn as_bool(&self) -> Option<bool> {
+        if self.0.format == mpv_sys::mpv_format_MPV_FORMAT_FLAG {
+            Some(unsafe { self.0.u.flag } != 0)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// The integer this node holds.
+    pub fn as_i64(&self) -> Option<i64> {
+        if self.0.format == mpv_sys::mpv_format_MPV_FORMAT_INT64 {
+            Some(unsafe { self.0.u.int64 })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// The number this node holds, integers are converted.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.0.format {
+            mpv_sys::mpv_format_MPV_FORMAT_DOUBLE => Some(unsafe { self.0.u.double_ }),
+            mpv_sys::mpv_format_MPV_FORMAT_INT64 => Some(unsafe { self.0.u.int64 } as f64),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    /// The bytes this node holds.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        if self.0.format != mpv_sys::mpv_format_MPV_FORMAT_BYTE_ARRAY {
+            return None;
+        }
+        let ba = unsafe { self.0.u.ba.as_ref() }?;
+        if ba.size == 0 {
+            Some(&[])
+        } else {
+            Some(unsafe { std::slice::from_raw_parts(ba.data as *const u8, ba.size) })
+        }
+    }
+
+    #[inline]
+    /// Whether this node is an array.
+    pub fn is_array(&self) -> bool {
+        self.0.format == mpv_sys::mpv_format_MPV_FORMAT_NODE_ARRAY
+    }
+
+    #[inline]
+    /// Whether this node is a map.
+    pub fn is_map(&self) -> bool {
+        self.0.format == mpv_sys::mpv_format_MPV_FORMAT_NODE_MAP
+    }
+
+    fn list(&self) -> Option<&'a mpv_sys::mpv_node_list> {
+        if self.is_array() || self.is_map() {
+            unsafe { self.0.u.list.as_ref() }
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// The number of elements of an array or map, 0 for other nodes.
+    pub fn len(&self) -> usize {
+        self.list().map_or(0, |list| list.num.max(0) as usize)
+    }
+
+    #[inline]
+    /// Whether this node has no elements, see `len`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    /// The element at `index` of an array or map.
+    pub fn index(&self, index: usize) -> Option<NodeRef<'a>> {
+        let list = self.list()?;
+        if index < self.len() {
+            Some(NodeRef(unsafe { &*list.values.add(index) }))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// The value of `key` in a map.
+    pub fn get(&self, key: &str) -> Option<NodeRef<'a>> {
+        self.entries()
+            .find(|&(k, _)| k == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    #[inline]
+    /// Iterate over the elements of an array or map.
+    pub fn iter(&self) -> impl Iterator<Item = NodeRef<'a>> {
+        self.entries().map(|(_, value)| value)
+    }
+
+    #[inline]
+    /// Iterate over the entries of a map, or the elements of an array without a key.
+    pub fn entries(&self) -> impl Iterator<Item = (Option<&'a str>, NodeRef<'a>)> {
+        let node = *self;
+        let keys = if self.is_map() {
+            self.list().map(|list| list.keys)
+        } else {
+            None
+        };
+        (0..self.len()).filter_map(move |i| {
+            let key = keys
+                .filter(|keys| !keys.is_null())
+                .and_then(|keys| unsafe { mpv_cstr_to_str!(*keys.add(i)) }.ok());
+            node.index(i).map(|value| (key, value))
+        })
+    }
+}
+
+impl<'a> fmt::Debug for NodeRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(s) = self.as_str() {
+            s.fmt(f)
+        } else if let Some(b) = self.as_bool() {
+            b.fmt(f)
+        } else if let Some(i) = self.as_i64() {
+            i.fmt(f)
+        } else if let Some(d) = self.as_f64() {
+            d.fmt(f)
+        } else if let Some(bytes) = self.as_bytes() {
+            bytes.fmt(f)
+        } else if self.is_map() {
+            f.debug_map()
+                .entries(self.entries().map(|(k, v)| (k.unwrap_or(""), v)))
+                .finish()
+        } else if self.is_array() {
+            f.debug_list().entries(self.iter()).finish()
+        } else {
+            f.write_str("None")
+        }
+    }
+}
+
+impl Mpv {
+    // --- Node functions ---
+    //
+
+    /// Get the property `name` as a node tree, and inspect it with `f` before it is freed again.
+    ///
+    /// Unlike reading the elements of e.g. `track-list` one at a time, this gets the whole tree
+    /// with one call, without copying any of it.
+    pub fn with_property_node<R, F: FnOnce(NodeRef) -> R>(&self, name: &str, f: F) -> Result<R> {
+        self.check_shutdown()?;
+        let mut node = MaybeUninit::<mpv_sys::mpv_node>::uninit();
+        with_c_name(name, |raw| {
+            mpv_err((), unsafe {
+                mpv_sys::mpv_get_property(
+                    self.ctx.as_ptr(),
+                    raw,
+                    mpv_sys::mpv_format_MPV_FORMAT_NODE,
+                    node.as_mut_ptr() as *mut _,
+                )
+            })
+        })
+        .map_err(|e| property_err(name, e))?;
+        let node = NodeGuard(unsafe { node.assume_init() });
+        Ok(f(unsafe { NodeRef::new(&node.0) }))
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! The command, property and playlist functions of `Mpv` as the `Player` trait, so player logic
+//! can be written against it and unit tested with `MockMpv`, without an initialized player or
+//! any media files.
+
+use super::*;
+
+use parking_lot::Mutex;
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_docs)]
+/// The value of a property, as passed through `Player`.
+pub enum Value {
+    String(String),
+    Flag(bool),
+    Int64(i64),
+    Double(f64),
+}
+
+impl Value {
+    // Converts the value to `format` like mpv does, integers can be read as doubles
+    fn into_format(self, format: Format) -> Option<Value> {
+        match (self, format) {
+            (value @ Value::String(_), Format::String)
+            | (value @ Value::Flag(_), Format::Flag)
+            | (value @ Value::Int64(_), Format::Int64)
+            | (value @ Value::Double(_), Format::Double) => Some(value),
+            (Value::Int64(i), Format::Double) => Some(Value::Double(i as f64)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value {
+        Value::String(s.to_owned())
+    }
+}
+
+macro_rules! value_conversions {
+    ($($ty:ty => $variant:ident,)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(val: $ty) -> Value {
+                    Value::$variant(val)
+                }
+            }
+
+            impl TryFrom<Value> for $ty {
+                type Error = Error;
+
+                fn try_from(value: Value) -> Result<$ty> {
+                    match value {
+                        Value::$variant(val) => Ok(val),
+                        _ => Err(Error::Raw(ErrorCode::PropertyFormat)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+value_conversions! {
+    String => String,
+    bool => Flag,
+    i64 => Int64,
+    f64 => Double,
+}
+
+/// The command, property and playlist functions of `Mpv`.
+///
+/// Only `command`, `set_value` and `get_value` have to be implemented, the other functions send
+/// the same commands and properties as the functions of `Mpv` with the same name.
+pub trait Player {
+    /// See `Mpv::command`.
+    fn command(&self, name: &str, args: &[&str]) -> Result<()>;
+
+    /// Set the property `name` to `value`.
+    fn set_value(&self, name: &str, value: Value) -> Result<()>;
+
+    /// Get the value of the property `name` in the given format.
+    fn get_value(&self, name: &str, format: Format) -> Result<Value>;
+
+    #[inline]
+    /// See `Mpv::set_property`.
+    fn set_property<T: Into<Value>>(&self, name: &str, data: T) -> Result<()>
+    where
+        Self: Sized,
+    {
+        self.set_value(name, data.into())
+    }
+
+    #[inline]
+    /// See `Mpv::get_property`.
+    fn get_property<T: GetData + TryFrom<Value, Error = Error>>(&self, name: &str) -> Result<T>
+    where
+        Self: Sized,
+    {
+        T::try_from(self.get_value(name, T::get_format())?)
+    }
+
+    #[inline]
+    /// See `Mpv::pause`.
+    fn pause(&self) -> Result<()> {
+        self.set_value("pause", Value::Flag(true))
+    }
+
+    #[inline]
+    /// See `Mpv::unpause`.
+    fn unpause(&self) -> Result<()> {
+        self.set_value("pause", Value::Flag(false))
+    }
+
+    #[inline]
+    /// Seek to `time`, see `Mpv::seek_absolute`.
+    fn seek_absolute(&self, time: Duration) -> Result<()> {
+        self.command("seek", &[&time.as_secs_f64().to_string(), "absolute"])
+    }
+
+    #[inline]
+    /// Load a single file, see `Mpv::playlist_load_files`.
+    fn load_file(&self, path: &str, state: FileState) -> Result<()> {
+        self.command("loadfile", &[path, state.val()])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_next_weak`.
+    fn playlist_next_weak(&self) -> Result<()> {
+        self.command("playlist-next", &["weak"])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_next_force`.
+    fn playlist_next_force(&self) -> Result<()> {
+        self.command("playlist-next", &["force"])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_previous_weak`.
+    fn playlist_previous_weak(&self) -> Result<()> {
+        self.command("playlist-previous", &["weak"])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_previous_force`.
+    fn playlist_previous_force(&self) -> Result<()> {
+        self.command("playlist-previous", &["force"])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_clear`.
+    fn playlist_clear(&self) -> Result<()> {
+        self.command("playlist-clear", &[])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_remove_index`.
+    fn playlist_remove_index(&self, position: usize) -> Result<()> {
+        self.command("playlist-remove", &[&position.to_string()])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_move`.
+    fn playlist_move(&self, old: usize, new: usize) -> Result<()> {
+        self.command("playlist-move", &[&new.to_string(), &old.to_string()])
+    }
+
+    #[inline]
+    /// See `Mpv::playlist_shuffle`.
+    fn playlist_shuffle(&self) -> Result<()> {
+        self.command("playlist-shuffle", &[])
+    }
+}
+
+impl Player for Mpv {
+    #[inline]
+    fn command(&self, name: &str, args: &[&str]) -> Result<()> {
+        Mpv::command(self, name, args)
+    }
+
+    #[inline]
+    fn set_value(&self, name: &str, value: Value) -> Result<()> {
+        match value {
+            Value::String(s) => Mpv::set_property(self, name, s),
+            Value::Flag(b) => Mpv::set_property(self, name, b),
+            Value::Int64(i) => Mpv::set_property(self, name, i),
+            Value::Double(d) => Mpv::set_property(self, name, d),
+        }
+    }
+
+    #[inline]
+    fn get_value(&self, name: &str, format: Format) -> Result<Value> {
+        match format {
+            Format::String => Mpv::get_property(self, name).map(Value::String),
+            Format::Flag => Mpv::get_property(self, name).map(Value::Flag),
+            Format::Int64 => Mpv::get_property(self, name).map(Value::Int64),
+            Format::Double => Mpv::get_property(self, name).map(Value::Double),
+        }
+    }
+
+    #[inline]
+    fn seek_absolute(&self, time: Duration) -> Result<()> {
+        Mpv::seek_absolute(self, time)
+    }
+
+    #[inline]
+    fn load_file(&self, path: &str, state: FileState) -> Result<()> {
+        self.loadfile(path, state, None)
+    }
+
+    #[inline]
+    fn playlist_remove_index(&self, position: usize) -> Result<()> {
+        Mpv::playlist_remove_index(self, position)
+    }
+
+    #[inline]
+    fn playlist_move(&self, old: usize, new: usize) -> Result<()> {
+        Mpv::playlist_move(self, old, new)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A call made to a `MockMpv`.
+pub enum Call {
+    /// A command with its arguments.
+    Command(String, Vec<String>),
+    /// Setting a property.
+    SetProperty(String, Value),
+    /// Getting a property.
+    GetProperty(String),
+}
+
+/// A `Player` that records all calls, and returns scripted property values.
+///
+/// Setting a property also changes the value returned for it. Getting a property without a value
+/// fails with `ErrorCode::PropertyNotFound`, like it does for properties unknown to mpv.
+pub struct MockMpv {
+    calls: Mutex<Vec<Call>>,
+    properties: Mutex<HashMap<String, Value>>,
+    failing_commands: Mutex<HashMap<String, Error>>,
+}
+
+impl MockMpv {
+    #[inline]
+    /// Create a `MockMpv` without any property values.
+    pub fn new() -> MockMpv {
+        MockMpv {
+            calls: Mutex::new(Vec::new()),
+            properties: Mutex::new(HashMap::new()),
+            failing_commands: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[inline]
+    /// Return `value` for the property `name` from now on.
+    pub fn script_property<V: Into<Value>>(&self, name: &str, value: V) {
+        self.properties.lock().insert(name.to_owned(), value.into());
+    }
+
+    #[inline]
+    /// Fail the command `name` with `error` from now on.
+    pub fn fail_command(&self, name: &str, error: Error) {
+        self.failing_commands.lock().insert(name.to_owned(), error);
+    }
+
+    #[inline]
+    /// The calls made so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().clone()
+    }
+
+    #[inline]
+    /// Return the calls made so far, and forget them.
+    pub fn take_calls(&self) -> Vec<Call> {
+        std::mem::take(&mut *self.calls.lock())
+    }
+}
+
+impl Default for MockMpv {
+    #[inline]
+    fn default() -> MockMpv {
+        MockMpv::new()
+    }
+}
+
+impl Player for MockMpv {
+    fn command(&self, name: &str, args: &[&str]) -> Result<()> {
+        self.calls.lock().push(Call::Command(
+            name.to_owned(),
+            args.iter().map(|&arg| arg.to_owned()).collect(),
+        ));
+        match self.failing_commands.lock().get(name) {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn set_value(&self, name: &str, value: Value) -> Result<()> {
+        self.calls
+            .lock()
+            .push(Call::SetProperty(name.to_owned(), value.clone()));
+        self.properties.lock().insert(name.to_owned(), value);
+        Ok(())
+    }
+
+    fn get_value(&self, name: &str, format: Format) -> Result<Value> {
+        self.calls.lock().push(Call::GetProperty(name.to_owned()));
+        let value = self
+            .properties
+            .lock()
+            .get(name)
+            .cloned()
+            .ok_or(Error::Raw(ErrorCode::PropertyNotFound))
+            .map_err(|e| property_err(name, e))?;
+        value
+            .into_format(format)
+            .ok_or(Error::Raw(ErrorCode::PropertyFormat))
+            .map_err(|e| property_err(name, e))
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Reading m3u, pls and cue playlists into entries for `Mpv::playlist_load_files`. Unlike
+//! `Mpv::playlist_load_list`, this gives control over every entry, e.g. to show their titles or
+//! to set options per entry.
+
+use super::*;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The format of a playlist file.
+pub enum PlaylistFormat {
+    /// Extended or plain m3u and m3u8 playlists.
+    M3u,
+    /// INI-like pls playlists.
+    Pls,
+    /// Cue sheets, which split files into tracks.
+    Cue,
+}
+
+impl PlaylistFormat {
+    /// Guess the format from the extension of `path`, or else from the `content`. Plain lists of
+    /// paths are read as m3u.
+    pub fn detect(path: &Path, content: &str) -> PlaylistFormat {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("m3u") | Some("m3u8") => PlaylistFormat::M3u,
+            Some("pls") => PlaylistFormat::Pls,
+            Some("cue") => PlaylistFormat::Cue,
+            _ => {
+                let first = content
+                    .trim_start_matches('\u{feff}')
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or("");
+                if first.eq_ignore_ascii_case("[playlist]") {
+                    PlaylistFormat::Pls
+                } else if is_cue_command(first) {
+                    PlaylistFormat::Cue
+                } else {
+                    PlaylistFormat::M3u
+                }
+            }
+        }
+    }
+}
+
+fn is_cue_command(line: &str) -> bool {
+    ["FILE ", "REM ", "TITLE ", "PERFORMER "].iter().any(
+        |cmd| matches!(line.get(..cmd.len()), Some(prefix) if prefix.eq_ignore_ascii_case(cmd)),
+    )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An entry of a playlist file.
+pub struct Item {
+    /// The path or URL, with relative paths resolved against the directory of the playlist.
+    pub path: String,
+    /// The title given by the playlist.
+    pub title: Option<String>,
+    /// The duration given by the playlist, which may be inaccurate.
+    pub duration: Option<Duration>,
+    /// Where the entry starts in the file, for the tracks of a cue sheet.
+    pub start: Option<Duration>,
+    /// Where the entry ends in the file, for the tracks of a cue sheet.
+    pub end: Option<Duration>,
+    /// The options passed to `loadfile`, which make mpv use the title, start and end. Further
+    /// options may be appended, separated by `,`.
+    pub options: String,
+}
+
+impl Item {
+    fn new(path: String, title: Option<String>, duration: Option<Duration>) -> Item {
+        Item {
+            path,
+            title,
+            duration,
+            start: None,
+            end: None,
+            options: String::new(),
+        }
+    }
+
+    fn with_options(mut self) -> Item {
+        let mut options = Vec::new();
+        if let Some(ref title) = self.title {
+            options.push(format!("force-media-title=%{}%{}", title.len(), title));
+        }
+        if let Some(start) = self.start {
+            options.push(format!("start={}", start.as_secs_f64()));
+        }
+        if let Some(end) = self.end {
+            options.push(format!("end={}", end.as_secs_f64()));
+        }
+        self.options = options.join(",");
+        self
+    }
+
+    #[inline]
+    /// The arguments of `Mpv::playlist_load_files` to load this entry.
+    pub fn as_tuple(&self, state: FileState) -> (&str, FileState, Option<&str>) {
+        let options = if self.options.is_empty() {
+            None
+        } else {
+            Some(&*self.options)
+        };
+        (&self.path, state, options)
+    }
+}
+
+/// The arguments of `Mpv::playlist_load_files` to load `items`. The first is loaded as given by
+/// `state`, the others are appended.
+pub fn load_args(items: &[Item], state: FileState) -> Vec<(&str, FileState, Option<&str>)> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| item.as_tuple(if i == 0 { state } else { FileState::Append }))
+        .collect()
+}
+
+/// Read the playlist at `path`, guessing its format with `PlaylistFormat::detect`.
+pub fn parse<P: AsRef<Path>>(path: P) -> io::Result<Vec<Item>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    Ok(parse_str(
+        &content,
+        PlaylistFormat::detect(path, &content),
+        base,
+    ))
+}
+
+/// Parse a playlist, resolving relative paths against the directory `base`. Lines that can't be
+/// parsed are skipped, as mpv does.
+pub fn parse_str(content: &str, format: PlaylistFormat, base: &Path) -> Vec<Item> {
+    let content = content.trim_start_matches('\u{feff}');
+    let items = match format {
+        PlaylistFormat::M3u => parse_m3u(content, base),
+        PlaylistFormat::Pls => parse_pls(content, base),
+        PlaylistFormat::Cue => parse_cue(content, base),
+    };
+    items.into_iter().map(Item::with_options).collect()
+}
+
+fn resolve(base: &Path, path: &str) -> String {
+    if path.contains("://") || Path::new(path).is_absolute() {
+        path.to_owned()
+    } else {
+        base.join(path).to_string_lossy().into_owned()
+    }
+}
+
+// Negative durations mean that it's unknown
+fn duration(secs: &str) -> Option<Duration> {
+    match secs.trim().parse::<f64>() {
+        // `Duration::from_secs_f64` panics on overflow
+        Ok(secs) if secs >= 0. && secs < u64::MAX as f64 => Some(Duration::from_secs_f64(secs)),
+        _ => None,
+    }
+}
+
+fn parse_m3u(content: &str, base: &Path) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut info = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<duration>[ <attributes>],<title>
+            let mut parts = extinf.splitn(2, ',');
+            let secs = parts.next().unwrap_or("").split(' ').next().unwrap_or("");
+            let title = parts
+                .next()
+                .map(str::trim)
+                .filter(|title| !title.is_empty())
+                .map(str::to_owned);
+            info = Some((title, duration(secs)));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (title, duration) = info.take().unwrap_or((None, None));
+            items.push(Item::new(resolve(base, line), title, duration));
+        }
+    }
+    items
+}
+
+fn parse_pls(content: &str, base: &Path) -> Vec<Item> {
+    // Entries are numbered, and their keys may come in any order
+    let mut entries = BTreeMap::new();
+    for line in content.lines().map(str::trim) {
+        let mut parts = line.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+            _ => continue,
+        };
+        let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let index = match key[split..].parse::<u32>() {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+        let entry = entries.entry(index).or_insert((None, None, None));
+        match &key[..split] {
+            "file" => entry.0 = Some(resolve(base, value)),
+            "title" if !value.is_empty() => entry.1 = Some(value.to_owned()),
+            "length" => entry.2 = duration(value),
+            _ => (),
+        }
+    }
+    entries
+        .into_iter()
+        .filter_map(|(_, (path, title, duration))| Some(Item::new(path?, title, duration)))
+        .collect()
+}
+
+// Returns the first, possibly quoted, argument of a cue command.
+fn cue_argument(args: &str) -> &str {
+    let args = args.trim();
+    if let Some(args) = args.strip_prefix('"') {
+        &args[..args.find('"').unwrap_or(args.len())]
+    } else {
+        args.split_whitespace().next().unwrap_or("")
+    }
+}
+
+// Parses an `mm:ss:ff` timestamp, with 75 frames per second
+fn cue_timestamp(timestamp: &str) -> Option<Duration> {
+    let mut parts = timestamp.split(':').map(|part| part.parse::<u64>().ok());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(min)), Some(Some(sec)), Some(Some(frames)), None) => {
+            Some(Duration::from_secs(min * 60 + sec) + Duration::from_millis(frames * 1000 / 75))
+        }
+        _ => None,
+    }
+}
+
+fn parse_cue(content: &str, base: &Path) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    let mut file = None;
+    // Whether the last `TRACK` is in the current `FILE`, so that its commands apply to it
+    let mut in_track = false;
+    for line in content.lines().map(str::trim) {
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let command = parts.next().unwrap_or("").to_ascii_uppercase();
+        let args = parts.next().unwrap_or("");
+        match &*command {
+            "FILE" => {
+                file = Some(resolve(base, cue_argument(args)));
+                in_track = false;
+            }
+            "TRACK" => {
+                in_track = false;
+                if let Some(ref file) = file {
+                    items.push(Item::new(file.clone(), None, None));
+                    in_track = true;
+                }
+            }
+            "TITLE" if in_track => {
+                let title = cue_argument(args);
+                if let Some(item) = items.last_mut() {
+                    if !title.is_empty() {
+                        item.title = Some(title.to_owned());
+                    }
+                }
+            }
+            "INDEX" if in_track => {
+                let mut args = args.split_whitespace();
+                if let (Some("01"), Some(timestamp)) = (args.next(), args.next()) {
+                    if let Some(item) = items.last_mut() {
+                        item.start = cue_timestamp(timestamp);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // A track ends where the next one in the same file starts
+    for i in 1..items.len() {
+        if items[i].path == items[i - 1].path {
+            items[i - 1].end = items[i].start;
+        }
+    }
+    for item in &mut items {
+        if let (Some(start), Some(end)) = (item.start, item.end) {
+            item.duration = end.checked_sub(start);
+        }
+    }
+    items
+}
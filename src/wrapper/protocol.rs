@@ -17,249 +17,414 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
 //! This allows registering custom protocols, which then can be used via
-//! `PlaylistOp::Loadfiles`.
-
-use parking_lot::Mutex;
+//! `Mpv::playlist_load_files`.
+//!
+//! # Lifetimes
+//! A registered `Protocol` is owned by `Mpv`, and mpv may call into its handler or any of its
+//! open streams until the core is destroyed. Hence protocols are only dropped after
+//! `mpv_terminate_destroy` has returned, at which point libmpv guarantees that every stream has
+//! been closed. libmpv has no way to unregister a protocol, so neither does this crate.
 
 use super::*;
 
-use std::alloc::{self, Layout};
+#[cfg(feature = "async_protocols")]
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt},
+    runtime::{self, Handle, Runtime},
+};
+
+use bytes::Bytes;
+
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use std::ffi::CString;
-use std::marker::PhantomData;
-use std::mem;
+#[cfg(feature = "async_protocols")]
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::os::raw as ctype;
-use std::panic;
-use std::panic::RefUnwindSafe;
-use std::ptr::{self, NonNull};
-use std::sync::atomic::Ordering;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SendError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Serves the streams of `Mpv::load_from_memory` and `Mpv::load_from_channel`
+const INTERNAL_PROTOCOL: &str = "mpv-rs";
+// How often a `PushStream` waiting for a chunk checks whether the core is being destroyed
+const PUSH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Opens a new stream every time mpv opens an `INTERNAL_PROTOCOL` URI, or returns `None` if the
+// entry's stream can't be opened again
+type StreamFactory = Box<dyn FnMut() -> Option<Box<dyn Stream>> + Send>;
 
 impl Mpv {
     #[inline]
-    /// Create a context with which custom protocols can be registered.
+    /// Register a custom `Protocol`. Once a protocol has been registered, it lives as long as
+    /// `Mpv`, and can't be unregistered.
+    ///
+    /// This can be done at any time, and for any number of protocols, each with its own
+    /// handler.
+    ///
+    /// Returns `Error::Raw(ErrorCode::InvalidParameter)` if a protocol with the same name has
+    /// already been registered.
+    pub fn register_protocol(&self, protocol: Protocol) -> Result<()> {
+        self.check_shutdown()?;
+        let mut protocols = self.protocols.lock();
+        protocol.register(self.ctx.as_ptr())?;
+        protocols.push(protocol);
+        Ok(())
+    }
+
+    #[inline]
+    /// Play `data` directly from memory, e.g. a `Vec<u8>`, a `&'static [u8]`, or a
+    /// `bytes::Bytes`, without writing a custom protocol.
     ///
-    /// Returns `None` if a context already exists
-    pub fn create_protocol_context<T, U>(&self, capacity: usize) -> Option<ProtocolContext<T, U>>
+    /// The buffer is kept as long as the returned `StreamRegistration`, so the playlist entry
+    /// can be played any number of times until then, e.g. with `loop-file`.
+    pub fn load_from_memory<T>(&self, data: T, state: FileState) -> Result<StreamRegistration>
     where
-        T: RefUnwindSafe,
-        U: RefUnwindSafe,
+        T: AsRef<[u8]> + Send + Sync + 'static,
     {
-        if self
-            .protocols_guard
-            .compare_and_swap(false, true, Ordering::AcqRel)
+        let data: Arc<dyn AsRef<[u8]> + Send + Sync> = Arc::new(data);
+        self.load_pending_stream(
+            Box::new(move || {
+                let reader = io::Cursor::new(MemoryBuffer(data.clone()));
+                Some(Box::new(ReaderStream(Box::new(reader))) as Box<dyn Stream>)
+            }),
+            state,
+        )
+    }
+
+    #[inline]
+    /// Play a live stream that is fed by pushing chunks into the returned `PushSender`, e.g.
+    /// transcoded or progressively downloaded data.
+    ///
+    /// Up to `capacity` chunks are buffered, after which `PushSender::send` blocks until mpv
+    /// has consumed some of them. The stream is unseekable, and ends once the `PushSender` has
+    /// been dropped, or `PushSender::finish` has been called.
+    ///
+    /// A live stream can only be opened once, so the playlist entry can only be played once.
+    /// When `Mpv` is dropped, reads waiting for a chunk return EOF, and blocked sends fail.
+    pub fn load_from_channel(&self, capacity: usize, state: FileState) -> Result<PushSender> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let shutdown = self.pending_streams.shutdown.clone();
+        let mut rx = Some(rx);
+        let registration = self.load_pending_stream(
+            Box::new(move || {
+                let stream = PushStream {
+                    rx: Some(rx.take()?),
+                    shutdown: shutdown.clone(),
+                    chunk: Bytes::new(),
+                    pos: 0,
+                };
+                Some(Box::new(stream) as Box<dyn Stream>)
+            }),
+            state,
+        )?;
+        Ok(PushSender { tx, registration })
+    }
+
+    fn load_pending_stream(
+        &self,
+        open: StreamFactory,
+        state: FileState,
+    ) -> Result<StreamRegistration> {
+        self.check_shutdown()?;
         {
-            None
-        } else {
-            Some(ProtocolContext::new(
-                self.ctx,
-                capacity,
-                PhantomData::<&Self>,
-            ))
+            let mut registered = self.pending_streams.registered.lock();
+            if !*registered {
+                let pending = self.pending_streams.clone();
+                self.register_protocol(Protocol::new(
+                    INTERNAL_PROTOCOL.into(),
+                    move |uri: &str| pending.open(uri),
+                ))?;
+                *registered = true;
+            }
         }
+
+        let id = self.pending_streams.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pending_streams.streams.lock().insert(id, open);
+        // Removes the stream again if loading fails
+        let registration = StreamRegistration {
+            id,
+            streams: self.pending_streams.clone(),
+        };
+
+        self.command(
+            "loadfile",
+            &[&format!("\"{}://{}\"", INTERNAL_PROTOCOL, id), state.val()],
+        )?;
+        Ok(registration)
     }
 }
 
-/// Return a persistent `T` that is passed to all other `Stream*` functions, panic on errors.
-pub type StreamOpen<T, U> = fn(&mut U, &str) -> T;
-/// Do any necessary cleanup.
-pub type StreamClose<T> = fn(Box<T>);
-/// Seek to the given offset. Return the new offset, or either `MpvError::Generic` if seeking
-/// failed or panic.
-pub type StreamSeek<T> = fn(&mut T, i64) -> i64;
-/// Read nbytes into the given buffer.
-/// Return either the number of read bytes, `0` on EOF, or either `-1` or panic on error.
-pub type StreamRead<T> = fn(&mut T, *mut ctype::c_char, u64) -> i64;
-/// Return the total size of the stream in bytes. Panic on error.
-pub type StreamSize<T> = fn(&mut T) -> i64;
+/// A stream opened by a `StreamHandler`. It is dropped once mpv closes the stream.
+///
+/// All methods are called from an mpv thread, and must not call libmpv functions.
+/// Panics are caught, and reported to mpv as errors.
+pub trait Stream: Send {
+    /// Read into `buf`, returning the number of read bytes, or `0` on EOF.
+    ///
+    /// Blocking reads have to time out eventually, or be interruptible by `canceller`,
+    /// otherwise quitting or switching files may hang.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
 
-unsafe extern "C" fn open_wrapper<T, U>(
-    user_data: *mut ctype::c_void,
-    uri: *mut ctype::c_char,
-    info: *mut mpv_sys::mpv_stream_cb_info,
-) -> ctype::c_int
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = user_data as *mut ProtocolData<T, U>;
-
-    (*info).cookie = user_data;
-    (*info).read_fn = Some(read_wrapper::<T, U>);
-    (*info).seek_fn = Some(seek_wrapper::<T, U>);
-    (*info).size_fn = Some(size_wrapper::<T, U>);
-    (*info).close_fn = Some(close_wrapper::<T, U>);
-
-    let ret = panic::catch_unwind(|| {
-        let uri = mpv_cstr_to_str!(uri as *const _).unwrap();
-        ptr::write(
-            (*data).cookie,
-            ((*data).open_fn)(&mut (*data).user_data, uri),
-        );
-    });
-    if ret.is_ok() {
-        0
-    } else {
-        mpv_error::Generic as _
+    /// A function that interrupts a blocking `read` from another thread, e.g. by shutting down
+    /// a socket. Reads after cancelling should fail or return `0`.
+    ///
+    /// This is called once after the stream has been opened, and mpv calls the returned function
+    /// when quitting or switching files. It is never called by mpv versions older than 0.33.
+    /// The default implementation returns `None`.
+    fn canceller(&mut self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        None
+    }
+
+    /// Seek to the absolute byte `offset`, returning the new offset.
+    ///
+    /// mpv seeks to `0` right after opening to probe whether the stream is seekable. The default
+    /// implementation returns `Error::Raw(ErrorCode::Unsupported)`, marking it as unseekable.
+    fn seek(&mut self, _offset: u64) -> Result<u64> {
+        Err(Error::Raw(ErrorCode::Unsupported))
+    }
+
+    /// The total size of the stream in bytes, or `None` if it is unknown.
+    fn size(&mut self) -> Option<u64> {
+        None
     }
+
+    /// Do any necessary cleanup, called right before the stream is dropped.
+    fn close(&mut self) {}
 }
 
-unsafe extern "C" fn read_wrapper<T, U>(
-    cookie: *mut ctype::c_void,
-    buf: *mut ctype::c_char,
-    nbytes: u64,
-) -> i64
+/// Opens `Stream`s for a custom protocol.
+///
+/// Every call to `open` creates fresh state for its stream, so streams can own sockets, file
+/// handles or decryptors that aren't `Clone`. Closures of the form
+/// `Fn(&str) -> Result<Box<dyn Stream>>` implement this trait, and can be used as factories.
+///
+/// `open` may be called from multiple mpv threads at once, and must not call libmpv functions.
+/// Panics are caught, and reported to mpv as errors.
+pub trait StreamHandler: Send + Sync {
+    /// Open a new stream for `uri`, which includes the protocol prefix, e.g. `name://path`.
+    fn open(&self, uri: &str) -> OpenResult;
+}
+
+impl<F> StreamHandler for F
 where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
+    F: Fn(&str) -> OpenResult + Send + Sync,
 {
-    let data = cookie as *mut ProtocolData<T, U>;
-
-    let ret = panic::catch_unwind(|| {
-        debug_assert!(!(*data).cookie.is_null());
-        ((*data).read_fn)(&mut *(*data).cookie, buf, nbytes)
-    });
-    if ret.is_ok() {
-        ret.unwrap()
-    } else {
-        -1
+    #[inline]
+    fn open(&self, uri: &str) -> OpenResult {
+        self(uri)
     }
 }
 
-unsafe extern "C" fn seek_wrapper<T, U>(cookie: *mut ctype::c_void, offset: i64) -> i64
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
+/// The result of `StreamHandler::open`.
+pub type OpenResult = ::std::result::Result<Box<dyn Stream>, StreamError>;
 
-    if (*data).seek_fn.is_none() {
-        return mpv_error::Unsupported as _;
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Why a `StreamHandler` couldn't open a stream. Each variant is reported to mpv as the
+/// corresponding `mpv_error` code.
+///
+/// libmpv only documents `mpv_error::LoadingFailed` for failed opens; other codes fail the load
+/// all the same, but are logged by mpv.
+pub enum StreamError {
+    /// The handler doesn't support this kind of URI. Maps to `mpv_error::Unsupported`.
+    UnsupportedScheme,
+    /// The resource doesn't exist. Maps to `mpv_error::LoadingFailed`.
+    NotFound,
+    /// Access to the resource was denied. Maps to `mpv_error::LoadingFailed`.
+    PermissionDenied,
+    /// Any other error. Maps to `mpv_error::Generic`.
+    Generic,
+}
 
-    let ret =
-        panic::catch_unwind(|| (*(*data).seek_fn.as_ref().unwrap())(&mut *(*data).cookie, offset));
-    if ret.is_ok() {
-        ret.unwrap()
-    } else {
-        mpv_error::Generic as _
+impl StreamError {
+    #[inline]
+    /// The `mpv_error` code this error is reported as.
+    pub fn as_raw(self) -> crate::MpvError {
+        match self {
+            StreamError::UnsupportedScheme => mpv_error::Unsupported,
+            StreamError::NotFound | StreamError::PermissionDenied => mpv_error::LoadingFailed,
+            StreamError::Generic => mpv_error::Generic,
+        }
     }
 }
 
-unsafe extern "C" fn size_wrapper<T, U>(cookie: *mut ctype::c_void) -> i64
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
-
-    if (*data).size_fn.is_none() {
-        return mpv_error::Unsupported as _;
+impl From<io::Error> for StreamError {
+    fn from(other: io::Error) -> StreamError {
+        match other.kind() {
+            io::ErrorKind::NotFound => StreamError::NotFound,
+            io::ErrorKind::PermissionDenied => StreamError::PermissionDenied,
+            _ => StreamError::Generic,
+        }
     }
+}
 
-    let ret = panic::catch_unwind(|| (*(*data).size_fn.as_ref().unwrap())(&mut *(*data).cookie));
-    if ret.is_ok() {
-        ret.unwrap()
-    } else {
-        mpv_error::Unsupported as _
+impl From<Error> for StreamError {
+    fn from(_other: Error) -> StreamError {
+        StreamError::Generic
     }
 }
 
-#[allow(unused_must_use)]
-unsafe extern "C" fn close_wrapper<T, U>(cookie: *mut ctype::c_void)
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
+// Translate an `io::Error` of an open stream into an mpv error code. mpv's read and seek
+// callbacks don't distinguish between causes of failure.
+fn io_err(_e: &io::Error) -> Error {
+    Error::Raw(ErrorCode::Generic)
+}
 
-    panic::catch_unwind(|| ((*data).close_fn)(Box::from_raw((*data).cookie)));
+fn stream_err(e: &Error) -> i64 {
+    i64::from(e.code().unwrap_or(ErrorCode::Generic).as_raw())
 }
 
-struct ProtocolData<T, U> {
-    cookie: *mut T,
-    user_data: U,
+// `cancel_fn` was appended to `mpv_stream_cb_info` in this client API version, older versions
+// pass a smaller struct
+const CANCEL_FN_API_VERSION: ctype::c_ulong = 1 << 16 | 106;
+
+// The cookie passed to the stream callbacks. mpv calls `cancel_fn` from another thread while
+// a read may be running, so the canceller is kept apart from the stream.
+pub(crate) struct StreamCookie {
+    stream: UnsafeCell<Box<dyn Stream>>,
+    cancel: Option<Box<dyn Fn() + Send + Sync>>,
+}
 
-    open_fn: StreamOpen<T, U>,
-    close_fn: StreamClose<T>,
-    read_fn: StreamRead<T>,
-    seek_fn: Option<StreamSeek<T>>,
-    size_fn: Option<StreamSize<T>>,
+pub(crate) fn stream_cookie(mut stream: Box<dyn Stream>) -> *mut ctype::c_void {
+    let cancel = stream.canceller();
+    let cookie = StreamCookie {
+        stream: UnsafeCell::new(stream),
+        cancel,
+    };
+    Box::into_raw(Box::new(cookie)) as *mut _
 }
 
-/// This context holds state relevant to custom protocols.
-/// It is created by calling `Mpv::create_protocol_context`.
-pub struct ProtocolContext<'parent, T: RefUnwindSafe, U: RefUnwindSafe> {
-    ctx: NonNull<mpv_sys::mpv_handle>,
-    protocols: Mutex<Vec<Protocol<T, U>>>,
-    _does_not_outlive: PhantomData<&'parent Mpv>,
+// mpv never calls `read_fn`, `seek_fn` and `size_fn` concurrently
+unsafe fn cookie_stream<'a>(cookie: *mut ctype::c_void) -> &'a mut Box<dyn Stream> {
+    &mut *(*(cookie as *const StreamCookie)).stream.get()
 }
 
-unsafe impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> Send for ProtocolContext<'parent, T, U> {}
-unsafe impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> Sync for ProtocolContext<'parent, T, U> {}
+unsafe extern "C" fn open_wrapper(
+    user_data: *mut ctype::c_void,
+    uri: *mut ctype::c_char,
+    info: *mut mpv_sys::mpv_stream_cb_info,
+) -> ctype::c_int {
+    let handler = &*(user_data as *const Box<dyn StreamHandler>);
 
-impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> ProtocolContext<'parent, T, U> {
-    fn new(
-        ctx: NonNull<mpv_sys::mpv_handle>,
-        capacity: usize,
-        marker: PhantomData<&'parent Mpv>,
-    ) -> ProtocolContext<'parent, T, U> {
-        ProtocolContext {
-            ctx,
-            protocols: Mutex::new(Vec::with_capacity(capacity)),
-            _does_not_outlive: marker,
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        let uri = mpv_cstr_to_str!(uri as *const _)?;
+        handler.open(uri).map(stream_cookie)
+    }));
+    match ret {
+        Ok(Ok(cookie)) => {
+            (*info).cookie = cookie;
+            (*info).read_fn = Some(read_wrapper);
+            (*info).seek_fn = Some(seek_wrapper);
+            (*info).size_fn = Some(size_wrapper);
+            (*info).close_fn = Some(close_wrapper);
+            if mpv_sys::mpv_client_api_version() >= CANCEL_FN_API_VERSION {
+                (*info).cancel_fn = Some(cancel_wrapper);
+            }
+            0
         }
+        Ok(Err(e)) => e.as_raw(),
+        Err(_) => mpv_error::Generic,
     }
+}
 
-    /// Register a custom `Protocol`. Once a protocol has been registered, it lives as long as
-    /// `Mpv`.
-    ///
-    /// Returns `Error::Mpv(MpvError::InvalidParameter)` if a protocol with the same name has
-    /// already been registered.
-    pub fn register(&self, protocol: Protocol<T, U>) -> Result<()> {
-        let mut protocols = self.protocols.lock();
-        protocol.register(self.ctx.as_ptr())?;
-        protocols.push(protocol);
-        Ok(())
+pub(crate) unsafe extern "C" fn read_wrapper(
+    cookie: *mut ctype::c_void,
+    buf: *mut ctype::c_char,
+    nbytes: u64,
+) -> i64 {
+    let stream = cookie_stream(cookie);
+    // A slice can't be larger than `isize::MAX`, mpv will retry reading the rest
+    let len = nbytes.min(isize::MAX as u64) as usize;
+    let buf: &mut [u8] = if len == 0 {
+        &mut []
+    } else if buf.is_null() {
+        return -1;
+    } else {
+        slice::from_raw_parts_mut(buf as *mut u8, len)
+    };
+
+    match panic::catch_unwind(AssertUnwindSafe(|| stream.read(buf))) {
+        // Claiming to have read more than fits is a bug, and would make mpv read garbage
+        Ok(Ok(read)) if read <= len => read as _,
+        _ => -1,
+    }
+}
+
+unsafe extern "C" fn seek_wrapper(cookie: *mut ctype::c_void, offset: i64) -> i64 {
+    let stream = cookie_stream(cookie);
+
+    if offset < 0 {
+        return mpv_error::Generic as _;
+    }
+    match panic::catch_unwind(AssertUnwindSafe(|| stream.seek(offset as _))) {
+        Ok(Ok(offset)) => offset as _,
+        Ok(Err(e)) => stream_err(&e),
+        Err(_) => mpv_error::Generic as _,
+    }
+}
+
+unsafe extern "C" fn size_wrapper(cookie: *mut ctype::c_void) -> i64 {
+    let stream = cookie_stream(cookie);
+
+    match panic::catch_unwind(AssertUnwindSafe(|| stream.size())) {
+        Ok(Some(size)) => size as _,
+        _ => mpv_error::Unsupported as _,
+    }
+}
+
+pub(crate) unsafe extern "C" fn cancel_wrapper(cookie: *mut ctype::c_void) {
+    let cookie = &*(cookie as *const StreamCookie);
+
+    if let Some(ref cancel) = cookie.cancel {
+        let _ = panic::catch_unwind(AssertUnwindSafe(cancel));
     }
 }
 
+#[allow(unused_must_use)]
+pub(crate) unsafe extern "C" fn close_wrapper(cookie: *mut ctype::c_void) {
+    let cookie = Box::from_raw(cookie as *mut StreamCookie);
+    let mut stream = cookie.stream.into_inner();
+
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        stream.close();
+        drop(stream);
+    }));
+}
+
 /// `Protocol` holds all state used by a custom protocol.
-pub struct Protocol<T: Sized + RefUnwindSafe, U: RefUnwindSafe> {
+pub struct Protocol {
     name: String,
-    data: *mut ProtocolData<T, U>,
+    // mpv holds a pointer to the inner box, so it must not move until the core is destroyed
+    handler: Box<Box<dyn StreamHandler>>,
 }
 
-impl<T: RefUnwindSafe, U: RefUnwindSafe> Protocol<T, U> {
+impl Protocol {
     #[inline]
     /// `name` is the prefix of the protocol, e.g. `name://path`.
     ///
-    /// `user_data` is data that will be passed to `open_fn`.
+    /// `handler` opens a new `Stream` for every URI that mpv loads with this prefix.
+    pub fn new<H: StreamHandler + 'static>(name: String, handler: H) -> Protocol {
+        Protocol {
+            name,
+            handler: Box::new(Box::new(handler)),
+        }
+    }
+
+    #[inline]
+    /// Create a protocol backed by any `Read + Seek` type, e.g. a `File`, a `Cursor<Vec<u8>>`,
+    /// or a decrypting reader.
     ///
-    /// # Safety
-    /// Do not call libmpv functions in any supplied function.
-    /// All panics of the provided functions are catched and can be used as generic error returns.
-    pub unsafe fn new(
-        name: String,
-        user_data: U,
-        open_fn: StreamOpen<T, U>,
-        close_fn: StreamClose<T>,
-        read_fn: StreamRead<T>,
-        seek_fn: Option<StreamSeek<T>>,
-        size_fn: Option<StreamSize<T>>,
-    ) -> Protocol<T, U> {
-        let c_layout = Layout::from_size_align(mem::size_of::<T>(), mem::align_of::<T>()).unwrap();
-        let cookie = alloc::alloc(c_layout) as *mut T;
-        let data = Box::into_raw(Box::new(ProtocolData {
-            cookie,
-            user_data,
-
-            open_fn,
-            close_fn,
-            read_fn,
-            seek_fn,
-            size_fn,
-        }));
-
-        Protocol { name, data }
+    /// `open` is called with the URI of every stream mpv opens, including the protocol prefix.
+    /// `io::Error`s are translated into the corresponding `StreamError`s.
+    pub fn from_reader<F>(name: String, open: F) -> Protocol
+    where
+        F: Fn(&str) -> io::Result<Box<dyn ReadSeek>> + Send + Sync + 'static,
+    {
+        Protocol::new(name, ReaderHandler(open))
     }
 
     fn register(&self, ctx: *mut mpv_sys::mpv_handle) -> Result<()> {
@@ -270,19 +435,289 @@ impl<T: RefUnwindSafe, U: RefUnwindSafe> Protocol<T, U> {
                 mpv_sys::mpv_stream_cb_add_ro(
                     ctx,
                     name.as_ptr(),
-                    self.data as *mut _,
-                    Some(open_wrapper::<T, U>),
+                    &*self.handler as *const Box<dyn StreamHandler> as *mut _,
+                    Some(open_wrapper),
                 ),
             )
         }
     }
 }
 
-impl<T: RefUnwindSafe, U: RefUnwindSafe> Drop for Protocol<T, U> {
+/// Types that can back the streams of `Protocol::from_reader`.
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+struct ReaderHandler<F>(F);
+
+impl<F> StreamHandler for ReaderHandler<F>
+where
+    F: Fn(&str) -> io::Result<Box<dyn ReadSeek>> + Send + Sync,
+{
+    fn open(&self, uri: &str) -> OpenResult {
+        let reader = (self.0)(uri)?;
+        Ok(Box::new(ReaderStream(reader)))
+    }
+}
+
+struct ReaderStream(Box<dyn ReadSeek>);
+
+impl Stream for ReaderStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            match self.0.read(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                ret => return ret.map_err(|e| io_err(&e)),
+            }
+        }
+    }
+
+    fn seek(&mut self, offset: u64) -> Result<u64> {
+        self.0
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| io_err(&e))
+    }
+
+    fn size(&mut self) -> Option<u64> {
+        let current = self.0.seek(SeekFrom::Current(0)).ok()?;
+        let end = self.0.seek(SeekFrom::End(0)).ok()?;
+        self.0.seek(SeekFrom::Start(current)).ok()?;
+        Some(end)
+    }
+}
+
+struct MemoryBuffer(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl AsRef<[u8]> for MemoryBuffer {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
+
+#[must_use = "dropping a StreamRegistration makes its playlist entry fail to open"]
+/// Keeps the stream of `Mpv::load_from_memory` or `Mpv::load_from_channel` available to mpv.
+///
+/// mpv opens a playlist entry's stream every time it plays the entry, and may reopen it to
+/// seek back past its cache. Once the registration is dropped, opening the entry fails, but
+/// streams that are already open keep playing.
+pub struct StreamRegistration {
+    id: u64,
+    streams: Arc<PendingStreams>,
+}
+
+impl Drop for StreamRegistration {
     fn drop(&mut self) {
-        unsafe {
-            Box::from_raw(self.data);
-            // data.cookie will be consumed by the close callback
-        };
+        self.streams.streams.lock().remove(&self.id);
+    }
+}
+
+/// Feeds the stream of `Mpv::load_from_channel`.
+pub struct PushSender {
+    tx: SyncSender<Bytes>,
+    registration: StreamRegistration,
+}
+
+impl PushSender {
+    #[inline]
+    /// Push a chunk of the stream, blocking while the buffer is full. Chunks are handed to mpv
+    /// without being copied, e.g. a `Vec<u8>` or a slice of a larger `Bytes`.
+    ///
+    /// Fails, returning the chunk, once mpv has closed the stream.
+    pub fn send<B: Into<Bytes>>(&self, chunk: B) -> ::std::result::Result<(), SendError<Bytes>> {
+        self.tx.send(chunk.into())
+    }
+
+    #[inline]
+    /// Push a chunk of the stream, failing instead of blocking if the buffer is full.
+    pub fn try_send<B: Into<Bytes>>(
+        &self,
+        chunk: B,
+    ) -> ::std::result::Result<(), TrySendError<Bytes>> {
+        self.tx.try_send(chunk.into())
+    }
+
+    #[inline]
+    /// Mark the end of the stream. mpv reaches EOF once it has consumed all buffered chunks.
+    ///
+    /// The returned `StreamRegistration` keeps the stream available until mpv opens it, e.g.
+    /// if it was appended to the playlist. Dropping the `PushSender` instead also removes the
+    /// stream, so mpv fails to open it if it hasn't already.
+    pub fn finish(self) -> StreamRegistration {
+        self.registration
+    }
+}
+
+struct PushStream {
+    // `None` once the stream has been closed
+    rx: Option<Receiver<Bytes>>,
+    shutdown: Arc<AtomicBool>,
+    chunk: Bytes,
+    pos: usize,
+}
+
+impl Stream for PushStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.pos == self.chunk.len() {
+            let rx = match self.rx {
+                Some(ref rx) => rx,
+                None => return Ok(0),
+            };
+            match rx.recv_timeout(PUSH_POLL_INTERVAL) {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                // `mpv_terminate_destroy` waits for every stream to close, so give up, or it
+                // never returns
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.shutdown.load(Ordering::Acquire) {
+                        return Ok(0);
+                    }
+                }
+                // The sender is gone, which marks the end of the stream
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn close(&mut self) {
+        // Fail blocked and future sends right away
+        self.rx = None;
+    }
+}
+
+// The streams passed to `Mpv::load_pending_stream`, kept until their `StreamRegistration`s are
+// dropped.
+pub(crate) struct PendingStreams {
+    registered: Mutex<bool>,
+    next_id: AtomicU64,
+    shutdown: Arc<AtomicBool>,
+    streams: Mutex<HashMap<u64, StreamFactory>>,
+}
+
+impl PendingStreams {
+    pub(crate) fn new() -> PendingStreams {
+        PendingStreams {
+            registered: Mutex::new(false),
+            next_id: AtomicU64::new(0),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Called right before the core is destroyed. Makes open streams give up waiting for data,
+    // and drops the channels that were never opened, which fails their blocked senders.
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        self.streams.lock().clear();
+    }
+
+    fn open(&self, uri: &str) -> OpenResult {
+        let id = uri.splitn(2, "://").nth(1).and_then(|id| id.parse().ok());
+        id.and_then(|id| self.streams.lock().get_mut(&id).and_then(|open| open()))
+            .ok_or(StreamError::NotFound)
+    }
+}
+
+#[cfg(feature = "async_protocols")]
+/// A `StreamHandler` that opens `tokio` readers, and bridges mpv's blocking stream callbacks to
+/// them by blocking on a runtime.
+pub struct AsyncStreamHandler<F> {
+    handle: Handle,
+    open: F,
+    _runtime: Option<Runtime>,
+}
+
+#[cfg(feature = "async_protocols")]
+impl<F, Fut, R> AsyncStreamHandler<F>
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = io::Result<R>>,
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    #[inline]
+    /// Create a handler with a dedicated runtime, which drives its readers on a single worker
+    /// thread.
+    ///
+    /// `open` is called with the URI of every stream mpv opens, including the protocol prefix.
+    pub fn new(open: F) -> io::Result<AsyncStreamHandler<F>> {
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("mpv-rs-protocol")
+            .enable_all()
+            .build()?;
+        Ok(AsyncStreamHandler {
+            handle: runtime.handle().clone(),
+            open,
+            _runtime: Some(runtime),
+        })
+    }
+
+    #[inline]
+    /// Create a handler that drives its readers on an existing runtime.
+    ///
+    /// The runtime must run on other threads than mpv's, e.g. a multi threaded runtime, as a
+    /// current thread runtime can't drive IO from `Handle::block_on`.
+    pub fn with_handle(handle: Handle, open: F) -> AsyncStreamHandler<F> {
+        AsyncStreamHandler {
+            handle,
+            open,
+            _runtime: None,
+        }
+    }
+}
+
+#[cfg(feature = "async_protocols")]
+impl<F, Fut, R> StreamHandler for AsyncStreamHandler<F>
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = io::Result<R>>,
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    fn open(&self, uri: &str) -> OpenResult {
+        let reader = self.handle.block_on((self.open)(uri.to_owned()))?;
+        Ok(Box::new(AsyncStream {
+            handle: self.handle.clone(),
+            reader,
+        }))
+    }
+}
+
+#[cfg(feature = "async_protocols")]
+struct AsyncStream<R> {
+    handle: Handle,
+    reader: R,
+}
+
+#[cfg(feature = "async_protocols")]
+impl<R: AsyncRead + AsyncSeek + Unpin + Send> Stream for AsyncStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let reader = &mut self.reader;
+        self.handle
+            .block_on(reader.read(buf))
+            .map_err(|e| io_err(&e))
+    }
+
+    fn seek(&mut self, offset: u64) -> Result<u64> {
+        let reader = &mut self.reader;
+        self.handle
+            .block_on(reader.seek(SeekFrom::Start(offset)))
+            .map_err(|e| io_err(&e))
+    }
+
+    fn size(&mut self) -> Option<u64> {
+        let reader = &mut self.reader;
+        self.handle
+            .block_on(async move {
+                let current = reader.seek(SeekFrom::Current(0)).await?;
+                let end = reader.seek(SeekFrom::End(0)).await?;
+                reader.seek(SeekFrom::Start(current)).await?;
+                Ok::<_, io::Error>(end)
+            })
+            .ok()
     }
 }
@@ -15,3 +15,1024 @@
 // You should have received a copy of the GNU Lesser General Public
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Custom rendering via mpv's render API, which lets the application embed the video into its
+//! own OpenGL context, or render it into byte buffers with `SoftwareRenderContext`.
+//!
+//! Set the `vo` option to `libmpv` before loading any files, otherwise mpv may open its own
+//! window.
+//!
+//! # Backends
+//! OpenGL is the only hardware accelerated backend of the render API that libmpv offers. The
+//! software renderer works everywhere, but is much slower.
+//!
+//! * Direct3D 11: libmpv has no D3D11 or DXGI interop. On Windows, an OpenGL ES context
+//!   created with ANGLE can be used instead, whose framebuffer may be backed by a D3D11 texture
+//!   via `EGL_ANGLE_d3d_texture_client_buffer`.
+//! * Vulkan: libmpv only uses Vulkan (via libplacebo) for its own windows, and the render API
+//!   has no parameters for an external instance, device or queue. Applications rendering with
+//!   Vulkan can render into an OpenGL texture that shares its memory with a Vulkan image via
+//!   `GL_EXT_memory_object`, or embed mpv's window with the `wid` option.
+//! * Metal: libmpv has no Metal backend either. On macOS and iOS, an OpenGL ES context created
+//!   with ANGLE's Metal backend can render into a `CAMetalLayer`, otherwise the deprecated CGL
+//!   OpenGL contexts remain.
+
+#[cfg(feature = "egui_texture")]
+/// Rendering into `egui` textures
+pub mod egui;
+
+use super::*;
+
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw as ctype;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+#[cfg(feature = "winit")]
+use glutin::{event_loop::EventLoopProxy, window::Window, ContextWrapper, PossiblyCurrent};
+
+type GetProcAddress<'a> = Box<dyn FnMut(&str) -> *const ctype::c_void + 'a>;
+type UpdateCallback = Mutex<Option<Box<dyn FnMut() + Send>>>;
+
+unsafe extern "C" fn get_proc_address_wrapper(
+    ctx: *mut ctype::c_void,
+    name: *const ctype::c_char,
+) -> *mut ctype::c_void {
+    let get_proc_address = &mut *(ctx as *mut GetProcAddress<'_>);
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    match panic::catch_unwind(AssertUnwindSafe(|| get_proc_address(name))) {
+        Ok(addr) => addr as *mut _,
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn update_wrapper(cb_ctx: *mut ctype::c_void) {
+    let callback = &*(cb_ctx as *const UpdateCallback);
+
+    if let Some(ref mut callback) = *callback.lock() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| callback()));
+    }
+}
+
+// The OpenGL functions used by `RenderContext::grab_frame`
+#[derive(Clone, Copy)]
+struct GrabGl {
+    get_integerv: unsafe extern "system" fn(u32, *mut i32),
+    gen_framebuffers: unsafe extern "system" fn(i32, *mut u32),
+    delete_framebuffers: unsafe extern "system" fn(i32, *const u32),
+    bind_framebuffer: unsafe extern "system" fn(u32, u32),
+    check_framebuffer_status: unsafe extern "system" fn(u32) -> u32,
+    gen_renderbuffers: unsafe extern "system" fn(i32, *mut u32),
+    delete_renderbuffers: unsafe extern "system" fn(i32, *const u32),
+    bind_renderbuffer: unsafe extern "system" fn(u32, u32),
+    renderbuffer_storage: unsafe extern "system" fn(u32, u32, i32, i32),
+    framebuffer_renderbuffer: unsafe extern "system" fn(u32, u32, u32, u32),
+    read_pixels: unsafe extern "system" fn(i32, i32, i32, i32, u32, u32, *mut ctype::c_void),
+}
+
+impl GrabGl {
+    const FRAMEBUFFER: u32 = 0x8D40;
+    const FRAMEBUFFER_BINDING: u32 = 0x8CA6;
+    const FRAMEBUFFER_COMPLETE: u32 = 0x8CD5;
+    const RENDERBUFFER: u32 = 0x8D41;
+    const COLOR_ATTACHMENT0: u32 = 0x8CE0;
+    const RGBA: u32 = 0x1908;
+    const RGBA8: u32 = 0x8058;
+    const UNSIGNED_BYTE: u32 = 0x1401;
+
+    fn load(get_proc_address: &mut GetProcAddress<'_>) -> Option<GrabGl> {
+        let mut load = |name| {
+            let addr = get_proc_address(name);
+            if addr.is_null() {
+                None
+            } else {
+                Some(addr)
+            }
+        };
+        unsafe {
+            Some(GrabGl {
+                get_integerv: mem::transmute(load("glGetIntegerv")?),
+                gen_framebuffers: mem::transmute(load("glGenFramebuffers")?),
+                delete_framebuffers: mem::transmute(load("glDeleteFramebuffers")?),
+                bind_framebuffer: mem::transmute(load("glBindFramebuffer")?),
+                check_framebuffer_status: mem::transmute(load("glCheckFramebufferStatus")?),
+                gen_renderbuffers: mem::transmute(load("glGenRenderbuffers")?),
+                delete_renderbuffers: mem::transmute(load("glDeleteRenderbuffers")?),
+                bind_renderbuffer: mem::transmute(load("glBindRenderbuffer")?),
+                renderbuffer_storage: mem::transmute(load("glRenderbufferStorage")?),
+                framebuffer_renderbuffer: mem::transmute(load("glFramebufferRenderbuffer")?),
+                read_pixels: mem::transmute(load("glReadPixels")?),
+            })
+        }
+    }
+}
+
+fn param(
+    type_: mpv_sys::mpv_render_param_type,
+    data: *mut ctype::c_void,
+) -> mpv_sys::mpv_render_param {
+    mpv_sys::mpv_render_param { type_, data }
+}
+
+fn params_end() -> mpv_sys::mpv_render_param {
+    param(
+        mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_INVALID,
+        ptr::null_mut(),
+    )
+}
+
+// Shared by `RenderContext` and `SoftwareRenderContext`
+fn set_update_callback(
+    ctx: NonNull<mpv_sys::mpv_render_context>,
+    slot: &mut Option<Box<UpdateCallback>>,
+    callback: Box<dyn FnMut() + Send>,
+) {
+    if let Some(ref slot) = *slot {
+        let old = slot.lock().replace(callback);
+        drop(old);
+        return;
+    }
+
+    let new: Box<UpdateCallback> = Box::new(Mutex::new(Some(callback)));
+    unsafe {
+        mpv_sys::mpv_render_context_set_update_callback(
+            ctx.as_ptr(),
+            Some(update_wrapper),
+            &*new as *const UpdateCallback as *mut _,
+        );
+    }
+    *slot = Some(new);
+}
+
+/// An OpenGL render context. It borrows its `Mpv`, as it has to be freed before the core is
+/// destroyed.
+///
+/// All methods have to be called from the thread on which the OpenGL context is current, and
+/// this thread must not call any other libmpv functions, otherwise deadlocks can occur. Thus a
+/// `RenderContext` is neither `Send` nor `Sync`, and should be created on the render thread.
+/// Other threads can be woken up by mpv through an `UpdateNotifier` or the update callback,
+/// which are `Send`.
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<mpv::render::RenderContext>();
+/// ```
+pub struct RenderContext<'parent> {
+    ctx: NonNull<mpv_sys::mpv_render_context>,
+    // mpv may call these until the context is freed
+    get_proc_address: Box<GetProcAddress<'parent>>,
+    update_callback: Option<Box<UpdateCallback>>,
+    _mpv: PhantomData<&'parent Mpv>,
+    // Bound to the thread of the OpenGL context, regardless of the other fields
+    _not_send: PhantomData<*const ()>,
+}
+
+#[derive(Clone)]
+/// A handle to wait for mpv to request an update of a `RenderContext`, which can be sent to
+/// other threads, see `RenderContext::update_notifier`.
+pub struct UpdateNotifier(Arc<(Mutex<bool>, Condvar)>);
+
+impl UpdateNotifier {
+    #[inline]
+    /// Block until an update was requested since the last wait, or `notify` was called.
+    pub fn wait(&self) {
+        let (ref pending, ref cvar) = *self.0;
+        let mut pending = pending.lock();
+        while !*pending {
+            cvar.wait(&mut pending);
+        }
+        *pending = false;
+    }
+
+    #[inline]
+    /// Like `wait`, but gives up after `timeout`. Returns whether an update was requested.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (ref pending, ref cvar) = *self.0;
+        let mut pending = pending.lock();
+        while !*pending {
+            if cvar.wait_until(&mut pending, deadline).timed_out() {
+                return false;
+            }
+        }
+        *pending = false;
+        true
+    }
+
+    #[inline]
+    /// Wake up a waiting render thread as if an update was requested, e.g. so that it can
+    /// notice that it should exit.
+    pub fn notify(&self) {
+        let (ref pending, ref cvar) = *self.0;
+        *pending.lock() = true;
+        cvar.notify_all();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// An OpenGL framebuffer that mpv renders into, e.g. an offscreen framebuffer of the
+/// application.
+pub struct OpenGlFbo {
+    /// The framebuffer object name as returned by `glGenFramebuffers`, or `0` for the default
+    /// framebuffer. It must be complete and color-renderable.
+    pub fbo: i32,
+    /// The width of the framebuffer.
+    pub width: i32,
+    /// The height of the framebuffer.
+    pub height: i32,
+    /// The internal format of the framebuffer's color attachment, e.g. `GL_RGBA8`, or `0` if
+    /// unknown.
+    pub internal_format: i32,
+}
+
+impl OpenGlFbo {
+    #[inline]
+    /// A framebuffer of the given size, with an unknown internal format.
+    pub fn new(fbo: i32, width: i32, height: i32) -> OpenGlFbo {
+        OpenGlFbo {
+            fbo,
+            width,
+            height,
+            internal_format: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Options of a single `RenderContext::render` call.
+pub struct RenderOptions {
+    /// Render the frame upside down, which is needed when rendering to the default framebuffer.
+    /// Defaults to `false`.
+    pub flip_y: bool,
+    /// Let mpv pace frames by blocking until the target display time of the frame is reached.
+    /// Defaults to `true`.
+    ///
+    /// If this is disabled, e.g. by a compositor that renders whatever is current immediately,
+    /// A/V sync will be slightly off, unless the target time is waited for by the application,
+    /// or the `video-timing-offset` option is set to `0`.
+    pub block_for_target_time: bool,
+    /// Don't render anything, but consider the frame as rendered. Defaults to `false`.
+    pub skip_rendering: bool,
+}
+
+impl Default for RenderOptions {
+    #[inline]
+    fn default() -> RenderOptions {
+        RenderOptions {
+            flip_y: false,
+            block_for_target_time: true,
+            skip_rendering: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Information about the next frame, see `RenderContext::next_frame_info`.
+pub struct FrameInfo {
+    /// The frame is not a new video frame, but a redraw request, e.g. because an option changed
+    /// while paused. Redraws are typically not subject to video timing.
+    pub redraw: bool,
+    /// The frame should reproduce the previous one exactly, as is done by some `video-sync`
+    /// modes.
+    pub repeat: bool,
+    /// mpv expects the render thread to block on vsync, by either delaying the render call, or
+    /// calling `RenderContext::report_swap` at vsync time.
+    pub block_vsync: bool,
+    /// The absolute time at which the frame should be displayed, in the same unit and base as
+    /// `Mpv::get_internal_time`. It can be `0` for redraws, or if the video is vsync locked.
+    pub target_time: i64,
+    /// The raw bitset of `mpv_render_frame_info_flag`s, which may contain flags unknown to this
+    /// crate.
+    pub flags: u64,
+}
+
+impl FrameInfo {
+    #[inline]
+    /// How long to wait from `now`, as returned by `Mpv::get_internal_time`, until the frame
+    /// should be displayed. This is zero if the target time has passed, and `None` if the frame
+    /// has no target time.
+    pub fn time_until_target(&self, now: i64) -> Option<Duration> {
+        if self.target_time == 0 {
+            None
+        } else {
+            Some(Duration::from_micros(
+                self.target_time.saturating_sub(now).max(0) as u64,
+            ))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What should happen after `RenderContext::update`.
+pub struct UpdateFlags(u64);
+
+impl UpdateFlags {
+    #[inline]
+    /// Whether a new frame should be rendered with `RenderContext::render`.
+    pub fn frame(self) -> bool {
+        self.0 & mpv_sys::mpv_render_update_flag_MPV_RENDER_UPDATE_FRAME as u64 != 0
+    }
+
+    #[inline]
+    /// The raw bitset of `mpv_render_update_flag`s, which may contain flags unknown to this
+    /// crate.
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The handles of a DRM/KMS display, see `RenderContext::with_drm`.
+pub struct DrmDisplay {
+    /// The DRM file descriptor, or a negative number if there is none.
+    pub fd: i32,
+    /// The id of the CRTC in use.
+    pub crtc_id: i32,
+    /// The id of the connector in use.
+    pub connector_id: i32,
+    /// Points to the `drmModeAtomicReq` pointer used by the render loop, which is usually
+    /// replaced every frame. Can be null.
+    pub atomic_request: *mut *mut ctype::c_void,
+    /// The DRM render node, used for VAAPI interop, or a negative number if there is none.
+    pub render_fd: i32,
+    /// The size of the draw plane surface in pixels, if it differs from the display mode.
+    pub draw_surface_size: Option<(i32, i32)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A render parameter that can be changed after the context has been created, see
+/// `RenderContext::set_parameter`.
+pub enum RenderParam<'a> {
+    /// The ICC profile of the display, e.g. after the window moved to another monitor. mpv
+    /// copies the data.
+    IccProfile(&'a [u8]),
+    /// The ambient light in lux, which mpv uses to adapt gamma and tone mapping.
+    AmbientLight(i32),
+}
+
+impl<'parent> RenderContext<'parent> {
+    #[inline]
+    /// Create a render context for the OpenGL context that is current on the calling thread.
+    ///
+    /// `get_proc_address` resolves the OpenGL function of the given name, returning null if it
+    /// doesn't exist, e.g. `glutin::Context::get_proc_address`.
+    ///
+    /// The context has to be current, which can't be checked through OpenGL itself, as calling
+    /// any of its functions without a current context is undefined behaviour. `from_glutin`
+    /// checks it through the windowing API instead.
+    pub fn new<F>(mpv: &'parent Mpv, get_proc_address: F) -> Result<RenderContext<'parent>>
+    where
+        F: FnMut(&str) -> *const ctype::c_void + 'parent,
+    {
+        RenderContext::create(mpv, Box::new(get_proc_address), false, &[])
+    }
+
+    #[inline]
+    /// Like `new`, but enables advanced control, which allows features like direct rendering,
+    /// and is needed for precise frame scheduling with `next_frame_info`.
+    ///
+    /// In this mode the core may wait for the render thread, so `update_callback` is set right
+    /// away, `update` must be called after every invocation of it, and the render thread must
+    /// never wait for the core.
+    pub fn with_advanced_control<F, U>(
+        mpv: &'parent Mpv,
+        get_proc_address: F,
+        update_callback: U,
+    ) -> Result<RenderContext<'parent>>
+    where
+        F: FnMut(&str) -> *const ctype::c_void + 'parent,
+        U: FnMut() + Send + 'static,
+    {
+        let mut ctx = RenderContext::create(mpv, Box::new(get_proc_address), true, &[])?;
+        ctx.set_update_callback(update_callback);
+        Ok(ctx)
+    }
+
+    #[inline]
+    /// Like `new`, but for an EGL context on a GBM surface of the DRM/KMS display `drm`, e.g.
+    /// on embedded devices without a windowing system.
+    ///
+    /// # Safety
+    /// `drm.atomic_request` has to be null, or stay valid until the context is dropped.
+    pub unsafe fn with_drm<F>(
+        mpv: &'parent Mpv,
+        get_proc_address: F,
+        drm: DrmDisplay,
+    ) -> Result<RenderContext<'parent>>
+    where
+        F: FnMut(&str) -> *const ctype::c_void + 'parent,
+    {
+        // mpv copies both structs
+        let mut drm_params = mpv_sys::mpv_opengl_drm_params {
+            fd: drm.fd,
+            crtc_id: drm.crtc_id,
+            connector_id: drm.connector_id,
+            atomic_request_ptr: drm.atomic_request as *mut _,
+            render_fd: drm.render_fd,
+        };
+        let mut surface_size;
+        let mut display = vec![param(
+            mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_DRM_DISPLAY,
+            &mut drm_params as *mut _ as *mut _,
+        )];
+        if let Some((width, height)) = drm.draw_surface_size {
+            surface_size = mpv_sys::mpv_opengl_drm_draw_surface_size { width, height };
+            display.push(param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_DRM_DRAW_SURFACE_SIZE,
+                &mut surface_size as *mut _ as *mut _,
+            ));
+        }
+        RenderContext::create(mpv, Box::new(get_proc_address), false, &display)
+    }
+
+    fn create(
+        mpv: &'parent Mpv,
+        get_proc_address: GetProcAddress<'parent>,
+        advanced_control: bool,
+        display: &[mpv_sys::mpv_render_param],
+    ) -> Result<RenderContext<'parent>> {
+        mpv.check_shutdown()?;
+        let mut get_proc_address = Box::new(get_proc_address);
+        let mut advanced_control: ctype::c_int = advanced_control as _;
+        let mut init_params = mpv_sys::mpv_opengl_init_params {
+            get_proc_address: Some(get_proc_address_wrapper),
+            get_proc_address_ctx: &mut *get_proc_address as *mut GetProcAddress<'parent> as *mut _,
+            extra_exts: ptr::null(),
+        };
+        let mut params = vec![
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_API_TYPE,
+                mpv_sys::MPV_RENDER_API_TYPE_OPENGL.as_ptr() as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_OPENGL_INIT_PARAMS,
+                &mut init_params as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_ADVANCED_CONTROL,
+                &mut advanced_control as *mut _ as *mut _,
+            ),
+        ];
+        params.extend_from_slice(display);
+        params.push(params_end());
+
+        let mut ctx = ptr::null_mut();
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_create(&mut ctx, mpv.ctx.as_ptr(), params.as_mut_ptr())
+        })?;
+
+        Ok(RenderContext {
+            ctx: NonNull::new(ctx).ok_or(Error::Null)?,
+            get_proc_address,
+            update_callback: None,
+            _mpv: PhantomData,
+            _not_send: PhantomData,
+        })
+    }
+
+    #[inline]
+    /// Render the current video frame into the framebuffer `fbo`, configured by `options`.
+    pub fn render(&self, fbo: OpenGlFbo, options: RenderOptions) -> Result<()> {
+        let mut fbo = mpv_sys::mpv_opengl_fbo {
+            fbo: fbo.fbo,
+            w: fbo.width,
+            h: fbo.height,
+            internal_format: fbo.internal_format,
+        };
+        let mut flip_y: ctype::c_int = options.flip_y as _;
+        let mut block_for_target_time: ctype::c_int = options.block_for_target_time as _;
+        let mut skip_rendering: ctype::c_int = options.skip_rendering as _;
+        let mut params = [
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_OPENGL_FBO,
+                &mut fbo as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_FLIP_Y,
+                &mut flip_y as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_BLOCK_FOR_TARGET_TIME,
+                &mut block_for_target_time as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SKIP_RENDERING,
+                &mut skip_rendering as *mut _ as *mut _,
+            ),
+            params_end(),
+        ];
+
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_render(self.ctx.as_ptr(), params.as_mut_ptr())
+        })
+    }
+
+    #[inline]
+    /// Set the callback that is invoked when a new frame is available, or a redraw is required,
+    /// replacing any previous one. It is invoked once right away.
+    ///
+    /// The callback is called from an mpv thread, and must not call any libmpv functions,
+    /// including the methods of `RenderContext`. Instead, it should signal the render thread to
+    /// call `update`.
+    pub fn set_update_callback<F: FnMut() + Send + 'static>(&mut self, callback: F) {
+        set_update_callback(self.ctx, &mut self.update_callback, Box::new(callback));
+    }
+
+    #[inline]
+    /// Replace the update callback with one that signals the returned `UpdateNotifier`, which
+    /// the render thread can wait on before calling `update`.
+    pub fn update_notifier(&mut self) -> UpdateNotifier {
+        let notifier = UpdateNotifier(Arc::new((Mutex::new(false), Condvar::new())));
+        let signal = notifier.clone();
+        self.set_update_callback(move || signal.notify());
+        notifier
+    }
+
+    #[inline]
+    /// Tell mpv that the rendered frame was just presented, e.g. right after swapping buffers.
+    /// This improves mpv's timing, e.g. for display sync.
+    ///
+    /// This is optional, but once it has been called, it has to be called after every render.
+    pub fn report_swap(&self) {
+        unsafe { mpv_sys::mpv_render_context_report_swap(self.ctx.as_ptr()) }
+    }
+
+    #[inline]
+    /// Render the current frame at the given size into a temporary framebuffer, and read it
+    /// back as RGBA8 pixels, with the top row first.
+    ///
+    /// Unlike the `screenshot` commands, this never includes the OSD. mpv considers the frame as
+    /// rendered, so this shouldn't be mixed with regular rendering on the same context.
+    pub fn grab_frame(&mut self, width: i32, height: i32) -> Result<Vec<u8>> {
+        if width <= 0 || height <= 0 {
+            return Err(Error::Raw(ErrorCode::InvalidParameter));
+        }
+        let gl =
+            GrabGl::load(&mut self.get_proc_address).ok_or(Error::Raw(ErrorCode::Unsupported))?;
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+        unsafe {
+            let mut previous = 0;
+            (gl.get_integerv)(GrabGl::FRAMEBUFFER_BINDING, &mut previous);
+            let (mut fbo, mut rbo) = (0, 0);
+            (gl.gen_framebuffers)(1, &mut fbo);
+            (gl.gen_renderbuffers)(1, &mut rbo);
+            (gl.bind_renderbuffer)(GrabGl::RENDERBUFFER, rbo);
+            (gl.renderbuffer_storage)(GrabGl::RENDERBUFFER, GrabGl::RGBA8, width, height);
+            (gl.bind_framebuffer)(GrabGl::FRAMEBUFFER, fbo);
+            (gl.framebuffer_renderbuffer)(
+                GrabGl::FRAMEBUFFER,
+                GrabGl::COLOR_ATTACHMENT0,
+                GrabGl::RENDERBUFFER,
+                rbo,
+            );
+
+            let ret = if (gl.check_framebuffer_status)(GrabGl::FRAMEBUFFER)
+                == GrabGl::FRAMEBUFFER_COMPLETE
+            {
+                self.render(
+                    OpenGlFbo {
+                        fbo: fbo as _,
+                        width,
+                        height,
+                        internal_format: GrabGl::RGBA8 as _,
+                    },
+                    RenderOptions {
+                        block_for_target_time: false,
+                        ..Default::default()
+                    },
+                )
+            } else {
+                Err(Error::Raw(ErrorCode::Generic))
+            };
+            if ret.is_ok() {
+                (gl.bind_framebuffer)(GrabGl::FRAMEBUFFER, fbo);
+                (gl.read_pixels)(
+                    0,
+                    0,
+                    width,
+                    height,
+                    GrabGl::RGBA,
+                    GrabGl::UNSIGNED_BYTE,
+                    pixels.as_mut_ptr() as *mut _,
+                );
+            }
+
+            (gl.bind_framebuffer)(GrabGl::FRAMEBUFFER, previous as _);
+            (gl.bind_renderbuffer)(GrabGl::RENDERBUFFER, 0);
+            (gl.delete_framebuffers)(1, &fbo);
+            (gl.delete_renderbuffers)(1, &rbo);
+            ret?;
+        }
+        Ok(pixels)
+    }
+
+    #[inline]
+    /// Change a render parameter without recreating the context.
+    pub fn set_parameter(&self, parameter: RenderParam) -> Result<()> {
+        let mut lux: ctype::c_int;
+        let mut icc_profile: mpv_sys::mpv_byte_array;
+        let raw = match parameter {
+            RenderParam::IccProfile(data) => {
+                icc_profile = mpv_sys::mpv_byte_array {
+                    data: data.as_ptr() as *mut _,
+                    size: data.len(),
+                };
+                param(
+                    mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_ICC_PROFILE,
+                    &mut icc_profile as *mut _ as *mut _,
+                )
+            }
+            RenderParam::AmbientLight(value) => {
+                lux = value;
+                param(
+                    mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_AMBIENT_LIGHT,
+                    &mut lux as *mut _ as *mut _,
+                )
+            }
+        };
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_set_parameter(self.ctx.as_ptr(), raw)
+        })
+    }
+
+    #[inline]
+    /// Set the ambient light in lux, which mpv uses to adapt gamma and tone mapping, e.g. from
+    /// the readings of a light sensor.
+    pub fn set_ambient_light(&self, lux: i32) -> Result<()> {
+        self.set_parameter(RenderParam::AmbientLight(lux))
+    }
+
+    #[inline]
+    /// Information about the next frame that will be rendered, or `None` if no frame has been
+    /// queued yet.
+    ///
+    /// This can be used to log presentation timing, or to decide how to schedule the next
+    /// swap, see `FrameInfo::time_until_target`.
+    pub fn next_frame_info(&self) -> Result<Option<FrameInfo>> {
+        let mut info = mpv_sys::mpv_render_frame_info {
+            flags: 0,
+            target_time: 0,
+        };
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_get_info(
+                self.ctx.as_ptr(),
+                param(
+                    mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_NEXT_FRAME_INFO,
+                    &mut info as *mut _ as *mut _,
+                ),
+            )
+        })?;
+
+        let flag = |flag: mpv_sys::mpv_render_frame_info_flag| info.flags & flag as u64 != 0;
+        if !flag(mpv_sys::mpv_render_frame_info_flag_MPV_RENDER_FRAME_INFO_PRESENT) {
+            return Ok(None);
+        }
+        Ok(Some(FrameInfo {
+            redraw: flag(mpv_sys::mpv_render_frame_info_flag_MPV_RENDER_FRAME_INFO_REDRAW),
+            repeat: flag(mpv_sys::mpv_render_frame_info_flag_MPV_RENDER_FRAME_INFO_REPEAT),
+            block_vsync: flag(
+                mpv_sys::mpv_render_frame_info_flag_MPV_RENDER_FRAME_INFO_BLOCK_VSYNC,
+            ),
+            target_time: info.target_time,
+            flags: info.flags,
+        }))
+    }
+
+    #[inline]
+    /// Call this on the render thread after the update callback was invoked, to find out
+    /// whether a frame should be rendered.
+    pub fn update(&self) -> UpdateFlags {
+        UpdateFlags(unsafe { mpv_sys::mpv_render_context_update(self.ctx.as_ptr()) })
+    }
+}
+
+impl<'parent> Drop for RenderContext<'parent> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            mpv_sys::mpv_render_context_free(self.ctx.as_ptr());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The pixel format of a `SoftwareSurface`. mpv doesn't render alpha, the padding byte of the
+/// 4 byte formats contains garbage.
+pub enum SoftwareFormat {
+    /// `rgb0`: red, green, blue and a padding byte.
+    Rgb0,
+    /// `bgr0`: blue, green, red and a padding byte.
+    Bgr0,
+    /// `0bgr`: a padding byte, blue, green and red.
+    ZeroBgr,
+    /// `0rgb`: a padding byte, red, green and blue.
+    ZeroRgb,
+    /// `rgb24`: red, green and blue without padding. mpv discourages this, as it is very slow.
+    Rgb24,
+}
+
+impl SoftwareFormat {
+    #[inline]
+    /// The name mpv uses for this format.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SoftwareFormat::Rgb0 => "rgb0",
+            SoftwareFormat::Bgr0 => "bgr0",
+            SoftwareFormat::ZeroBgr => "0bgr",
+            SoftwareFormat::ZeroRgb => "0rgb",
+            SoftwareFormat::Rgb24 => "rgb24",
+        }
+    }
+
+    #[inline]
+    /// The size of a pixel in bytes.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            SoftwareFormat::Rgb24 => 3,
+            _ => 4,
+        }
+    }
+
+    fn as_cstr(self) -> &'static [u8] {
+        match self {
+            SoftwareFormat::Rgb0 => b"rgb0\0",
+            SoftwareFormat::Bgr0 => b"bgr0\0",
+            SoftwareFormat::ZeroBgr => b"0bgr\0",
+            SoftwareFormat::ZeroRgb => b"0rgb\0",
+            SoftwareFormat::Rgb24 => b"rgb24\0",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The layout of a buffer that a `SoftwareRenderContext` renders into.
+pub struct SoftwareSurface {
+    /// The width in pixels.
+    pub width: i32,
+    /// The height in pixels.
+    pub height: i32,
+    /// The number of bytes from the start of one row to the start of the next. It has to be a
+    /// multiple of the pixel size, and large enough for `width` pixels. mpv is fastest if it,
+    /// and the address of the buffer, are multiples of 64.
+    pub stride: usize,
+    /// The pixel format.
+    pub format: SoftwareFormat,
+}
+
+impl SoftwareSurface {
+    #[inline]
+    /// A surface of the given size, whose rows are packed without padding.
+    pub fn new(width: i32, height: i32, format: SoftwareFormat) -> SoftwareSurface {
+        SoftwareSurface {
+            width,
+            height,
+            stride: width.max(0) as usize * format.bytes_per_pixel(),
+            format,
+        }
+    }
+
+    #[inline]
+    /// The number of bytes a buffer for this surface needs, which is `stride * height`.
+    pub fn buffer_len(&self) -> usize {
+        self.stride.saturating_mul(self.height.max(0) as usize)
+    }
+
+    // mpv doesn't check the buffer, so invalid layouts would make it write out of bounds
+    pub(crate) fn check(&self, buffer_len: usize) -> Result<()> {
+        let bpp = self.format.bytes_per_pixel();
+        let row_pixels = self.stride / bpp;
+        if self.width <= 0
+            || self.height <= 0
+            || row_pixels * bpp != self.stride
+            || row_pixels < self.width as usize
+            || buffer_len < self.buffer_len()
+        {
+            return Err(Error::Raw(ErrorCode::InvalidParameter));
+        }
+        Ok(())
+    }
+}
+
+/// A render context that renders into byte buffers on the CPU, e.g. for thumbnails, CPU
+/// compositing, or platforms without OpenGL. Requires mpv 0.33. It borrows its `Mpv`, as it
+/// has to be freed before the core is destroyed.
+///
+/// Like with `RenderContext`, the thread that renders must not call any other libmpv
+/// functions, and is woken up through an `UpdateNotifier` or the update callback.
+pub struct SoftwareRenderContext<'parent> {
+    ctx: NonNull<mpv_sys::mpv_render_context>,
+    // mpv may call this until the context is freed
+    update_callback: Option<Box<UpdateCallback>>,
+    _mpv: PhantomData<&'parent Mpv>,
+}
+
+impl<'parent> SoftwareRenderContext<'parent> {
+    #[inline]
+    /// Create a software render context.
+    pub fn new(mpv: &'parent Mpv) -> Result<SoftwareRenderContext<'parent>> {
+        mpv.check_shutdown()?;
+        let mut params = [
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_API_TYPE,
+                mpv_sys::MPV_RENDER_API_TYPE_SW.as_ptr() as *mut _,
+            ),
+            params_end(),
+        ];
+
+        let mut ctx = ptr::null_mut();
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_create(&mut ctx, mpv.ctx.as_ptr(), params.as_mut_ptr())
+        })?;
+
+        Ok(SoftwareRenderContext {
+            ctx: NonNull::new(ctx).ok_or(Error::Null)?,
+            update_callback: None,
+            _mpv: PhantomData,
+        })
+    }
+
+    #[inline]
+    /// Render the current video frame into `buffer`, which is laid out as described by
+    /// `surface`. The video is scaled to the surface, with black bars if the aspect ratio
+    /// differs.
+    ///
+    /// `options.flip_y` is ignored. Returns `Error::Raw(ErrorCode::InvalidParameter)` if the
+    /// surface is invalid, or `buffer` is smaller than `surface.buffer_len()`.
+    pub fn render(
+        &self,
+        buffer: &mut [u8],
+        surface: SoftwareSurface,
+        options: RenderOptions,
+    ) -> Result<()> {
+        surface.check(buffer.len())?;
+        let mut size: [ctype::c_int; 2] = [surface.width, surface.height];
+        let mut stride: usize = surface.stride;
+        let mut block_for_target_time: ctype::c_int = options.block_for_target_time as _;
+        let mut skip_rendering: ctype::c_int = options.skip_rendering as _;
+        let mut params = [
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_SIZE,
+                size.as_mut_ptr() as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_FORMAT,
+                surface.format.as_cstr().as_ptr() as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_STRIDE,
+                &mut stride as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_POINTER,
+                buffer.as_mut_ptr() as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_BLOCK_FOR_TARGET_TIME,
+                &mut block_for_target_time as *mut _ as *mut _,
+            ),
+            param(
+                mpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SKIP_RENDERING,
+                &mut skip_rendering as *mut _ as *mut _,
+            ),
+            params_end(),
+        ];
+
+        mpv_err((), unsafe {
+            mpv_sys::mpv_render_context_render(self.ctx.as_ptr(), params.as_mut_ptr())
+        })
+    }
+
+    #[inline]
+    /// Like `render`, but into a new packed buffer of the given size.
+    pub fn render_to_vec(
+        &self,
+        width: i32,
+        height: i32,
+        format: SoftwareFormat,
+        options: RenderOptions,
+    ) -> Result<Vec<u8>> {
+        let surface = SoftwareSurface::new(width, height, format);
+        let mut buffer = vec![0u8; surface.buffer_len()];
+        self.render(&mut buffer, surface, options)?;
+        Ok(buffer)
+    }
+
+    #[inline]
+    /// See `RenderContext::set_update_callback`.
+    pub fn set_update_callback<F: FnMut() + Send + 'static>(&mut self, callback: F) {
+        set_update_callback(self.ctx, &mut self.update_callback, Box::new(callback));
+    }
+
+    #[inline]
+    /// See `RenderContext::update_notifier`.
+    pub fn update_notifier(&mut self) -> UpdateNotifier {
+        let notifier = UpdateNotifier(Arc::new((Mutex::new(false), Condvar::new())));
+        let signal = notifier.clone();
+        self.set_update_callback(move || signal.notify());
+        notifier
+    }
+
+    #[inline]
+    /// Call this on the render thread after the update callback was invoked, to find out
+    /// whether a frame should be rendered.
+    pub fn update(&self) -> UpdateFlags {
+        UpdateFlags(unsafe { mpv_sys::mpv_render_context_update(self.ctx.as_ptr()) })
+    }
+}
+
+impl<'parent> Drop for SoftwareRenderContext<'parent> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            mpv_sys::mpv_render_context_free(self.ctx.as_ptr());
+        }
+    }
+}
+
+#[cfg(feature = "winit")]
+/// Glue for `glutin` windows, and the `winit` event loop they run on.
+impl<'parent> RenderContext<'parent> {
+    #[inline]
+    /// Create a render context for the OpenGL context of `windowed`, which has to be current on
+    /// the calling thread.
+    ///
+    /// Returns `Error::NoCurrentContext` if it isn't.
+    pub fn from_glutin(
+        mpv: &'parent Mpv,
+        windowed: &'parent ContextWrapper<PossiblyCurrent, Window>,
+    ) -> Result<RenderContext<'parent>> {
+        if !windowed.is_current() {
+            return Err(Error::NoCurrentContext);
+        }
+        RenderContext::new(mpv, move |name| windowed.get_proc_address(name))
+    }
+
+    #[inline]
+    /// Send `event` through `proxy` whenever mpv requests a redraw. The event loop should then
+    /// call `update`, and request a redraw of the window if a frame should be rendered.
+    pub fn set_update_proxy<T: Clone + Send + 'static>(
+        &mut self,
+        proxy: EventLoopProxy<T>,
+        event: T,
+    ) {
+        self.set_update_callback(move || {
+            // The event loop is gone, so there is nobody left to redraw
+            let _ = proxy.send_event(event.clone());
+        });
+    }
+
+    #[inline]
+    /// Render the current frame into the default framebuffer of `windowed` at its current
+    /// size, then swap its buffers and report the swap.
+    ///
+    /// `options.flip_y` is ignored, as the default framebuffer always has to be flipped.
+    pub fn render_window(
+        &self,
+        windowed: &ContextWrapper<PossiblyCurrent, Window>,
+        options: RenderOptions,
+    ) -> Result<()> {
+        let size = windowed.window().inner_size();
+        self.render(
+            OpenGlFbo::new(0, size.width as _, size.height as _),
+            RenderOptions {
+                flip_y: true,
+                ..options
+            },
+        )?;
+        windowed
+            .swap_buffers()
+            .map_err(|_| Error::Raw(ErrorCode::Generic))?;
+        self.report_swap();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sdl2")]
+/// Glue for SDL2 windows.
+impl<'parent> RenderContext<'parent> {
+    #[inline]
+    /// Create a render context for the OpenGL context `gl` of `window`, making it current on the
+    /// calling thread.
+    pub fn from_sdl_gl(
+        mpv: &'parent Mpv,
+        window: &sdl2::video::Window,
+        gl: &sdl2::video::GLContext,
+    ) -> Result<RenderContext<'parent>> {
+        window
+            .gl_make_current(gl)
+            .map_err(|_| Error::Raw(ErrorCode::Generic))?;
+        let video = window.subsystem().clone();
+        RenderContext::new(mpv, move |name| video.gl_get_proc_address(name) as *const _)
+    }
+}
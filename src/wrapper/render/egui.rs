@@ -0,0 +1,165 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Rendering into textures of `egui` apps that are painted with `egui_glow`.
+//!
+//! wgpu has no way to share textures with an OpenGL context, so apps using `egui_wgpu` have to
+//! switch to the `glow` backend to show video.
+
+use super::*;
+
+use ::egui::TextureId;
+use egui_glow::Painter;
+use glow::HasContext;
+
+/// Renders the video into an OpenGL texture that is registered with an `egui_glow::Painter`, so
+/// it can be shown like any other texture, e.g. with `egui::Image`.
+///
+/// All methods have to be called on the thread of the painter, with its context being current.
+/// The OpenGL objects are only freed by `destroy`, dropping leaks them.
+pub struct EguiVideo<'parent> {
+    ctx: RenderContext<'parent>,
+    fbo: glow::Framebuffer,
+    texture: glow::Texture,
+    texture_id: TextureId,
+    size: (i32, i32),
+}
+
+impl<'parent> EguiVideo<'parent> {
+    #[inline]
+    /// Create the render context and the texture. `get_proc_address` is the loader that the
+    /// painter's `glow::Context` was created with.
+    pub fn new<F>(
+        mpv: &'parent Mpv,
+        painter: &mut Painter,
+        get_proc_address: F,
+    ) -> Result<EguiVideo<'parent>>
+    where
+        F: FnMut(&str) -> *const ctype::c_void + 'parent,
+    {
+        let ctx = RenderContext::new(mpv, get_proc_address)?;
+        let gl = painter.gl().clone();
+
+        let (texture, fbo) = unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|_| Error::Raw(ErrorCode::Generic))?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for &(param, value) in &[
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, param, value as _);
+            }
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            match gl.create_framebuffer() {
+                Ok(fbo) => (texture, fbo),
+                Err(_) => {
+                    gl.delete_texture(texture);
+                    return Err(Error::Raw(ErrorCode::Generic));
+                }
+            }
+        };
+
+        Ok(EguiVideo {
+            ctx,
+            fbo,
+            texture,
+            texture_id: painter.register_native_texture(texture),
+            size: (0, 0),
+        })
+    }
+
+    #[inline]
+    /// The id of the video texture.
+    pub fn texture_id(&self) -> TextureId {
+        self.texture_id
+    }
+
+    #[inline]
+    /// The underlying render context, e.g. to set an update callback that requests a repaint.
+    pub fn render_context(&mut self) -> &mut RenderContext<'parent> {
+        &mut self.ctx
+    }
+
+    #[inline]
+    /// Render the current frame into the texture at the given size, resizing it if needed.
+    /// This has to happen before egui's frame is painted.
+    ///
+    /// The texture is RGBA8, and its first row is the top of the video, as egui expects.
+    pub fn render(&mut self, painter: &Painter, width: i32, height: i32) -> Result<TextureId> {
+        if self.size != (width, height) {
+            let gl = painter.gl();
+            let complete = unsafe {
+                gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA8 as _,
+                    width,
+                    height,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    None,
+                );
+                gl.bind_texture(glow::TEXTURE_2D, None);
+
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.fbo));
+                gl.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::TEXTURE_2D,
+                    Some(self.texture),
+                    0,
+                );
+                let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                status == glow::FRAMEBUFFER_COMPLETE
+            };
+            if !complete {
+                return Err(Error::Raw(ErrorCode::Generic));
+            }
+            self.size = (width, height);
+        }
+
+        // Rendering happens on the painter's context, so egui's draw calls are ordered after it
+        self.ctx.render(
+            OpenGlFbo {
+                fbo: self.fbo.0.get() as _,
+                width,
+                height,
+                internal_format: glow::RGBA8 as _,
+            },
+            RenderOptions::default(),
+        )?;
+        Ok(self.texture_id)
+    }
+
+    #[inline]
+    /// Free the render context, the texture, and the framebuffer.
+    pub fn destroy(self, painter: &mut Painter) {
+        drop(self.ctx);
+        // This deletes the texture as well
+        painter.free_texture(self.texture_id);
+        unsafe { painter.gl().delete_framebuffer(self.fbo) };
+    }
+}
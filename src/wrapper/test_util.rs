@@ -0,0 +1,98 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Helpers for tests that need a running core, but no media files or output devices: a headless
+//! `Mpv`, short synthetic media generated by libavfilter, and assertions that wait for the player.
+
+use super::*;
+
+use std::fmt::Debug;
+use std::thread;
+use std::time::Instant;
+
+/// How often `assert_property_eventually` polls the property.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Create an `Mpv` without audio or video output, which stays idle once playback ends.
+///
+/// youtube-dl is disabled, so tests don't access the network.
+pub fn headless() -> Result<Mpv> {
+    let mpv = Mpv::new()?;
+    mpv.set_property("vo", "null")?;
+    mpv.set_property("ao", "null")?;
+    mpv.set_property("idle", "yes")?;
+    mpv.set_property("ytdl", false)?;
+    Ok(mpv)
+}
+
+fn lavfi(graph: String) -> MediaSource {
+    MediaSource::Protocol {
+        scheme: "av".into(),
+        payload: format!("lavfi:{}", graph),
+    }
+}
+
+/// A sine tone of `duration`, as audio-only media.
+pub fn sine(duration: Duration) -> MediaSource {
+    lavfi(format!(
+        "sine=frequency=440:duration={}",
+        duration.as_secs_f64()
+    ))
+}
+
+/// A 320x240 test pattern at 25 fps of `duration`, as video-only media.
+pub fn testsrc(duration: Duration) -> MediaSource {
+    lavfi(format!(
+        "testsrc=size=320x240:rate=25:duration={}",
+        duration.as_secs_f64()
+    ))
+}
+
+/// Poll the property `name` until it equals `expected`, returning whether it did before
+/// `timeout` passed. Errors, e.g. while the property is unavailable, count as not equal.
+pub fn property_eventually<T>(mpv: &Mpv, name: &str, expected: &T, timeout: Duration) -> bool
+where
+    T: GetData + PartialEq,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if mpv.get_property::<T>(name).ok().as_ref() == Some(expected) {
+            return true;
+        } else if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Like `property_eventually`, but panics with the last value of the property if it didn't
+/// become `expected` in time.
+pub fn assert_property_eventually<T>(mpv: &Mpv, name: &str, expected: T, timeout: Duration)
+where
+    T: GetData + PartialEq + Debug,
+{
+    if !property_eventually(mpv, name, &expected, timeout) {
+        panic!(
+            "Property {} didn't become {:?} within {:?}, last value: {:?}",
+            name,
+            expected,
+            timeout,
+            mpv.get_property::<T>(name)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of mpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! Audio visualizations rendered by libavfilter, which are shown in place of the video of
+//! audio-only files through the `lavfi-complex` option.
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How `Visualizer::Waves` draws samples.
+pub enum WavesMode {
+    /// A point per sample.
+    Point,
+    /// A vertical line per sample.
+    Line,
+    /// Points, connected by lines.
+    PointToPoint,
+    /// A vertical line per sample, centered on the middle.
+    CenteredLine,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How `Visualizer::Spectrum` lays out channels.
+pub enum SpectrumMode {
+    /// All channels in the same area.
+    Combined,
+    /// Each channel in its own row.
+    Separate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How `Visualizer::VectorScope` plots the stereo channels.
+pub enum VectorScopeMode {
+    /// A Lissajous figure rotated by 45 degrees.
+    Lissajous,
+    /// An unrotated Lissajous figure.
+    LissajousXY,
+    /// A half circle.
+    Polar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A visualization of the audio, with the size of the generated video in pixels, see
+/// `Mpv::set_visualizer`.
+pub enum Visualizer {
+    /// The waveform, using the `showwaves` filter.
+    Waves {
+        #[allow(missing_docs)]
+        size: (u32, u32),
+        #[allow(missing_docs)]
+        mode: WavesMode,
+    },
+    /// The frequency spectrum over time, using the `showspectrum` filter.
+    Spectrum {
+        #[allow(missing_docs)]
+        size: (u32, u32),
+        #[allow(missing_docs)]
+        mode: SpectrumMode,
+    },
+    /// The stereo image, using the `avectorscope` filter.
+    VectorScope {
+        #[allow(missing_docs)]
+        size: (u32, u32),
+        #[allow(missing_docs)]
+        mode: VectorScopeMode,
+    },
+}
+
+impl Visualizer {
+    // The filter name, size and mode name
+    fn parts(&self) -> (&'static str, (u32, u32), &'static str) {
+        match *self {
+            Visualizer::Waves { size, mode } => (
+                "showwaves",
+                size,
+                match mode {
+                    WavesMode::Point => "point",
+                    WavesMode::Line => "line",
+                    WavesMode::PointToPoint => "p2p",
+                    WavesMode::CenteredLine => "cline",
+                },
+            ),
+            Visualizer::Spectrum { size, mode } => (
+                "showspectrum",
+                size,
+                match mode {
+                    SpectrumMode::Combined => "combined",
+                    SpectrumMode::Separate => "separate",
+                },
+            ),
+            Visualizer::VectorScope { size, mode } => (
+                "avectorscope",
+                size,
+                match mode {
+                    VectorScopeMode::Lissajous => "lissajous",
+                    VectorScopeMode::LissajousXY => "lissajous_xy",
+                    VectorScopeMode::Polar => "polar",
+                },
+            ),
+        }
+    }
+
+    /// The filter graph for the `lavfi-complex` option, which feeds the first audio track both
+    /// to the audio output and to the visualizer, e.g.
+    /// `[aid1]asplit[ao][viz];[viz]showwaves=s=640x360:mode=line[vo]`.
+    pub fn filter(&self) -> String {
+        let (filter, (width, height), mode) = self.parts();
+        format!(
+            "[aid1]asplit[ao][viz];[viz]{}=s={}x{}:mode={}[vo]",
+            filter, width, height, mode
+        )
+    }
+}
+
+impl Mpv {
+    /// Show `visualizer` as video, or remove the visualization if `None`. This replaces the
+    /// `lavfi-complex` option, so it is meant for audio-only files, and disables the video
+    /// track of other files.
+    ///
+    /// Returns `Error::InvalidArgument` if the size of the visualizer is zero.
+    pub fn set_visualizer(&self, visualizer: Option<Visualizer>) -> Result<()> {
+        match visualizer {
+            Some(visualizer) => {
+                let (_, (width, height), _) = visualizer.parts();
+                if width == 0 || height == 0 {
+                    return Err(Error::InvalidArgument);
+                }
+                self.set_property("lavfi-complex", visualizer.filter())
+            }
+            None => self.set_property("lavfi-complex", ""),
+        }
+    }
+}